@@ -1,7 +1,305 @@
-from typing import Any
+import argparse
+from os import PathLike
+from typing import IO, Any, Awaitable, Callable, Dict, Iterator, List, Literal, Mapping, Optional, Sequence, Tuple, Union
 
-def loads(s: str, preserve_structs: bool = False, preserve_class_names: bool = False, print_errors: bool = True) -> Any: ...
+class Record:
+    __name__: str
+    def __init__(self, name: str, /, **fields: Any) -> None: ...
+    def __getattr__(self, name: str) -> Any: ...
+    def __setattr__(self, name: str, value: Any) -> None: ...
+    def __delattr__(self, name: str) -> None: ...
+    def _asdict(self) -> Dict[str, Any]: ...
+
+class ValidationError(ValueError):
+    errors: List[Tuple[str, str]]
+
+class Char:
+    value: str
+    def __init__(self, value: str) -> None: ...
+
+class Some:
+    value: Any
+    def __init__(self, value: Any) -> None: ...
+
+class Unit:
+    name: str
+    def __init__(self, name: str) -> None: ...
+
+class LiteralInt(int):
+    source: str
+
+class LiteralFloat(float):
+    source: str
+
+def loads(
+    s: Union[str, bytes, bytearray],
+    preserve_structs: bool = False,
+    preserve_class_names: bool = False,
+    mutable_structs: bool = False,
+    tag_key: str = "!__name__",
+    print_errors: bool = True,
+    include_paths: Optional[Sequence[Union[str, PathLike]]] = None,
+    include_resolver: Optional[Callable[[str], str]] = None,
+    allowed_urls: Optional[Sequence[str]] = None,
+    url_fetcher: Optional[Callable[[str], str]] = None,
+    allow_includes: bool = True,
+    interpolate_env: bool = False,
+    interpolate_refs: bool = False,
+    comments: Optional[Dict[str, str]] = None,
+    migrate: bool = False,
+    validators: Optional[Mapping[str, Callable[[Any], Any]]] = None,
+    frozen: bool = False,
+    seq_type: Literal["list", "tuple"] = "list",
+    intern_strings: bool = False,
+    numpy_arrays: bool = False,
+    non_finite: Literal["float", "error", "none"] = "float",
+    use_decimal: bool = False,
+    preserve_numbers: bool = False,
+    preserve_chars: bool = False,
+    preserve_options: bool = False,
+    unwrap_newtypes: bool = False,
+    preserve_units: bool = False,
+    enum_repr: Literal["untagged", "external", "adjacent"] = "untagged",
+    content_key: str = "content",
+) -> Any: ...
 def load(
-    path: str, preserve_structs: bool = False, preserve_class_names: bool = False, print_errors: bool = True
+    path: Union[str, PathLike, IO[str], IO[bytes]],
+    preserve_structs: bool = False,
+    preserve_class_names: bool = False,
+    mutable_structs: bool = False,
+    tag_key: str = "!__name__",
+    print_errors: bool = True,
+    include_paths: Optional[Sequence[Union[str, PathLike]]] = None,
+    include_resolver: Optional[Callable[[str], str]] = None,
+    allowed_urls: Optional[Sequence[str]] = None,
+    url_fetcher: Optional[Callable[[str], str]] = None,
+    allow_includes: bool = True,
+    interpolate_env: bool = False,
+    interpolate_refs: bool = False,
+    comments: Optional[Dict[str, str]] = None,
+    cache: bool = False,
+    lazy: bool = False,
+    mmap: bool = False,
+    progress: Optional[Callable[[int], Any]] = None,
+    profile: Optional[str] = None,
+    migrate: bool = False,
+    validators: Optional[Mapping[str, Callable[[Any], Any]]] = None,
+    frozen: bool = False,
+    seq_type: Literal["list", "tuple"] = "list",
+    intern_strings: bool = False,
+    numpy_arrays: bool = False,
+    non_finite: Literal["float", "error", "none"] = "float",
+    use_decimal: bool = False,
+    preserve_numbers: bool = False,
+    preserve_chars: bool = False,
+    preserve_options: bool = False,
+    unwrap_newtypes: bool = False,
+    preserve_units: bool = False,
+    enum_repr: Literal["untagged", "external", "adjacent"] = "untagged",
+    content_key: str = "content",
+) -> Any: ...
+def to_string(
+    value: Any,
+    comments: Optional[Mapping[str, str]] = None,
+    field_docs: bool = False,
+    redact: Optional[Union[Sequence[str], Callable[[str, Any], bool]]] = None,
+    tag_key: str = "!__name__",
+    allow_nan: bool = True,
+    float_format: Literal["shortest", "fixed"] = "shortest",
+    float_precision: Optional[int] = None,
+    float_trailing_zero: bool = True,
+    float_exponent_threshold: Optional[int] = None,
+    int_formats: Optional[Mapping[str, Literal["hex", "binary", "octal"]]] = None,
+    int_separators: bool = False,
+    implicit_some: bool = False,
+    extensions: Optional[Sequence[str]] = None,
+    unwrap_newtypes: bool = False,
+    enum_repr: Literal["untagged", "external", "adjacent"] = "untagged",
+    content_key: str = "content",
+    trailing_commas: bool = True,
+    max_width: Optional[int] = None,
+    align_fields: bool = False,
+    indent: str = "    ",
+) -> str: ...
+def dump(
+    value: Any,
+    path: Union[str, PathLike, IO[str]],
+    comments: Optional[Mapping[str, str]] = None,
+    field_docs: bool = False,
+    atomic: bool = False,
+    redact: Optional[Union[Sequence[str], Callable[[str, Any], bool]]] = None,
+    tag_key: str = "!__name__",
+    allow_nan: bool = True,
+    float_format: Literal["shortest", "fixed"] = "shortest",
+    float_precision: Optional[int] = None,
+    float_trailing_zero: bool = True,
+    float_exponent_threshold: Optional[int] = None,
+    int_formats: Optional[Mapping[str, Literal["hex", "binary", "octal"]]] = None,
+    int_separators: bool = False,
+    implicit_some: bool = False,
+    extensions: Optional[Sequence[str]] = None,
+    unwrap_newtypes: bool = False,
+    enum_repr: Literal["untagged", "external", "adjacent"] = "untagged",
+    content_key: str = "content",
+    trailing_commas: bool = True,
+    max_width: Optional[int] = None,
+    align_fields: bool = False,
+    indent: str = "    ",
+) -> None: ...
+def merge(base: Any, override: Any, lists: Literal["replace", "concat"] = "replace") -> Any: ...
+def apply_overrides(value: Any, overrides: Mapping[str, Any], schema: Optional[type] = None) -> Any: ...
+def diff(a: Any, b: Any) -> Dict[str, Dict[str, Any]]: ...
+def patch(value: Any, diff: Mapping[str, Mapping[str, Any]], strict: bool = False) -> Any: ...
+def canonicalize(text_or_value: Any) -> str: ...
+def fingerprint(text_or_value: Any, length: Optional[int] = None) -> str: ...
+def flatten(value: Any) -> Dict[str, Any]: ...
+def unflatten(flat: Mapping[str, Any]) -> Any: ...
+def get(
+    file: Optional[Union[str, PathLike]] = None,
+    path: Optional[str] = None,
+    default: Any = None,
+    *,
+    text: Optional[str] = None,
 ) -> Any: ...
-def to_string(obj: Any) -> str: ...
+def transform(value: Any, callback: Callable[[str, Any, Optional[str]], Any]) -> Any: ...
+
+class DocumentItem:
+    value: Any
+    def __getitem__(self, key: Any) -> Any: ...
+    def __setitem__(self, key: Any, value: Any) -> None: ...
+    def __delitem__(self, key: Any) -> None: ...
+    def __contains__(self, key: Any) -> bool: ...
+    def __len__(self) -> int: ...
+    def keys(self) -> List[Any]: ...
+    def append(self, value: Any) -> None: ...
+
+class Document(DocumentItem):
+    path: Optional[str]
+    extensions: List[str]
+    def to_string(self) -> str: ...
+    def save(self, path: Optional[Union[str, PathLike]] = None) -> None: ...
+    def __enter__(self) -> "Document": ...
+    def __exit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> bool: ...
+
+def extensions(
+    path: Optional[Union[str, PathLike]] = None, *, text: Optional[str] = None
+) -> List[str]: ...
+def parse_document(
+    path: Optional[Union[str, PathLike]] = None, *, text: Optional[str] = None
+) -> Document: ...
+def edit(path: Union[str, PathLike], updates: Mapping[str, Any]) -> None: ...
+def open(path: Union[str, PathLike]) -> Document: ...
+def main(argv: Optional[Sequence[str]] = None) -> int: ...
+def to_json(value_or_path: Any, indent: Optional[int] = None) -> str: ...
+def from_json(text: str) -> Any: ...
+# Only available when built with the `yaml` feature.
+def to_yaml(value_or_path: Any) -> str: ...
+def from_yaml(text: str) -> Any: ...
+def to_toml(value_or_path: Any) -> str: ...
+def from_toml(text: str) -> Any: ...
+def to_msgpack(value_or_path: Any) -> bytes: ...
+def from_msgpack(data: bytes) -> Any: ...
+def compile(path: Union[str, PathLike], **kwargs: Any) -> bytes: ...
+def load_compiled(data: bytes) -> Any: ...
+def clear_cache() -> None: ...
+def load_many(
+    paths: Sequence[Union[str, PathLike]],
+    preserve_structs: bool = False,
+    preserve_class_names: bool = False,
+    print_errors: bool = True,
+    threads: Optional[int] = None,
+) -> List[Any]: ...
+
+class SeqIterator:
+    def __iter__(self) -> "SeqIterator": ...
+    def __next__(self) -> Any: ...
+
+def iter_load(
+    path: Union[str, PathLike],
+    preserve_structs: bool = False,
+    preserve_class_names: bool = False,
+    progress: Optional[Callable[[int], Any]] = None,
+    seq_type: Literal["list", "tuple"] = "list",
+    intern_strings: bool = False,
+    numpy_arrays: bool = False,
+) -> SeqIterator: ...
+def load_columns(
+    path: Union[str, PathLike],
+    numpy_arrays: bool = False,
+    print_errors: bool = True,
+) -> Dict[str, Any]: ...
+def to_arrow(path_or_value: Any) -> Any: ...
+
+class EventIterator:
+    def __iter__(self) -> "EventIterator": ...
+    def __next__(self) -> Tuple[str, Any]: ...
+
+def events(
+    path: Optional[Union[str, PathLike]] = None, *, text: Optional[str] = None
+) -> EventIterator: ...
+
+class LazyValue:
+    name: Optional[str]
+    def __getitem__(self, key: Any) -> Any: ...
+    def __getattr__(self, name: str) -> Any: ...
+    def __len__(self) -> int: ...
+    def __contains__(self, key: Any) -> bool: ...
+    def __iter__(self) -> Iterator[Any]: ...
+    def keys(self) -> List[Any]: ...
+    def materialize(self) -> Any: ...
+
+def load_async(path: Union[str, PathLike], **kwargs: Any) -> Awaitable[Any]: ...
+def register_opener(scheme: str, opener: Optional[Callable[[str], Any]]) -> None: ...
+def append_record(path: Union[str, PathLike], value: Any) -> None: ...
+
+class RecordIterator:
+    def __iter__(self) -> "RecordIterator": ...
+    def __next__(self) -> Any: ...
+
+def iter_records(
+    path: Union[str, PathLike],
+    preserve_structs: bool = False,
+    preserve_class_names: bool = False,
+    seq_type: Literal["list", "tuple"] = "list",
+    intern_strings: bool = False,
+    numpy_arrays: bool = False,
+) -> RecordIterator: ...
+def load_all(
+    path: Union[str, PathLike],
+    preserve_structs: bool = False,
+    preserve_class_names: bool = False,
+) -> List[Any]: ...
+def dump_all(values: Sequence[Any], path: Union[str, PathLike]) -> None: ...
+def load_dir(
+    path: Union[str, PathLike],
+    lists: Literal["replace", "concat"] = "replace",
+    preserve_structs: bool = False,
+    preserve_class_names: bool = False,
+) -> Any: ...
+
+class Watcher:
+    def stop(self) -> None: ...
+    def __enter__(self) -> "Watcher": ...
+    def __exit__(self, exc_type: Any, exc_value: Any, traceback: Any) -> bool: ...
+
+def watch(
+    path: Union[str, PathLike],
+    callback: Callable[[Any], Any],
+    interval: float = 1.0,
+    on_error: Optional[Callable[[BaseException], Any]] = None,
+    preserve_structs: bool = False,
+    preserve_class_names: bool = False,
+) -> Watcher: ...
+def register_migration(version: int, migration: Optional[Callable[[Any], Any]]) -> None: ...
+def migrate(value: Any) -> Tuple[Any, List[int]]: ...
+def expand_sweeps(
+    path: Optional[Union[str, PathLike]] = None,
+    preserve_structs: bool = False,
+    preserve_class_names: bool = False,
+    *,
+    text: Optional[str] = None,
+) -> List[Any]: ...
+def grid(base: Any, grid: Mapping[str, Sequence[Any]]) -> List[Tuple[str, Any]]: ...
+def argparser(config_class: type, **kwargs: Any) -> argparse.ArgumentParser: ...
+def from_args(args: argparse.Namespace) -> Any: ...
+def register(name: str, class_: Optional[type]) -> None: ...
@@ -0,0 +1,139 @@
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyDict, PyTuple};
+
+/// The destination of the path of the RON file in the parsed arguments, which can't clash with a
+/// field name.
+const CONFIG: &str = "!config";
+
+/// Builds an `argparse.ArgumentParser` for a config dataclass, taking the path of a RON file and
+/// one `--dotted.path` option per field, with fields of nested dataclasses flattened into their
+/// own options. Option values are converted to the annotated type of their field as by
+/// `apply_overrides` with a schema, so `argparse` reports values that don't match it. The help of
+/// an option is the `doc` metadata of its field. `kwargs` are passed on to `ArgumentParser`. Use
+/// `from_args` to load the parsed arguments.
+#[pyfunction]
+#[pyo3(signature = (config_class, **kwargs))]
+pub fn argparser<'py>(
+    py: Python<'py>,
+    config_class: &'py PyAny,
+    kwargs: Option<&'py PyDict>,
+) -> PyResult<&'py PyAny> {
+    let argparse = PyModule::import(py, "argparse")?;
+    let parser = argparse.call_method("ArgumentParser", (), kwargs)?;
+    let help = [("metavar", "config"), ("help", "RON file with the config")];
+    parser.call_method("add_argument", (CONFIG,), Some(help.into_py_dict(py)))?;
+    add_fields(py, parser, config_class, "")?;
+    Ok(parser)
+}
+
+/// Loads the RON file of arguments parsed by a parser from `argparser`, and sets the value of each
+/// option that was given on the command line.
+#[pyfunction]
+pub fn from_args(py: Python, args: &PyAny) -> PyResult<PyObject> {
+    let args = PyModule::import(py, "builtins")?
+        .getattr("vars")?
+        .call1((args,))?
+        .downcast::<PyDict>()?;
+    let path = match args.get_item(CONFIG) {
+        Some(path) => path.extract::<std::path::PathBuf>()?,
+        None => {
+            return Err(exceptions::PyValueError::new_err(
+                "Expected arguments parsed by a parser from argparser",
+            ))
+        }
+    };
+    let mut value = crate::load_path(py, &path)?;
+    for (key, option) in args {
+        let key = key.extract::<&str>()?;
+        if key != CONFIG {
+            value = crate::path::set(py, value.as_ref(py), key, option.into())?;
+        }
+    }
+    Ok(value)
+}
+
+fn add_fields(py: Python, parser: &PyAny, class: &PyAny, prefix: &str) -> PyResult<()> {
+    let dataclasses = PyModule::import(py, "dataclasses")?;
+    if !dataclasses
+        .call_method1("is_dataclass", (class,))?
+        .extract::<bool>()?
+    {
+        return Err(exceptions::PyTypeError::new_err(format!(
+            "Expected a dataclass, found {}",
+            class.repr()?
+        )));
+    }
+    let hints = PyModule::import(py, "typing")?.call_method1("get_type_hints", (class,))?;
+    for field in dataclasses
+        .call_method1("fields", (class,))?
+        .downcast::<PyTuple>()?
+    {
+        let name = field.getattr("name")?.extract::<&str>()?;
+        let path = if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        let hint = hints.get_item(name)?;
+        if dataclasses
+            .call_method1("is_dataclass", (hint,))?
+            .extract::<bool>()?
+        {
+            add_fields(py, parser, hint, &path)?;
+            continue;
+        }
+        let coercion = Coercion {
+            hint: hint.into(),
+            path: path.clone(),
+            name: crate::schema::type_name(py, hint)?,
+        };
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("dest", &path)?;
+        kwargs.set_item("metavar", &coercion.name)?;
+        kwargs.set_item("type", Py::new(py, coercion)?)?;
+        // Options that weren't given are left out of the parsed arguments, so that the values in
+        // the RON file are kept.
+        kwargs.set_item(
+            "default",
+            PyModule::import(py, "argparse")?.getattr("SUPPRESS")?,
+        )?;
+        if let Some(doc) = field
+            .getattr("metadata")?
+            .call_method1("get", ("doc",))?
+            .extract::<Option<String>>()?
+        {
+            kwargs.set_item("help", doc)?;
+        }
+        parser.call_method("add_argument", (format!("--{}", path),), Some(kwargs))?;
+    }
+    Ok(())
+}
+
+/// Converts the values of an option of a parser from `argparser` to the type of its field.
+#[pyclass(module = "pyron")]
+struct Coercion {
+    hint: PyObject,
+    path: String,
+    name: String,
+}
+
+#[pymethods]
+impl Coercion {
+    /// Invalid values raise an `ArgumentTypeError`, whose message `argparse` reports as is.
+    fn __call__(&self, py: Python, text: &str) -> PyResult<PyObject> {
+        crate::schema::coerce(py, text, self.hint.as_ref(py), &self.path).map_err(|e| {
+            match PyModule::import(py, "argparse")
+                .and_then(|argparse| argparse.getattr("ArgumentTypeError"))
+                .and_then(|error| error.call1((e.value(py).to_string(),)))
+            {
+                Ok(error) => PyErr::from_value(error),
+                Err(e) => e,
+            }
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        self.name.clone()
+    }
+}
@@ -0,0 +1,57 @@
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Loads a RON file on a background thread, passing `kwargs` on to `load`, and returns an asyncio
+/// future for the value. The file is read and parsed without holding the GIL, so the event loop
+/// keeps running in the meantime. Must be called from a running event loop.
+#[pyfunction]
+#[pyo3(signature = (path, **kwargs))]
+pub fn load_async(py: Python, path: PyObject, kwargs: Option<Py<PyDict>>) -> PyResult<PyObject> {
+    let event_loop: PyObject = PyModule::import(py, "asyncio")?
+        .call_method0("get_running_loop")?
+        .into();
+    let future: PyObject = event_loop.call_method0(py, "create_future")?;
+    let completion_future = future.clone_ref(py);
+    std::thread::spawn(move || {
+        Python::with_gil(|py| {
+            let result = PyModule::import(py, "pyron")
+                .and_then(|pyron| pyron.getattr("load"))
+                .and_then(|load| {
+                    load.call((path,), kwargs.as_ref().map(|kwargs| kwargs.as_ref(py)))
+                })
+                .map(PyObject::from);
+            let completion = Completion {
+                future: completion_future,
+                result: Some(result),
+            };
+            // If the event loop was closed in the meantime, there is no one left to notify.
+            let _ = event_loop.call_method1(py, "call_soon_threadsafe", (completion,));
+        })
+    });
+    Ok(future)
+}
+
+/// Sets the result of a `load_async` future, called on the thread of its event loop.
+#[pyclass(module = "pyron")]
+struct Completion {
+    future: PyObject,
+    result: Option<PyResult<PyObject>>,
+}
+
+#[pymethods]
+impl Completion {
+    fn __call__(&mut self, py: Python) -> PyResult<()> {
+        // The future is done if it was cancelled while loading.
+        if self.future.call_method0(py, "done")?.extract::<bool>(py)? {
+            return Ok(());
+        }
+        match self.result.take() {
+            Some(Ok(value)) => self.future.call_method1(py, "set_result", (value,))?,
+            Some(Err(e)) => self
+                .future
+                .call_method1(py, "set_exception", (e.value(py),))?,
+            None => return Ok(()),
+        };
+        Ok(())
+    }
+}
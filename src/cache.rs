@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use pyo3::prelude::*;
+
+/// Loaded values by canonical path and load options.
+static CACHE: Mutex<Option<HashMap<(PathBuf, String), Entry>>> = Mutex::new(None);
+
+struct Entry {
+    /// Every file the value was loaded from (the file itself and its includes) with the
+    /// modification time it had when it was read.
+    files: Vec<(PathBuf, SystemTime)>,
+    /// The value encoded with `msgpack::encode`, so that each cache hit decodes into new objects
+    /// that callers can modify freely.
+    data: Vec<u8>,
+}
+
+/// Returns the cached value for a file loaded with `options`, unless the file or any of its
+/// includes changed since it was cached. Strings are interned if `intern_strings` is set, as they
+/// would be when loading the file again.
+pub fn get(
+    py: Python,
+    path: &Path,
+    options: &str,
+    intern_strings: bool,
+) -> PyResult<Option<PyObject>> {
+    // The lock is released before converting the value, since creating Python objects can run
+    // Python code, which can switch to another thread that uses the cache.
+    let data = {
+        let cache = CACHE.lock().unwrap();
+        let entry = match cache
+            .as_ref()
+            .and_then(|cache| cache.get(&(path.to_path_buf(), options.to_string())))
+        {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let fresh = entry
+            .files
+            .iter()
+            .all(|(file, mtime)| modified(file).map_or(false, |m| m == *mtime));
+        if !fresh {
+            return Ok(None);
+        }
+        entry.data.clone()
+    };
+    let value = crate::msgpack::decode(&data)?;
+    crate::msgpack::to_py(py, &value, intern_strings).map(Some)
+}
+
+/// Caches a value loaded from `files`. Values that were loaded from sources other than files
+/// (`files` is `None`) aren't cached, and neither are values that MessagePack can't encode, which
+/// are loaded again each time instead of failing the load.
+pub fn insert(
+    py: Python,
+    path: PathBuf,
+    options: String,
+    files: Option<&[PathBuf]>,
+    value: &PyAny,
+) {
+    let files = match files {
+        Some(files) => files,
+        None => return,
+    };
+    let mut mtimes = vec![];
+    for file in files {
+        match modified(file) {
+            Some(mtime) => mtimes.push((file.clone(), mtime)),
+            None => return,
+        }
+    }
+    let data = match crate::msgpack::to_value(py, value) {
+        Ok(data) => crate::msgpack::encode(&data),
+        Err(_) => return,
+    };
+    CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(
+            (path, options),
+            Entry {
+                files: mtimes,
+                data,
+            },
+        );
+}
+
+/// Empties the cache used by `load(..., cache=True)`.
+#[pyfunction]
+pub fn clear_cache() {
+    *CACHE.lock().unwrap() = None;
+}
+
+pub fn modified(file: &Path) -> Option<SystemTime> {
+    std::fs::metadata(file).and_then(|m| m.modified()).ok()
+}
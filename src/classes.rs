@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyTuple};
+use pyo3::wrap_pyfunction;
+
+/// Classes registered with `register`, by struct name.
+static CLASSES: Mutex<Option<HashMap<String, PyObject>>> = Mutex::new(None);
+
+/// The namedtuple classes created for named structs, by struct name and field names.
+static NAMEDTUPLES: Mutex<Option<HashMap<(String, Vec<String>), PyObject>>> = Mutex::new(None);
+
+/// Registers `class` for structs named `name`, so that `load` with `preserve_structs` constructs
+/// it instead of a namedtuple: with the fields as keyword arguments for structs like
+/// `OptimizerConfig(lr: 0.1)`, and with the elements as positional arguments for tuple structs
+/// like `Point(1, 2)`. With `class_=None`, the name is unregistered.
+#[pyfunction]
+#[pyo3(signature = (name, class_))]
+pub fn register(name: &str, class_: Option<PyObject>) {
+    let mut classes = CLASSES.lock().unwrap();
+    let classes = classes.get_or_insert_with(HashMap::new);
+    match class_ {
+        Some(class) => classes.insert(name.to_string(), class),
+        None => classes.remove(name),
+    };
+}
+
+/// Returns the class registered for structs named `name`, if any.
+pub fn get(py: Python, name: &str) -> Option<PyObject> {
+    CLASSES
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|classes| classes.get(name))
+        .map(|class| class.clone_ref(py))
+}
+
+/// Whether any classes are registered.
+pub fn any() -> bool {
+    CLASSES
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map_or(false, |classes| !classes.is_empty())
+}
+
+/// Returns the namedtuple class for structs named `name` with the given fields, creating it the
+/// first time, so that all structs with the same name and fields share one class.
+pub fn namedtuple(py: Python, name: &str, fields: Vec<String>) -> PyResult<PyObject> {
+    let key = (name.to_string(), fields);
+    if let Some(class) = NAMEDTUPLES
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|namedtuples| namedtuples.get(&key))
+    {
+        return Ok(class.clone_ref(py));
+    }
+    // Namedtuples don't allow field names starting with an underscore, like the `_0`, `_1`, ... of
+    // tuple structs. `rename` replaces invalid names with these positional names, so they are kept
+    // and other invalid names are reported instead of being renamed.
+    let kwargs = [("rename", true)].into_py_dict(py);
+    let class: PyObject = PyModule::import(py, "collections")?
+        .call_method("namedtuple", (name, &key.1), Some(kwargs))?
+        .into();
+    let renamed = class.getattr(py, "_fields")?.extract::<Vec<String>>(py)?;
+    for (field, renamed) in key.1.iter().zip(renamed) {
+        if *field != renamed {
+            return Err(exceptions::PyValueError::new_err(format!(
+                "Field `{}` of struct {} isn't a valid namedtuple field name, load it with \
+                 mutable_structs instead",
+                field, name
+            )));
+        }
+    }
+    // The class can't be imported by name like the classes `pickle` normally refers to, so its
+    // instances are pickled as a call to `_make_struct` instead.
+    PyModule::import(py, "copyreg")?
+        .call_method1("pickle", (&class, wrap_pyfunction!(reduce_struct, py)?))?;
+    NAMEDTUPLES
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(key, class.clone_ref(py));
+    Ok(class)
+}
+
+/// Recreates a struct pickled by `reduce_struct`.
+#[pyfunction]
+#[pyo3(name = "_make_struct")]
+pub fn make_struct(
+    py: Python,
+    name: &str,
+    fields: Vec<String>,
+    values: &PyTuple,
+) -> PyResult<PyObject> {
+    namedtuple(py, name, fields)?.call1(py, values)
+}
+
+/// Reduces a namedtuple created by `namedtuple` to a call to `_make_struct` for `pickle`.
+#[pyfunction]
+fn reduce_struct<'py>(
+    py: Python<'py>,
+    value: &'py PyTuple,
+) -> PyResult<(&'py PyAny, (&'py str, Vec<String>, &'py PyTuple))> {
+    let make_struct = PyModule::import(py, "pyron")?.getattr("_make_struct")?;
+    let fields = value.getattr("_fields")?.extract::<Vec<String>>()?;
+    let values = PyTuple::new(py, value.iter());
+    Ok((make_struct, (value.get_type().name()?, fields, values)))
+}
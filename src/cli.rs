@@ -0,0 +1,306 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyDict};
+
+use crate::format::inline;
+use crate::merge::{merge_values, ListMerge};
+
+const USAGE: &str = "\
+usage: pyron <command> [<args>]
+
+commands:
+  check <file>... [--schema <module:Class>]
+      Parse files, resolving includes, and report all errors. With --schema, also check that
+      each file matches a dataclass.
+  convert <file> --to <ron|json> [--from <ron|json>] [--output <file>]
+      Convert between RON and JSON. The input format defaults to the file extension, and the
+      output is written to stdout unless --output is given.
+  get <file> <path> [--raw]
+      Print the value at a dotted path like training.optimizer.lr. With --raw, strings are
+      printed without quotes.
+  diff <a> <b>
+      Compare two files structurally and print the added (+), removed (-) and changed (~)
+      paths. Exits with status 1 if the files differ.
+  merge <file>... [--lists <replace|concat>] [--output <file>]
+      Deep-merge files in order, later files overriding earlier ones, and write the result to
+      stdout unless --output is given.
+";
+
+/// Entry point of the `pyron` command line tool. Runs the command given by `argv`, which defaults
+/// to `sys.argv[1:]`, and returns the exit status.
+#[pyfunction]
+#[pyo3(signature = (argv = None))]
+pub fn main(py: Python, argv: Option<Vec<String>>) -> PyResult<i32> {
+    let argv = match argv {
+        Some(argv) => argv,
+        None => {
+            let argv = py
+                .import("sys")?
+                .getattr("argv")?
+                .extract::<Vec<String>>()?;
+            argv.into_iter().skip(1).collect()
+        }
+    };
+    let (command, args) = match argv.split_first() {
+        Some((command, args)) => (command.as_str(), args),
+        None => return usage_error(py, "missing command"),
+    };
+    let result = match command {
+        "check" => check(py, args),
+        "convert" => convert(py, args),
+        "get" => get(py, args),
+        "diff" => diff(py, args),
+        "merge" => merge(py, args),
+        "-h" | "--help" => {
+            write(py, "stdout", USAGE)?;
+            return Ok(0);
+        }
+        _ => return usage_error(py, &format!("unknown command `{}`", command)),
+    };
+    match result {
+        Ok(status) => Ok(status),
+        Err(e) => {
+            write(py, "stderr", &format!("pyron: {}\n", e.value(py)))?;
+            Ok(1)
+        }
+    }
+}
+
+/// Parsed command line arguments of a subcommand.
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: HashSet<String>,
+}
+
+impl Args {
+    /// Parses `args`, where `options` are the options that take a value (`--name value` or
+    /// `--name=value`) and `flags` the options that don't.
+    fn parse(args: &[String], options: &[&str], flags: &[&str]) -> Result<Args, String> {
+        let mut parsed = Args {
+            positional: vec![],
+            options: HashMap::new(),
+            flags: HashSet::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                parsed.positional.push(arg.clone());
+                continue;
+            }
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            if options.contains(&name) {
+                let value = match value.or_else(|| args.next().cloned()) {
+                    Some(value) => value,
+                    None => return Err(format!("{} requires a value", name)),
+                };
+                parsed.options.insert(name.to_string(), value);
+            } else if flags.contains(&name) && value.is_none() {
+                parsed.flags.insert(name.to_string());
+            } else {
+                return Err(format!("unknown option `{}`", arg));
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+}
+
+/// Writes to `sys.stdout` or `sys.stderr`, so that output interleaves correctly with output from
+/// Python.
+fn write(py: Python, stream: &str, text: &str) -> PyResult<()> {
+    py.import("sys")?
+        .getattr(stream)?
+        .call_method1("write", (text,))?;
+    Ok(())
+}
+
+fn usage_error(py: Python, message: &str) -> PyResult<i32> {
+    write(py, "stderr", &format!("pyron: {}\n\n{}", message, USAGE))?;
+    Ok(2)
+}
+
+/// `pyron check <file>... [--schema <module:Class>]`
+fn check(py: Python, args: &[String]) -> PyResult<i32> {
+    let args = match Args::parse(args, &["--schema"], &[]) {
+        Ok(args) => args,
+        Err(message) => return usage_error(py, &message),
+    };
+    if args.positional.is_empty() {
+        return usage_error(py, "check requires at least one file");
+    }
+    let schema = match args.option("--schema") {
+        Some(schema) => Some(crate::schema::import(py, schema)?),
+        None => None,
+    };
+    let mut status = 0;
+    for file in &args.positional {
+        let mut errors = vec![];
+        match crate::load_path(py, Path::new(file)) {
+            Ok(value) => {
+                if let Some(schema) = schema {
+                    crate::schema::validate(py, value.as_ref(py), schema, "", &mut errors)?;
+                }
+            }
+            Err(e) => errors.push(e.value(py).to_string()),
+        }
+        for error in &errors {
+            write(py, "stderr", &format!("{}: {}\n", file, error))?;
+        }
+        if !errors.is_empty() {
+            status = 1;
+        }
+    }
+    Ok(status)
+}
+
+/// `pyron convert <file> --to <ron|json> [--from <ron|json>] [--output <file>]`
+fn convert(py: Python, args: &[String]) -> PyResult<i32> {
+    let args = match Args::parse(args, &["--to", "--from", "--output"], &[]) {
+        Ok(args) => args,
+        Err(message) => return usage_error(py, &message),
+    };
+    let file = match &args.positional[..] {
+        [file] => file,
+        _ => return usage_error(py, "convert requires exactly one file"),
+    };
+    let path = Path::new(file);
+    let from = match args.option("--from") {
+        Some(from) => from,
+        None => match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => "json",
+            _ => "ron",
+        },
+    };
+    let to = match args.option("--to") {
+        Some(to) => to,
+        None => return usage_error(py, "convert requires --to"),
+    };
+    let json = py.import("json")?;
+    let value = match from {
+        "ron" => crate::load_path(py, path)?,
+        "json" => json
+            .call_method1("loads", (std::fs::read_to_string(path)?,))?
+            .into(),
+        _ => return usage_error(py, &format!("unsupported input format `{}`", from)),
+    };
+    let mut output = match to {
+        "ron" => crate::serialize(py, value.as_ref(py))?,
+        "json" => json
+            .call_method("dumps", (value,), Some([("indent", 4)].into_py_dict(py)))?
+            .extract()?,
+        _ => return usage_error(py, &format!("unsupported output format `{}`", to)),
+    };
+    output.push('\n');
+    match args.option("--output") {
+        Some(output_path) => std::fs::write(output_path, output)?,
+        None => write(py, "stdout", &output)?,
+    }
+    Ok(0)
+}
+
+/// `pyron get <file> <path> [--raw]`
+fn get(py: Python, args: &[String]) -> PyResult<i32> {
+    let args = match Args::parse(args, &[], &["--raw"]) {
+        Ok(args) => args,
+        Err(message) => return usage_error(py, &message),
+    };
+    let (file, path) = match &args.positional[..] {
+        [file, path] => (file, path),
+        _ => return usage_error(py, "get requires a file and a path"),
+    };
+    let file = Some(PathBuf::from(file));
+    let missing = py.import("builtins")?.getattr("object")?.call0()?;
+    let value = crate::query::get(py, file, Some(path.as_str()), Some(missing.into()), None)?;
+    let value = value.as_ref(py);
+    if value.is(missing) {
+        write(py, "stderr", &format!("pyron: {} not found\n", path))?;
+        return Ok(1);
+    }
+    let text = match value.extract::<String>() {
+        Ok(text) if args.flag("--raw") => text,
+        _ => crate::serialize(py, value)?,
+    };
+    write(py, "stdout", &format!("{}\n", text))?;
+    Ok(0)
+}
+
+/// `pyron diff <a> <b>`
+fn diff(py: Python, args: &[String]) -> PyResult<i32> {
+    let args = match Args::parse(args, &[], &[]) {
+        Ok(args) => args,
+        Err(message) => return usage_error(py, &message),
+    };
+    let (a, b) = match &args.positional[..] {
+        [a, b] => (a, b),
+        _ => return usage_error(py, "diff requires two files"),
+    };
+    let a = load_named(py, Path::new(a))?;
+    let b = load_named(py, Path::new(b))?;
+    let diff = crate::diff::diff(py, a.as_ref(py), b.as_ref(py))?;
+    let diff = diff.as_ref(py);
+    let mut lines = vec![];
+    for (section, sign) in [("removed", '-'), ("added", '+'), ("changed", '~')] {
+        for (path, value) in diff.get_item(section)?.downcast::<PyDict>()? {
+            let value = if sign == '~' {
+                let (old, new): (&PyAny, &PyAny) = value.extract()?;
+                format!("{} -> {}", inline(py, old)?, inline(py, new)?)
+            } else {
+                inline(py, value)?
+            };
+            lines.push((path.extract::<String>()?, sign, value));
+        }
+    }
+    lines.sort_by(|a, b| a.0.cmp(&b.0));
+    for (path, sign, value) in &lines {
+        write(py, "stdout", &format!("{} {}: {}\n", sign, path, value))?;
+    }
+    Ok(if lines.is_empty() { 0 } else { 1 })
+}
+
+/// Loads a RON file like `load` with `preserve_class_names`, so that struct names are compared
+/// and written back.
+fn load_named(py: Python, path: &Path) -> PyResult<PyObject> {
+    let value = crate::parse_file(py, path, true)?;
+    let mut converter = crate::Converter::new(py);
+    converter.preserve_class_names = true;
+    converter.includes.enter_file(path)?;
+    converter.convert_document(&value)
+}
+
+/// `pyron merge <file>... [--lists <replace|concat>] [--output <file>]`
+fn merge(py: Python, args: &[String]) -> PyResult<i32> {
+    let args = match Args::parse(args, &["--lists", "--output"], &[]) {
+        Ok(args) => args,
+        Err(message) => return usage_error(py, &message),
+    };
+    let lists = ListMerge::parse(args.option("--lists").unwrap_or("replace"))?;
+    let (first, rest) = match args.positional.split_first() {
+        Some(split) => split,
+        None => return usage_error(py, "merge requires at least one file"),
+    };
+    let mut result = load_named(py, Path::new(first))?;
+    for file in rest {
+        let value = load_named(py, Path::new(file))?;
+        result = merge_values(py, result.as_ref(py), value.as_ref(py), lists)?;
+    }
+    let mut output = crate::serialize(py, result.as_ref(py))?;
+    output.push('\n');
+    match args.option("--output") {
+        Some(output_path) => std::fs::write(output_path, output)?,
+        None => write(py, "stdout", &output)?,
+    }
+    Ok(0)
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyByteArray, PyDict, PyList, PyString};
+use ron_parser::{Number, Value};
+
+use crate::Converter;
+
+/// Loads a RON file containing a top-level sequence of structs and returns its fields as columns:
+/// a dict mapping each field name to the list of its values in every struct, in the order in
+/// which the fields first appear. Structs without a field have `None` in its column. This is the
+/// layout expected by `pandas.DataFrame`, and avoids creating a dict per row. With
+/// `numpy_arrays`, columns of only integers or only floats are numpy arrays, as by `load`.
+#[pyfunction]
+#[pyo3(signature = (path, numpy_arrays = false, print_errors = true))]
+pub fn load_columns(
+    py: Python,
+    path: PathBuf,
+    numpy_arrays: bool,
+    print_errors: bool,
+) -> PyResult<PyObject> {
+    let document = crate::parse_for_load(py, &path, print_errors, Default::default(), false, None)?;
+    let columns = split(&document, &path.display().to_string())?;
+    let mut converter = Converter::new(py);
+    converter.print_errors = print_errors;
+    converter.includes.enter_file(&path)?;
+    let result = PyDict::new(py);
+    for (name, column) in columns {
+        let values = column.iter().copied().collect::<Option<Vec<_>>>();
+        match values {
+            Some(values) if numpy_arrays && crate::numeric_seq(values.iter().copied()) => {
+                result.set_item(name, crate::numpy_array(py, values.into_iter())?)?;
+            }
+            _ => {
+                let mut list = vec![];
+                for value in column {
+                    list.push(match value {
+                        Some(value) => converter.convert(value)?,
+                        None => py.None(),
+                    });
+                }
+                result.set_item(name, PyList::new(py, list))?;
+            }
+        }
+    }
+    Ok(result.into())
+}
+
+/// Builds a `pyarrow.Table` with a column per field from a sequence of structs, given as a value
+/// or as the path (`os.PathLike`) of a RON file. Columns are built as by `load_columns`, and
+/// columns of only integers or only floats, and missing values, are written to Arrow buffers
+/// directly instead of going through Python objects. Requires `pyarrow`.
+#[pyfunction]
+pub fn to_arrow(py: Python, path_or_value: &PyAny) -> PyResult<PyObject> {
+    let pa = PyModule::import(py, "pyarrow")?;
+    let (document, source) =
+        if path_or_value.downcast::<PyString>().is_err() && path_or_value.hasattr("__fspath__")? {
+            let path = path_or_value.extract::<PathBuf>()?;
+            let document = crate::parse_file(py, &path, true)?;
+            (document, Some(path))
+        } else {
+            let text = crate::serialize(py, path_or_value)?;
+            (crate::parse_str(py, &text, "<value>", true)?, None)
+        };
+    let name = match &source {
+        Some(path) => path.display().to_string(),
+        None => "value".to_string(),
+    };
+    let columns = split(&document, &name)?;
+
+    let mut converter = Converter::new(py);
+    if let Some(path) = &source {
+        converter.includes.enter_file(path)?;
+    }
+    let arrays = PyDict::new(py);
+    for (name, column) in columns {
+        let array = match numeric_buffers(&column) {
+            Some((dtype, validity, data)) => {
+                let null_count = column.iter().filter(|value| value.is_none()).count();
+                let validity = match validity {
+                    Some(validity) => {
+                        pa.call_method1("py_buffer", (PyByteArray::new(py, &validity),))?
+                    }
+                    None => py.None().into_ref(py),
+                };
+                let data = pa.call_method1("py_buffer", (PyByteArray::new(py, &data),))?;
+                pa.getattr("Array")?.call_method1(
+                    "from_buffers",
+                    (
+                        pa.call_method0(dtype)?,
+                        column.len(),
+                        PyList::new(py, [validity, data]),
+                        null_count,
+                    ),
+                )?
+            }
+            None => {
+                let mut list = vec![];
+                for value in column {
+                    list.push(match value {
+                        Some(value) => converter.convert(value)?,
+                        None => py.None(),
+                    });
+                }
+                pa.call_method1("array", (PyList::new(py, list),))?
+            }
+        };
+        arrays.set_item(name, array)?;
+    }
+    Ok(pa.call_method1("table", (arrays,))?.into())
+}
+
+/// Splits a sequence of structs into the values of each field, in the order in which the fields
+/// first appear, with `None` for structs without the field.
+fn split<'a>(document: &'a Value, source: &str) -> PyResult<Vec<(String, Vec<Option<&'a Value>>)>> {
+    let rows = match document {
+        Value::Seq(rows) => rows,
+        _ => {
+            return Err(exceptions::PyValueError::new_err(format!(
+                "{}: expected a sequence of structs",
+                source
+            )))
+        }
+    };
+    let not_a_struct =
+        |i| exceptions::PyValueError::new_err(format!("{}: element {} is not a struct", source, i));
+    let mut indices = HashMap::new();
+    let mut columns: Vec<(String, Vec<Option<&Value>>)> = vec![];
+    for (i, row) in rows.iter().enumerate() {
+        let fields = match row {
+            Value::Struct(fields) => fields
+                .iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect::<Vec<_>>(),
+            // Dicts are written as maps, so that `to_arrow` accepts a list of dicts.
+            Value::Map(map) => {
+                let mut fields = vec![];
+                for (key, value) in map.iter() {
+                    match key {
+                        Value::String(name) => fields.push((name.to_string(), value)),
+                        _ => return Err(not_a_struct(i)),
+                    }
+                }
+                fields
+            }
+            _ => return Err(not_a_struct(i)),
+        };
+        for (name, value) in fields {
+            let column = *indices.entry(name.clone()).or_insert_with(|| {
+                columns.push((name, vec![]));
+                columns.len() - 1
+            });
+            // Rows without this field so far get `None`.
+            columns[column].1.resize(i, None);
+            columns[column].1.push(Some(value));
+        }
+    }
+    for (_, column) in &mut columns {
+        column.resize(rows.len(), None);
+    }
+    Ok(columns)
+}
+
+/// Returns the name of the `pyarrow` type, the validity bitmap (if any value is missing) and the
+/// data buffer of a column of only integers or only floats.
+fn numeric_buffers(column: &[Option<&Value>]) -> Option<(&'static str, Option<Vec<u8>>, Vec<u8>)> {
+    let present = column.iter().flatten().copied();
+    if !crate::numeric_seq(present.clone()) {
+        return None;
+    }
+    let dtype = match present.clone().next() {
+        Some(Value::Number(Number::Float(_))) => "float64",
+        _ => "int64",
+    };
+    let mut validity = vec![0u8; (column.len() + 7) / 8];
+    let mut data = Vec::with_capacity(column.len() * 8);
+    for (i, value) in column.iter().enumerate() {
+        match value {
+            Some(Value::Number(Number::Integer(n))) => data.extend_from_slice(&n.to_ne_bytes()),
+            Some(Value::Number(Number::Float(f))) => data.extend_from_slice(&f.get().to_ne_bytes()),
+            _ => {
+                data.extend_from_slice(&[0; 8]);
+                continue;
+            }
+        }
+        validity[i / 8] |= 1 << (i % 8);
+    }
+    let complete = column.iter().all(|value| value.is_some());
+    Some((dtype, if complete { None } else { Some(validity) }, data))
+}
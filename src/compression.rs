@@ -0,0 +1,73 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+
+/// Compression of files with a `.gz` or `.zst` extension, which are decompressed when read and
+/// compressed when written.
+enum Format {
+    Gzip,
+    Zstd,
+}
+
+fn format(path: &Path) -> Option<Format> {
+    match path.extension()?.to_str()? {
+        "gz" => Some(Format::Gzip),
+        "zst" => Some(Format::Zstd),
+        _ => None,
+    }
+}
+
+/// Opens a file for reading, decompressing it if necessary.
+pub fn open(path: &Path) -> io::Result<Box<dyn Read + Send>> {
+    let file = File::open(path)?;
+    Ok(match format(path) {
+        // Multiple gzip members are read as one stream, so compressed files can be appended to.
+        Some(Format::Gzip) => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        Some(Format::Zstd) => Box::new(zstd::stream::read::Decoder::new(file)?),
+        None => Box::new(file),
+    })
+}
+
+/// Decodes the contents of the file at `path` to text, decompressing them if necessary.
+pub fn decode<'a>(path: &Path, bytes: &'a [u8]) -> PyResult<Cow<'a, str>> {
+    let bytes = match format(path) {
+        Some(Format::Gzip) => {
+            let mut decompressed = vec![];
+            flate2::read::MultiGzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+            Cow::Owned(decompressed)
+        }
+        Some(Format::Zstd) => Cow::Owned(zstd::stream::decode_all(bytes)?),
+        None => Cow::Borrowed(bytes),
+    };
+    let invalid = |e: std::str::Utf8Error| {
+        exceptions::PyValueError::new_err(format!("{}: {}", path.display(), e))
+    };
+    Ok(match bytes {
+        Cow::Borrowed(bytes) => Cow::Borrowed(std::str::from_utf8(bytes).map_err(invalid)?),
+        Cow::Owned(bytes) => {
+            Cow::Owned(String::from_utf8(bytes).map_err(|e| invalid(e.utf8_error()))?)
+        }
+    })
+}
+
+pub fn read_to_string(path: &Path) -> PyResult<String> {
+    let bytes = std::fs::read(path)?;
+    Ok(decode(path, &bytes)?.into_owned())
+}
+
+/// Encodes text for the file at `path`, compressing it if necessary.
+pub fn encode<'a>(path: &Path, text: &'a str) -> io::Result<Cow<'a, [u8]>> {
+    Ok(match format(path) {
+        Some(Format::Gzip) => {
+            let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+            encoder.write_all(text.as_bytes())?;
+            Cow::Owned(encoder.finish()?)
+        }
+        Some(Format::Zstd) => Cow::Owned(zstd::stream::encode_all(text.as_bytes(), 0)?),
+        None => Cow::Borrowed(text.as_bytes()),
+    })
+}
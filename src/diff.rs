@@ -0,0 +1,155 @@
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyString, PyTuple};
+
+/// Structurally compares two values (or files, if given as `os.PathLike`) and returns a dict with
+/// the dotted paths that were `added` (mapped to their new value), `removed` (mapped to their old
+/// value) and `changed` (mapped to an `(old, new)` tuple).
+#[pyfunction]
+pub fn diff(py: Python, a: &PyAny, b: &PyAny) -> PyResult<PyObject> {
+    let a = crate::load_if_path(py, a)?;
+    let b = crate::load_if_path(py, b)?;
+    let mut diff = Diff {
+        added: PyDict::new(py),
+        removed: PyDict::new(py),
+        changed: PyDict::new(py),
+    };
+    diff.compare("", a.as_ref(py), b.as_ref(py))?;
+    let result = PyDict::new(py);
+    result.set_item("added", diff.added)?;
+    result.set_item("removed", diff.removed)?;
+    result.set_item("changed", diff.changed)?;
+    Ok(result.into())
+}
+
+struct Diff<'py> {
+    added: &'py PyDict,
+    removed: &'py PyDict,
+    changed: &'py PyDict,
+}
+
+impl<'py> Diff<'py> {
+    fn compare(&mut self, path: &str, a: &PyAny, b: &PyAny) -> PyResult<()> {
+        if let (Ok(a), Ok(b)) = (a.downcast::<PyDict>(), b.downcast::<PyDict>()) {
+            if a.get_item("!__name__").map(|n| n.to_string())
+                == b.get_item("!__name__").map(|n| n.to_string())
+            {
+                return self.compare_dicts(path, a, b);
+            }
+        } else if let (Ok(a), Ok(b)) = (a.downcast::<PyList>(), b.downcast::<PyList>()) {
+            return self.compare_seqs(path, a.iter().collect(), b.iter().collect());
+        } else if let (Ok(a), Ok(b)) = (a.downcast::<PyTuple>(), b.downcast::<PyTuple>()) {
+            match (crate::is_namedtuple(a), crate::is_namedtuple(b)) {
+                (true, true) if a.get_type().name()? == b.get_type().name()? => {
+                    return self.compare_dicts(
+                        path,
+                        a.call_method0("_asdict")?.downcast::<PyDict>()?,
+                        b.call_method0("_asdict")?.downcast::<PyDict>()?,
+                    );
+                }
+                (false, false) => {
+                    return self.compare_seqs(path, a.iter().collect(), b.iter().collect())
+                }
+                _ => {}
+            }
+        }
+        if !a.eq(b)? || a.get_type().name()? != b.get_type().name()? {
+            self.changed.set_item(path, (a, b))?;
+        }
+        Ok(())
+    }
+
+    fn compare_dicts(&mut self, path: &str, a: &PyDict, b: &PyDict) -> PyResult<()> {
+        for (key, value) in a {
+            let child = join(path, key)?;
+            match b.get_item(key) {
+                Some(other) => self.compare(&child, value, other)?,
+                None => self.removed.set_item(child, value)?,
+            }
+        }
+        for (key, value) in b {
+            if !a.contains(key)? {
+                self.added.set_item(join(path, key)?, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn compare_seqs(&mut self, path: &str, a: Vec<&PyAny>, b: Vec<&PyAny>) -> PyResult<()> {
+        for i in 0..a.len().max(b.len()) {
+            let child = format!("{}[{}]", path, i);
+            match (a.get(i), b.get(i)) {
+                (Some(a), Some(b)) => self.compare(&child, a, b)?,
+                (Some(a), None) => self.removed.set_item(child, a)?,
+                (None, Some(b)) => self.added.set_item(child, b)?,
+                (None, None) => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Appends a dict key to a dotted path.
+pub fn join(path: &str, key: &PyAny) -> PyResult<String> {
+    let key = match key.downcast::<PyString>() {
+        Ok(key) => key.to_str()?.to_string(),
+        Err(_) => key.str()?.to_str()?.to_string(),
+    };
+    Ok(if path.is_empty() {
+        key
+    } else {
+        format!("{}.{}", path, key)
+    })
+}
+
+/// Applies a diff returned by `diff` to `value` (or a file, if given as `os.PathLike`) and returns
+/// the result without modifying `value`. With `strict`, the old values recorded in the diff must
+/// match the values being changed or removed.
+#[pyfunction]
+#[pyo3(signature = (value, diff, strict = false))]
+pub fn patch(py: Python, value: &PyAny, diff: &PyDict, strict: bool) -> PyResult<PyObject> {
+    let mut result = crate::load_if_path(py, value)?;
+    for (path, change) in entries(diff, "changed")? {
+        let (old, new): (&PyAny, &PyAny) = change.extract()?;
+        if strict {
+            check(result.as_ref(py), &path, old)?;
+        }
+        result = crate::path::set(py, result.as_ref(py), &path, new.into())?;
+    }
+    // Remove later sequence elements first so that earlier indices stay valid.
+    for (path, old) in entries(diff, "removed")?.into_iter().rev() {
+        if strict {
+            check(result.as_ref(py), &path, old)?;
+        }
+        result = crate::path::remove(py, result.as_ref(py), &path)?;
+    }
+    for (path, new) in entries(diff, "added")? {
+        result = crate::path::set(py, result.as_ref(py), &path, new.into())?;
+    }
+    Ok(result)
+}
+
+fn entries<'py>(diff: &'py PyDict, section: &str) -> PyResult<Vec<(String, &'py PyAny)>> {
+    match diff.get_item(section) {
+        Some(entries) => entries
+            .downcast::<PyDict>()?
+            .iter()
+            .map(|(path, value)| Ok((path.extract::<String>()?, value)))
+            .collect(),
+        None => Ok(vec![]),
+    }
+}
+
+fn check(value: &PyAny, path: &str, expected: &PyAny) -> PyResult<()> {
+    match crate::path::get(value, path)? {
+        Some(actual) if actual.eq(expected)? => Ok(()),
+        Some(actual) => Err(exceptions::PyValueError::new_err(format!(
+            "Cannot apply patch: expected {} at {} but found {}",
+            expected, path, actual
+        ))),
+        None => Err(exceptions::PyValueError::new_err(format!(
+            "Cannot apply patch: {} not found",
+            path
+        ))),
+    }
+}
@@ -0,0 +1,277 @@
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+
+use crate::lexer::{self, Token, TokenKind};
+use crate::numbers;
+use crate::Converter;
+
+/// Converts RON source to Python objects as it is parsed, without building a `ron_parser::Value`
+/// tree of the whole document first. Only plain data is handled: structs, tuples, sequences, maps,
+/// `Some` and `None`, booleans, numbers accepted by `numbers::parse`, strings with the common
+/// escapes and, with `preserve_units`, unit structs, after any `#![enable(...)]` attributes.
+/// Returns `None` for anything else, including invalid syntax, so that the caller can fall back to
+/// `ron_parser`, which also reports errors. Numbers are converted by the same `numbers::convert` in
+/// both cases.
+pub fn convert(converter: &Converter, source: &str) -> PyResult<Option<PyObject>> {
+    let mut parser = Parser {
+        converter,
+        source,
+        pos: 0,
+    };
+    let value = match parser.attributes().and_then(|_| parser.value()) {
+        Ok(value) => value,
+        Err(Error::Unsupported) => return Ok(None),
+        Err(Error::Python(e)) => return Err(e),
+    };
+    if parser.peek().is_some() {
+        return Ok(None);
+    }
+    Ok(Some(value))
+}
+
+enum Error {
+    /// Syntax that isn't handled here.
+    Unsupported,
+    /// An exception raised while constructing a value, such as by the constructor of a
+    /// registered class.
+    Python(PyErr),
+}
+
+impl From<PyErr> for Error {
+    fn from(e: PyErr) -> Error {
+        Error::Python(e)
+    }
+}
+
+struct Parser<'a, 'py> {
+    converter: &'a Converter<'py>,
+    source: &'a str,
+    /// Offset of the next token.
+    pos: usize,
+}
+
+impl<'a, 'py> Parser<'a, 'py> {
+    /// Returns the next token that isn't whitespace or a comment, without consuming it.
+    fn peek(&mut self) -> Option<Token> {
+        while self.pos < self.source.len() {
+            let (kind, end) = lexer::next_token(self.source, self.pos);
+            let token = Token {
+                kind,
+                start: self.pos,
+                end,
+            };
+            if !token.is_trivia() {
+                return Some(token);
+            }
+            self.pos = end;
+        }
+        None
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek()?;
+        self.pos = token.end;
+        Some(token)
+    }
+
+    /// Consumes the next token if it is `c`.
+    fn eat(&mut self, c: char) -> bool {
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::Punct(c) => {
+                self.pos = token.end;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(Error::Unsupported)
+        }
+    }
+
+    /// Skips `#![enable(...)]` attributes. The extensions don't change how documents are
+    /// converted without type information: values of `Option` fields and newtypes are loaded the
+    /// same way with and without the `Some(...)` or the name around them.
+    fn attributes(&mut self) -> Result<(), Error> {
+        while self.eat('#') {
+            self.expect('!')?;
+            self.expect('[')?;
+            match self.next() {
+                Some(token) if token.text(self.source) == "enable" => {}
+                _ => return Err(Error::Unsupported),
+            }
+            self.expect('(')?;
+            self.items(')', |parser| match parser.next() {
+                Some(token) if token.kind == TokenKind::Ident => Ok(()),
+                _ => Err(Error::Unsupported),
+            })?;
+            self.expect(']')?;
+        }
+        Ok(())
+    }
+
+    /// Parses comma-separated items up to `close`, with an optional trailing comma.
+    fn items(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        loop {
+            if self.eat(close) {
+                return Ok(());
+            }
+            item(self)?;
+            if self.eat(close) {
+                return Ok(());
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn value(&mut self) -> Result<PyObject, Error> {
+        let py = self.converter.py;
+        let token = self.next().ok_or(Error::Unsupported)?;
+        let text = token.text(self.source);
+        match token.kind {
+            TokenKind::String => Ok(self.converter.text(&unescape(text)?)?),
+            TokenKind::Number => number(self.converter, text, false),
+            TokenKind::Punct('-') => match self.next() {
+                Some(token) if token.kind == TokenKind::Number => {
+                    number(self.converter, token.text(self.source), true)
+                }
+                _ => Err(Error::Unsupported),
+            },
+            TokenKind::Punct('[') => {
+                let mut elements = vec![];
+                self.items(']', |parser| {
+                    elements.push(parser.value()?);
+                    Ok(())
+                })?;
+                Ok(self.converter.seq(elements))
+            }
+            TokenKind::Punct('{') => {
+                let dict = PyDict::new(py);
+                self.items('}', |parser| {
+                    let key = parser.value()?;
+                    parser.expect(':')?;
+                    let value = parser.value()?;
+                    // Leave duplicate keys to `ron_parser`.
+                    if dict.contains(&key)? {
+                        return Err(Error::Unsupported);
+                    }
+                    Ok(dict.set_item(key, value)?)
+                })?;
+                Ok(dict.into())
+            }
+            TokenKind::Punct('(') => self.parenthesized(None),
+            TokenKind::Ident => match text {
+                "true" => Ok(true.into_py(py)),
+                "false" => Ok(false.into_py(py)),
+                "None" => Ok(py.None()),
+                "Some" => {
+                    self.expect('(')?;
+                    let value = self.value()?;
+                    self.expect(')')?;
+                    Ok(self.converter.some(value)?)
+                }
+                name if self.eat('(') => self.parenthesized(Some(name)),
+                // Unit structs and enum variants. `inf` and `NaN` are left to `ron_parser`, which
+                // loads them as floats.
+                "inf" | "NaN" => Err(Error::Unsupported),
+                name if self.converter.preserve_units => Ok(self.converter.unit(name)?),
+                _ => Err(Error::Unsupported),
+            },
+            _ => Err(Error::Unsupported),
+        }
+    }
+
+    /// Parses a struct, tuple or unit after its `(`.
+    fn parenthesized(&mut self, name: Option<&str>) -> Result<PyObject, Error> {
+        let py = self.converter.py;
+        if self.is_field() {
+            let dict = PyDict::new(py);
+            self.items(')', |parser| {
+                let field = match parser.next() {
+                    Some(token) if token.kind == TokenKind::Ident => token.text(parser.source),
+                    _ => return Err(Error::Unsupported),
+                };
+                parser.expect(':')?;
+                let value = parser.value()?;
+                if dict.contains(field)? {
+                    return Err(Error::Unsupported);
+                }
+                Ok(dict.set_item(parser.converter.string(field), value)?)
+            })?;
+            return Ok(self.converter.structure(name, dict)?);
+        }
+        if self.eat(')') {
+            // `()` is the unit value, and `Name()` is left to `ron_parser`.
+            return match name {
+                None => Ok(py.None()),
+                Some(_) => Err(Error::Unsupported),
+            };
+        }
+        let mut elements = vec![];
+        self.items(')', |parser| {
+            elements.push(parser.value()?);
+            Ok(())
+        })?;
+        match name {
+            Some(name) => Ok(self.converter.tuple(Some(name), elements)?),
+            // `(x)` is left to `ron_parser`, which decides whether it is a tuple.
+            None if elements.len() == 1 => Err(Error::Unsupported),
+            None => Ok(PyTuple::new(py, elements).into()),
+        }
+    }
+
+    /// Whether the next tokens are `field:`, which starts a struct rather than a tuple.
+    fn is_field(&mut self) -> bool {
+        let start = self.pos;
+        let field =
+            matches!(self.next(), Some(token) if token.kind == TokenKind::Ident) && self.eat(':');
+        self.pos = start;
+        field
+    }
+}
+
+/// Converts a number literal with `numbers::convert`.
+fn number(converter: &Converter, text: &str, negative: bool) -> Result<PyObject, Error> {
+    let literal = if negative {
+        format!("-{}", text)
+    } else {
+        text.to_string()
+    };
+    let number = numbers::parse(&literal).ok_or(Error::Unsupported)?;
+    Ok(numbers::convert(converter, &literal, number)?)
+}
+
+/// Returns the contents of a string literal with escapes replaced. Raw strings and Unicode
+/// escapes are left to `ron_parser`.
+fn unescape(literal: &str) -> Result<String, Error> {
+    let body = literal.strip_prefix('"').ok_or(Error::Unsupported)?;
+    let mut result = String::with_capacity(body.len());
+    let mut chars = body.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' if i + 1 == body.len() => return Ok(result),
+            '"' => return Err(Error::Unsupported),
+            '\\' => result.push(match chars.next() {
+                Some((_, 'n')) => '\n',
+                Some((_, 't')) => '\t',
+                Some((_, 'r')) => '\r',
+                Some((_, '0')) => '\0',
+                Some((_, '\\')) => '\\',
+                Some((_, '"')) => '"',
+                Some((_, '\'')) => '\'',
+                _ => return Err(Error::Unsupported),
+            }),
+            c => result.push(c),
+        }
+    }
+    // Unterminated string.
+    Err(Error::Unsupported)
+}
@@ -0,0 +1,1117 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyString};
+
+use crate::lexer::{self, Token, TokenKind};
+use crate::Source;
+
+/// A node of the concrete syntax tree. Nodes only record where values are in the source text, so
+/// edits can replace exactly the affected text and leave comments and formatting untouched.
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub start: usize,
+    pub end: usize,
+    pub kind: NodeKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum NodeKind {
+    /// Any value that isn't a container, including `#include(...)` directives and `$variables`.
+    Scalar,
+    /// `Name(field: value, ...)`. Empty parentheses are parsed as a struct without fields.
+    Struct {
+        name: Option<String>,
+        fields: Vec<Entry>,
+        open: usize,
+        close: usize,
+    },
+    Tuple {
+        name: Option<String>,
+        elements: Vec<Node>,
+        open: usize,
+        close: usize,
+    },
+    Seq {
+        elements: Vec<Node>,
+        open: usize,
+        close: usize,
+    },
+    Map {
+        entries: Vec<Entry>,
+        open: usize,
+        close: usize,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub key: Node,
+    pub value: Node,
+}
+
+impl Node {
+    /// Looks through `Some(...)`, which is transparent when accessing items.
+    fn unwrap_some(&self) -> &Node {
+        match &self.kind {
+            NodeKind::Tuple {
+                name: Some(name),
+                elements,
+                ..
+            } if name == "Some" && elements.len() == 1 => elements[0].unwrap_some(),
+            _ => self,
+        }
+    }
+
+    pub fn is_container(&self) -> bool {
+        !matches!(self.unwrap_some().kind, NodeKind::Scalar)
+    }
+}
+
+/// Parses `source` into a syntax tree. `#![...]` attributes and `#let` definitions before the
+/// top-level value are skipped.
+pub fn parse(source: &str) -> PyResult<Node> {
+    let mut parser = Parser {
+        source,
+        tokens: lexer::tokenize(source),
+        pos: 0,
+    };
+    parser.skip_preamble()?;
+    let value = parser.value()?;
+    if let Some(token) = parser.peek() {
+        return Err(parser.error(token, "expected end of document"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Returns the next non-trivia token without consuming it.
+    fn peek(&mut self) -> Option<Token> {
+        while self.pos < self.tokens.len() && self.tokens[self.pos].is_trivia() {
+            self.pos += 1;
+        }
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_second(&mut self) -> Option<Token> {
+        self.peek()?;
+        self.tokens[self.pos + 1..]
+            .iter()
+            .find(|token| !token.is_trivia())
+            .copied()
+    }
+
+    fn error(&self, token: Token, message: &str) -> PyErr {
+        let line = self.source[..token.start].matches('\n').count() + 1;
+        exceptions::PyValueError::new_err(format!(
+            "Invalid RON on line {}: {}, found `{}`",
+            line,
+            message,
+            token.text(self.source)
+        ))
+    }
+
+    fn eof(&self, message: &str) -> PyErr {
+        exceptions::PyValueError::new_err(format!(
+            "Invalid RON: {}, found end of document",
+            message
+        ))
+    }
+
+    fn expect(&mut self, c: char) -> PyResult<Token> {
+        match self.next() {
+            Some(token) if token.kind == TokenKind::Punct(c) => Ok(token),
+            Some(token) => Err(self.error(token, &format!("expected `{}`", c))),
+            None => Err(self.eof(&format!("expected `{}`", c))),
+        }
+    }
+
+    fn skip_preamble(&mut self) -> PyResult<()> {
+        loop {
+            self.peek();
+            if let Some(end) = crate::variables::attribute_end(&self.tokens, self.pos) {
+                self.pos = end;
+                continue;
+            }
+            let is_let = matches!(self.peek(), Some(token) if token.kind == TokenKind::Punct('#'))
+                && matches!(self.peek_second(), Some(token) if token.text(self.source) == "let");
+            if !is_let {
+                return Ok(());
+            }
+            while let Some(token) = self.next() {
+                if token.kind == TokenKind::Punct(';') {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn value(&mut self) -> PyResult<Node> {
+        let token = match self.next() {
+            Some(token) => token,
+            None => return Err(self.eof("expected a value")),
+        };
+        let scalar = |end: usize| Node {
+            start: token.start,
+            end,
+            kind: NodeKind::Scalar,
+        };
+        match token.kind {
+            TokenKind::Punct('(') => self.parens(None, token.start, token.start),
+            TokenKind::Punct('[') => {
+                let (elements, close) = self.elements(']')?;
+                Ok(Node {
+                    start: token.start,
+                    end: close + 1,
+                    kind: NodeKind::Seq {
+                        elements,
+                        open: token.start,
+                        close,
+                    },
+                })
+            }
+            TokenKind::Punct('{') => {
+                let (entries, close) = self.entries(None)?;
+                Ok(Node {
+                    start: token.start,
+                    end: close + 1,
+                    kind: NodeKind::Map {
+                        entries,
+                        open: token.start,
+                        close,
+                    },
+                })
+            }
+            TokenKind::Ident => match self.peek() {
+                Some(open) if open.kind == TokenKind::Punct('(') => {
+                    self.next();
+                    let name = token.text(self.source).to_string();
+                    self.parens(Some(name), token.start, open.start)
+                }
+                _ => Ok(scalar(token.end)),
+            },
+            TokenKind::String | TokenKind::Char | TokenKind::Number => Ok(scalar(token.end)),
+            TokenKind::Punct('-') | TokenKind::Punct('+') | TokenKind::Punct('$') => {
+                match self.next() {
+                    Some(next) if matches!(next.kind, TokenKind::Number | TokenKind::Ident) => {
+                        Ok(scalar(next.end))
+                    }
+                    Some(next) => Err(self.error(next, "expected a number or identifier")),
+                    None => Err(self.eof("expected a number or identifier")),
+                }
+            }
+            TokenKind::Punct('#') => {
+                // #include("path")
+                match self.next() {
+                    Some(next) if next.text(self.source) == "include" => {}
+                    Some(next) => return Err(self.error(next, "expected `include`")),
+                    None => return Err(self.eof("expected `include`")),
+                }
+                self.expect('(')?;
+                match self.next() {
+                    Some(path) if path.kind == TokenKind::String => {}
+                    Some(path) => return Err(self.error(path, "expected a string")),
+                    None => return Err(self.eof("expected a string")),
+                }
+                let close = self.expect(')')?;
+                Ok(scalar(close.end))
+            }
+            _ => Err(self.error(token, "expected a value")),
+        }
+    }
+
+    /// Parses the contents of parentheses as struct fields if they start with `ident:`, and as
+    /// tuple elements otherwise. The opening parenthesis has already been consumed.
+    fn parens(&mut self, name: Option<String>, start: usize, open: usize) -> PyResult<Node> {
+        let is_struct = match (self.peek(), self.peek_second()) {
+            (Some(close), _) if close.kind == TokenKind::Punct(')') => true,
+            (Some(key), Some(colon)) => {
+                key.kind == TokenKind::Ident && colon.kind == TokenKind::Punct(':')
+            }
+            _ => false,
+        };
+        if is_struct {
+            let (fields, close) = self.entries(Some(')'))?;
+            Ok(Node {
+                start,
+                end: close + 1,
+                kind: NodeKind::Struct {
+                    name,
+                    fields,
+                    open,
+                    close,
+                },
+            })
+        } else {
+            let (elements, close) = self.elements(')')?;
+            Ok(Node {
+                start,
+                end: close + 1,
+                kind: NodeKind::Tuple {
+                    name,
+                    elements,
+                    open,
+                    close,
+                },
+            })
+        }
+    }
+
+    /// Parses comma separated values up to the closing delimiter, returning the values and the
+    /// offset of the delimiter.
+    fn elements(&mut self, close: char) -> PyResult<(Vec<Node>, usize)> {
+        let mut elements = vec![];
+        loop {
+            match self.peek() {
+                Some(token) if token.kind == TokenKind::Punct(close) => {
+                    self.next();
+                    return Ok((elements, token.start));
+                }
+                Some(_) => {}
+                None => return Err(self.eof(&format!("expected `{}`", close))),
+            }
+            elements.push(self.value()?);
+            match self.next() {
+                Some(token) if token.kind == TokenKind::Punct(',') => {}
+                Some(token) if token.kind == TokenKind::Punct(close) => {
+                    return Ok((elements, token.start))
+                }
+                Some(token) => {
+                    return Err(self.error(token, &format!("expected `,` or `{}`", close)))
+                }
+                None => return Err(self.eof(&format!("expected `,` or `{}`", close))),
+            }
+        }
+    }
+
+    /// Parses `key: value` entries up to the closing delimiter. Struct fields (`close` is `)`)
+    /// have identifier keys, map entries (`close` is `None`, meaning `}`) can have any key.
+    fn entries(&mut self, close: Option<char>) -> PyResult<(Vec<Entry>, usize)> {
+        let is_struct = close.is_some();
+        let close = close.unwrap_or('}');
+        let mut entries = vec![];
+        loop {
+            let token = match self.peek() {
+                Some(token) if token.kind == TokenKind::Punct(close) => {
+                    self.next();
+                    return Ok((entries, token.start));
+                }
+                Some(token) => token,
+                None => return Err(self.eof(&format!("expected `{}`", close))),
+            };
+            let key = if is_struct {
+                if token.kind != TokenKind::Ident {
+                    return Err(self.error(token, "expected a field name"));
+                }
+                self.next();
+                Node {
+                    start: token.start,
+                    end: token.end,
+                    kind: NodeKind::Scalar,
+                }
+            } else {
+                self.value()?
+            };
+            self.expect(':')?;
+            let value = self.value()?;
+            entries.push(Entry { key, value });
+            match self.next() {
+                Some(token) if token.kind == TokenKind::Punct(',') => {}
+                Some(token) if token.kind == TokenKind::Punct(close) => {
+                    return Ok((entries, token.start))
+                }
+                Some(token) => {
+                    return Err(self.error(token, &format!("expected `,` or `{}`", close)))
+                }
+                None => return Err(self.eof(&format!("expected `,` or `{}`", close))),
+            }
+        }
+    }
+}
+
+/// A RON document that keeps its comments, ordering and formatting. Items can be read and
+/// assigned like a dict (nested containers are returned as `DocumentItem` views) and only the
+/// edited values change when the document is serialized again.
+#[pyclass(module = "pyron")]
+pub struct Document {
+    source: String,
+    root: Node,
+    /// The file the document was read from, used as the default destination of `save`.
+    #[pyo3(get)]
+    path: Option<PathBuf>,
+    /// Whether the document was edited, so that `__exit__` only writes changed documents.
+    modified: bool,
+}
+
+/// A view of a container inside a `Document`. Views address their container by path, so they stay
+/// valid across edits of the document.
+#[pyclass(module = "pyron")]
+pub struct DocumentItem {
+    document: Py<Document>,
+    path: Vec<PyObject>,
+}
+
+/// Parses the RON file at `path`, given as a `str` or `os.PathLike` as for `load`, or the RON text
+/// `text` into a `Document` that preserves comments and formatting when edited.
+#[pyfunction]
+#[pyo3(signature = (path = None, *, text = None))]
+pub fn parse_document(path: Option<PathBuf>, text: Option<String>) -> PyResult<Document> {
+    match Source::new("parse_document", path, text)? {
+        Source::Text(text) => Document::new(text, None),
+        Source::File(path) => {
+            let source = std::fs::read_to_string(&path)?;
+            Document::new(source, Some(path))
+        }
+    }
+}
+
+/// Returns the RON extensions enabled by `#![enable(...)]` attributes at the start of the RON file
+/// at `path`, given as a `str` or `os.PathLike` as for `load`, or of the RON text `text`, such as
+/// `["implicit_some"]`. Passing them to `dump` writes the same attributes, so that rewriting a file
+/// doesn't strip them.
+#[pyfunction]
+#[pyo3(signature = (path = None, *, text = None))]
+pub fn extensions(path: Option<PathBuf>, text: Option<String>) -> PyResult<Vec<String>> {
+    match Source::new("extensions", path, text)? {
+        Source::Text(text) => Ok(enabled_extensions(&text)),
+        Source::File(path) => Ok(enabled_extensions(&std::fs::read_to_string(path)?)),
+    }
+}
+
+/// Returns the extensions enabled by the `#![enable(...)]` attributes at the start of `source`.
+fn enabled_extensions(source: &str) -> Vec<String> {
+    let tokens = lexer::tokenize(source);
+    let mut extensions = vec![];
+    let mut i = crate::variables::skip_trivia(&tokens, 0);
+    while let Some(end) = crate::variables::attribute_end(&tokens, i) {
+        let attribute = tokens[i..end]
+            .iter()
+            .filter(|token| !token.is_trivia())
+            .collect::<Vec<_>>();
+        if attribute.len() > 4 && attribute[3].text(source) == "enable" {
+            for token in &attribute[4..] {
+                let name = token.text(source);
+                if token.kind == TokenKind::Ident && !extensions.iter().any(|e| e == name) {
+                    extensions.push(name.to_string());
+                }
+            }
+        }
+        i = crate::variables::skip_trivia(&tokens, end);
+    }
+    extensions
+}
+
+/// Opens a RON file as a `Document` for use in a `with` block, which writes the changes made to
+/// the document back to the file when the block exits without an exception.
+#[pyfunction]
+pub fn open(path: PathBuf) -> PyResult<Document> {
+    let source = std::fs::read_to_string(&path)?;
+    Document::new(source, Some(path))
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it over `path`, syncing the
+/// file and the directory so that a crash leaves either the old or the new contents. The file
+/// keeps the permissions of the file it replaces.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> PyResult<()> {
+    // Numbers temporary files, so that threads writing the same path don't share one.
+    static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+    let name = path.file_name().map_or_else(
+        || "pyron".into(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    let number = TEMP_FILES.fetch_add(1, Ordering::Relaxed);
+    let temp = path.with_file_name(format!(".{}.{}.{}.tmp", name, std::process::id(), number));
+    let written = write_synced(&temp, path, contents.as_ref());
+    if let Err(e) = written.and_then(|()| std::fs::rename(&temp, path)) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    sync_directory(path)?;
+    Ok(())
+}
+
+/// Writes a new file `temp` with the permissions of `path`, if it exists, and syncs it to disk.
+fn write_synced(temp: &Path, path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = File::create(temp)?;
+    // The permissions are set before writing, so that the contents are never readable with wider
+    // permissions than those of `path`.
+    if let Ok(metadata) = std::fs::metadata(path) {
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+/// Syncs the directory containing `path`, so that a rename into it is on disk. Only Unix allows
+/// opening directories as files to sync them.
+#[cfg(unix)]
+fn sync_directory(path: &Path) -> io::Result<()> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(directory)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_directory(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Sets the values at the dotted paths in `updates` in a RON file, changing only the text of those
+/// values and leaving comments, ordering and formatting untouched.
+#[pyfunction]
+pub fn edit(py: Python, path: PathBuf, updates: &PyDict) -> PyResult<()> {
+    let source = std::fs::read_to_string(&path)?;
+    let mut document = Document::new(source, Some(path))?;
+    for (key, value) in updates {
+        document.set_path(py, key.extract()?, value)?;
+    }
+    document.save(None)
+}
+
+impl Document {
+    pub fn new(source: String, path: Option<PathBuf>) -> PyResult<Document> {
+        let root = parse(&source)?;
+        Ok(Document {
+            source,
+            root,
+            path,
+            modified: false,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Replaces `start..end` of the source with `text` and parses the result.
+    fn splice(&mut self, start: usize, end: usize, text: &str) -> PyResult<()> {
+        let mut source = self.source.clone();
+        source.replace_range(start..end, text);
+        self.root = parse(&source)?;
+        self.source = source;
+        self.modified = true;
+        Ok(())
+    }
+
+    /// Sets the value at a dotted path like `training.layers[0].dim`.
+    pub fn set_path(&mut self, py: Python, path: &str, value: &PyAny) -> PyResult<()> {
+        let mut parts = crate::path::split(path);
+        let last = match parts.pop() {
+            Some(last) => last,
+            None => return Err(exceptions::PyValueError::new_err("Empty path")),
+        };
+        let mut keys = vec![];
+        for part in parts {
+            let key = self.path_key(py, &keys, &part)?;
+            keys.push(key);
+        }
+        let key = self.path_key(py, &keys, &last)?;
+        self.set(py, &keys, key.as_ref(py), value)
+    }
+
+    /// Converts a component of a dotted path into a key of the container at `path`: an index for
+    /// sequences and tuples, and like `path.get`, a string key or else an integer key for maps.
+    fn path_key(&self, py: Python, path: &[PyObject], part: &str) -> PyResult<PyObject> {
+        let node = self.node(py, path)?;
+        match node.unwrap_some().kind {
+            NodeKind::Seq { .. } | NodeKind::Tuple { .. } => match part.parse::<isize>() {
+                Ok(i) => Ok(i.into_py(py)),
+                Err(_) => Err(exceptions::PyKeyError::new_err(part.to_string())),
+            },
+            NodeKind::Map { .. } => {
+                let key = PyString::new(py, part);
+                if !self.contains(py, path, key)? {
+                    if let Ok(i) = part.parse::<i64>() {
+                        if self.contains(py, path, i.into_py(py).as_ref(py))? {
+                            return Ok(i.into_py(py));
+                        }
+                    }
+                }
+                Ok(key.into())
+            }
+            _ => Ok(part.into_py(py)),
+        }
+    }
+
+    fn node(&self, py: Python, path: &[PyObject]) -> PyResult<&Node> {
+        let mut node = &self.root;
+        for key in path {
+            node = match self.child(py, node, key.as_ref(py))? {
+                Some((_, child)) => child,
+                None => return Err(exceptions::PyKeyError::new_err(key.clone_ref(py))),
+            };
+        }
+        Ok(node)
+    }
+
+    /// Finds the item with the given key in a container, returning the node of its key (for
+    /// structs and maps) and of its value.
+    fn child<'n>(
+        &self,
+        py: Python,
+        node: &'n Node,
+        key: &PyAny,
+    ) -> PyResult<Option<(Option<&'n Node>, &'n Node)>> {
+        Ok(match &node.unwrap_some().kind {
+            NodeKind::Struct { fields, .. } => match key.extract::<&str>() {
+                Ok(key) => fields
+                    .iter()
+                    .find(|field| field.key.text(&self.source) == key)
+                    .map(|field| (Some(&field.key), &field.value)),
+                Err(_) => None,
+            },
+            NodeKind::Map { entries, .. } => {
+                let mut found = None;
+                for entry in entries {
+                    let entry_key = crate::load_str(py, entry.key.text(&self.source), false)?;
+                    if entry_key.as_ref(py).eq(key)? {
+                        found = Some((Some(&entry.key), &entry.value));
+                        break;
+                    }
+                }
+                found
+            }
+            NodeKind::Seq { elements, .. } | NodeKind::Tuple { elements, .. } => {
+                index(key, elements.len())?.map(|i| (None, &elements[i]))
+            }
+            NodeKind::Scalar => {
+                return Err(exceptions::PyTypeError::new_err(format!(
+                    "`{}` is not a container",
+                    node.text(&self.source)
+                )))
+            }
+        })
+    }
+
+    fn get(
+        &self,
+        py: Python,
+        document: &Py<Document>,
+        path: &[PyObject],
+        key: &PyAny,
+    ) -> PyResult<PyObject> {
+        let node = self.node(py, path)?;
+        let child = match self.child(py, node, key)? {
+            Some((_, child)) => child,
+            None => return Err(exceptions::PyKeyError::new_err(key.to_object(py))),
+        };
+        if child.is_container() {
+            let mut path = path.iter().map(|key| key.clone_ref(py)).collect::<Vec<_>>();
+            path.push(key.into());
+            Ok(DocumentItem {
+                document: document.clone_ref(py),
+                path,
+            }
+            .into_py(py))
+        } else {
+            crate::load_str(py, child.text(&self.source), true)
+        }
+    }
+
+    fn set(&mut self, py: Python, path: &[PyObject], key: &PyAny, value: &PyAny) -> PyResult<()> {
+        let node = self.node(py, path)?.unwrap_some();
+        if let Some((_, child)) = self.child(py, node, key)? {
+            let text = self.format(py, value, child.start)?;
+            let (start, end) = (child.start, child.end);
+            return self.splice(start, end, &text);
+        }
+        let entry = match &node.kind {
+            NodeKind::Struct { .. } => match key.extract::<&str>() {
+                Ok(name) => format!("{}: ", name),
+                Err(_) => {
+                    return Err(exceptions::PyTypeError::new_err(
+                        "struct fields must be strings",
+                    ))
+                }
+            },
+            NodeKind::Map { .. } => format!("{}: ", crate::serialize(py, key)?),
+            _ => return Err(exceptions::PyIndexError::new_err("index out of range")),
+        };
+        self.insert(py, node.clone(), entry, value)
+    }
+
+    fn append(&mut self, py: Python, path: &[PyObject], value: &PyAny) -> PyResult<()> {
+        let node = self.node(py, path)?.unwrap_some().clone();
+        match node.kind {
+            NodeKind::Seq { .. } | NodeKind::Tuple { .. } => {
+                self.insert(py, node, String::new(), value)
+            }
+            _ => Err(exceptions::PyTypeError::new_err(
+                "can only append to a sequence or tuple",
+            )),
+        }
+    }
+
+    /// Inserts a new item after the last item of a container, following the layout of the
+    /// existing items: one item per line in multi-line containers, inline otherwise.
+    fn insert(&mut self, py: Python, node: Node, prefix: String, value: &PyAny) -> PyResult<()> {
+        let (items, open, close) = match &node.kind {
+            NodeKind::Struct {
+                fields,
+                open,
+                close,
+                ..
+            } => (
+                fields
+                    .iter()
+                    .map(|f| (f.key.start, f.value.end))
+                    .collect::<Vec<_>>(),
+                *open,
+                *close,
+            ),
+            NodeKind::Map {
+                entries,
+                open,
+                close,
+            } => (
+                entries.iter().map(|e| (e.key.start, e.value.end)).collect(),
+                *open,
+                *close,
+            ),
+            NodeKind::Seq {
+                elements,
+                open,
+                close,
+            }
+            | NodeKind::Tuple {
+                elements,
+                open,
+                close,
+                ..
+            } => (
+                elements.iter().map(|e| (e.start, e.end)).collect(),
+                *open,
+                *close,
+            ),
+            NodeKind::Scalar => unreachable!(),
+        };
+        let (first_start, last_end) = match (items.first(), items.last()) {
+            (Some(first), Some(last)) => (first.0, last.1),
+            _ => {
+                let text = format!("{}{}", prefix, self.format(py, value, open)?);
+                return self.splice(open + 1, close, &text);
+            }
+        };
+        let multiline = self.source[open..first_start].contains('\n');
+        let comma = self.next_comma(last_end);
+        if multiline {
+            let indent = line_indent(&self.source, first_start).to_string();
+            let text = format!("{}{}", prefix, self.format_indented(py, value, &indent)?);
+            match comma {
+                Some(comma) => {
+                    // Insert after any comment that follows the comma on the same line.
+                    let line_end = self.source[comma..]
+                        .find('\n')
+                        .map_or(self.source.len(), |i| comma + i);
+                    let rest = self.source[comma + 1..line_end].trim();
+                    let at = if rest.is_empty() || rest.starts_with("//") {
+                        line_end
+                    } else {
+                        comma + 1
+                    };
+                    self.splice(at, at, &format!("\n{}{},", indent, text))
+                }
+                None => self.splice(last_end, last_end, &format!(",\n{}{}", indent, text)),
+            }
+        } else {
+            let text = format!("{}{}", prefix, self.format(py, value, first_start)?);
+            match comma {
+                Some(comma) => self.splice(comma + 1, comma + 1, &format!(" {},", text)),
+                None => self.splice(last_end, last_end, &format!(", {}", text)),
+            }
+        }
+    }
+
+    fn delete(&mut self, py: Python, path: &[PyObject], key: &PyAny) -> PyResult<()> {
+        let node = self.node(py, path)?;
+        let (key_node, value_node) = match self.child(py, node, key)? {
+            Some(child) => child,
+            None => return Err(exceptions::PyKeyError::new_err(key.to_object(py))),
+        };
+        let mut start = key_node.map_or(value_node.start, |key| key.start);
+        let mut end = value_node.end;
+        match self.next_comma(end) {
+            Some(comma) => end = comma + 1,
+            None => {
+                if let Some(comma) = self.previous_comma(start) {
+                    start = comma;
+                }
+            }
+        }
+        // Remove the whole line, including a trailing comment and any comment lines directly
+        // above it, if the item is on a line of its own.
+        let line_start = self.source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.source[end..]
+            .find('\n')
+            .map_or(self.source.len(), |i| end + i);
+        let before = &self.source[line_start..start];
+        let after = self.source[end..line_end].trim();
+        if before.trim().is_empty() && (after.is_empty() || after.starts_with("//")) {
+            start = line_start;
+            while start > 0 {
+                let previous_start = self.source[..start - 1].rfind('\n').map_or(0, |i| i + 1);
+                if self.source[previous_start..start]
+                    .trim_start()
+                    .starts_with("//")
+                {
+                    start = previous_start;
+                } else {
+                    break;
+                }
+            }
+            end = (line_end + 1).min(self.source.len());
+        }
+        self.splice(start, end, "")
+    }
+
+    /// Offset of the `,` following `end`, if the next non-trivia token is a comma.
+    fn next_comma(&self, end: usize) -> Option<usize> {
+        lexer::tokenize(&self.source[end..])
+            .into_iter()
+            .find(|token| !token.is_trivia())
+            .filter(|token| token.kind == TokenKind::Punct(','))
+            .map(|token| end + token.start)
+    }
+
+    /// Offset of the `,` preceding `start`, if the previous non-trivia token is a comma.
+    fn previous_comma(&self, start: usize) -> Option<usize> {
+        lexer::tokenize(&self.source[..start])
+            .into_iter()
+            .rev()
+            .find(|token| !token.is_trivia())
+            .filter(|token| token.kind == TokenKind::Punct(','))
+            .map(|token| token.start)
+    }
+
+    /// Serializes `value` for insertion at `at`, indenting continuation lines like the line `at`
+    /// is on.
+    fn format(&self, py: Python, value: &PyAny, at: usize) -> PyResult<String> {
+        let indent = line_indent(&self.source, at).to_string();
+        self.format_indented(py, value, &indent)
+    }
+
+    fn format_indented(&self, py: Python, value: &PyAny, indent: &str) -> PyResult<String> {
+        Ok(crate::serialize(py, value)?.replace('\n', &format!("\n{}", indent)))
+    }
+
+    fn keys(&self, py: Python, path: &[PyObject]) -> PyResult<Vec<PyObject>> {
+        match &self.node(py, path)?.unwrap_some().kind {
+            NodeKind::Struct { fields, .. } => Ok(fields
+                .iter()
+                .map(|field| field.key.text(&self.source).into_py(py))
+                .collect()),
+            NodeKind::Map { entries, .. } => entries
+                .iter()
+                .map(|entry| crate::load_str(py, entry.key.text(&self.source), true))
+                .collect(),
+            _ => Err(exceptions::PyTypeError::new_err(
+                "only structs and maps have keys",
+            )),
+        }
+    }
+
+    fn len(&self, py: Python, path: &[PyObject]) -> PyResult<usize> {
+        Ok(match &self.node(py, path)?.unwrap_some().kind {
+            NodeKind::Struct { fields, .. } => fields.len(),
+            NodeKind::Map { entries, .. } => entries.len(),
+            NodeKind::Seq { elements, .. } | NodeKind::Tuple { elements, .. } => elements.len(),
+            NodeKind::Scalar => 0,
+        })
+    }
+
+    fn contains(&self, py: Python, path: &[PyObject], key: &PyAny) -> PyResult<bool> {
+        let node = self.node(py, path)?;
+        Ok(self.child(py, node, key)?.is_some())
+    }
+
+    fn value(&self, py: Python, path: &[PyObject]) -> PyResult<PyObject> {
+        if path.is_empty() {
+            return crate::load_str(py, &self.source, true);
+        }
+        crate::load_str(py, self.node(py, path)?.text(&self.source), true)
+    }
+}
+
+impl Node {
+    pub fn text<'s>(&self, source: &'s str) -> &'s str {
+        &source[self.start..self.end]
+    }
+}
+
+/// Collects the `//` comments attached to struct fields, map entries and sequence elements, keyed
+/// by dotted path. A comment is attached to an item if it is on the lines directly above it or
+/// after it on the same line.
+pub fn comments(py: Python, source: &str) -> PyResult<Vec<(String, String)>> {
+    let root = parse(source)?;
+    let mut items = vec![];
+    collect_items(py, source, &root, "", &mut items)?;
+    let mut comments = vec![];
+    for (path, start, end) in items {
+        let mut lines = vec![];
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        if source[line_start..start].trim().is_empty() {
+            let mut above = line_start;
+            while above > 0 {
+                let previous = source[..above - 1].rfind('\n').map_or(0, |i| i + 1);
+                match source[previous..above].trim().strip_prefix("//") {
+                    Some(comment) => lines.insert(0, comment.trim().to_string()),
+                    None => break,
+                }
+                above = previous;
+            }
+        }
+        let line_end = source[end..].find('\n').map_or(source.len(), |i| end + i);
+        let rest = source[end..line_end].trim_start();
+        let rest = rest.strip_prefix(',').unwrap_or(rest).trim();
+        if let Some(comment) = rest.strip_prefix("//") {
+            lines.push(comment.trim().to_string());
+        }
+        if !lines.is_empty() {
+            comments.push((path, lines.join("\n")));
+        }
+    }
+    Ok(comments)
+}
+
+/// Writes each comment as `//` lines above the item at its dotted path in `source`.
+pub fn insert_comments(
+    py: Python,
+    source: &str,
+    comments: &HashMap<String, String>,
+) -> PyResult<String> {
+    let root = parse(source)?;
+    let mut items = vec![];
+    collect_items(py, source, &root, "", &mut items)?;
+    let mut result = source.to_string();
+    // Insert from the end so that the offsets of earlier items stay valid.
+    for (path, start, _) in items.iter().rev() {
+        if let Some(comment) = comments.get(path) {
+            let line_start = source[..*start].rfind('\n').map_or(0, |i| i + 1);
+            let indent = line_indent(source, *start);
+            let mut lines = String::new();
+            for line in comment.lines() {
+                if line.is_empty() {
+                    lines.push_str(&format!("{}//\n", indent));
+                } else {
+                    lines.push_str(&format!("{}// {}\n", indent, line));
+                }
+            }
+            result.insert_str(line_start, &lines);
+        }
+    }
+    Ok(result)
+}
+
+/// Appends the dotted path and the source range of every item nested in `node`.
+fn collect_items(
+    py: Python,
+    source: &str,
+    node: &Node,
+    path: &str,
+    items: &mut Vec<(String, usize, usize)>,
+) -> PyResult<()> {
+    match &node.unwrap_some().kind {
+        NodeKind::Struct { fields, .. } => {
+            for field in fields {
+                let child = crate::diff::join(path, PyString::new(py, field.key.text(source)))?;
+                items.push((child.clone(), field.key.start, field.value.end));
+                collect_items(py, source, &field.value, &child, items)?;
+            }
+        }
+        NodeKind::Map { entries, .. } => {
+            for entry in entries {
+                let key = crate::load_str(py, entry.key.text(source), false)?;
+                let child = crate::diff::join(path, key.as_ref(py))?;
+                items.push((child.clone(), entry.key.start, entry.value.end));
+                collect_items(py, source, &entry.value, &child, items)?;
+            }
+        }
+        NodeKind::Seq { elements, .. } | NodeKind::Tuple { elements, .. } => {
+            for (i, element) in elements.iter().enumerate() {
+                let child = format!("{}[{}]", path, i);
+                items.push((child.clone(), element.start, element.end));
+                collect_items(py, source, element, &child, items)?;
+            }
+        }
+        NodeKind::Scalar => {}
+    }
+    Ok(())
+}
+
+/// Resolves a possibly negative Python index into a sequence of length `len`.
+fn index(key: &PyAny, len: usize) -> PyResult<Option<usize>> {
+    let i = match key.extract::<isize>() {
+        Ok(i) => i,
+        Err(_) => return Ok(None),
+    };
+    let i = if i < 0 { i + len as isize } else { i };
+    Ok(if 0 <= i && (i as usize) < len {
+        Some(i as usize)
+    } else {
+        None
+    })
+}
+
+/// The leading whitespace of the line containing `offset`.
+fn line_indent(source: &str, offset: usize) -> &str {
+    let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = &source[line_start..];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+#[pymethods]
+impl Document {
+    fn __getitem__(slf: &PyCell<Self>, key: &PyAny) -> PyResult<PyObject> {
+        let py = slf.py();
+        let document: Py<Document> = slf.into();
+        slf.borrow().get(py, &document, &[], key)
+    }
+
+    fn __setitem__(&mut self, py: Python, key: &PyAny, value: &PyAny) -> PyResult<()> {
+        self.set(py, &[], key, value)
+    }
+
+    fn __delitem__(&mut self, py: Python, key: &PyAny) -> PyResult<()> {
+        self.delete(py, &[], key)
+    }
+
+    fn __contains__(&self, py: Python, key: &PyAny) -> PyResult<bool> {
+        self.contains(py, &[], key)
+    }
+
+    fn __len__(&self, py: Python) -> PyResult<usize> {
+        self.len(py, &[])
+    }
+
+    fn __str__(&self) -> String {
+        self.source.clone()
+    }
+
+    /// Returns the keys of the top-level struct or map.
+    #[pyo3(name = "keys")]
+    fn py_keys(&self, py: Python) -> PyResult<Vec<PyObject>> {
+        self.keys(py, &[])
+    }
+
+    /// Appends a value to the top-level sequence.
+    #[pyo3(name = "append")]
+    fn py_append(&mut self, py: Python, value: &PyAny) -> PyResult<()> {
+        self.append(py, &[], value)
+    }
+
+    /// The document converted to Python objects, as by `loads`.
+    #[getter(value)]
+    fn py_value(&self, py: Python) -> PyResult<PyObject> {
+        self.value(py, &[])
+    }
+
+    /// The RON extensions enabled by the attributes at the start of the document, as returned by
+    /// `extensions`.
+    #[getter(extensions)]
+    fn py_extensions(&self) -> Vec<String> {
+        enabled_extensions(&self.source)
+    }
+
+    #[pyo3(name = "to_string")]
+    fn py_to_string(&self) -> String {
+        self.source.clone()
+    }
+
+    /// Writes the document to `path`, or to the file it was read from. The file is replaced
+    /// atomically, so readers never see a partially written document.
+    #[pyo3(signature = (path = None))]
+    fn save(&self, path: Option<PathBuf>) -> PyResult<()> {
+        let path = match path.as_ref().or(self.path.as_ref()) {
+            Some(path) => path,
+            None => {
+                return Err(exceptions::PyValueError::new_err(
+                    "The document wasn't read from a file, so a path is required",
+                ))
+            }
+        };
+        write_atomic(path, &self.source)
+    }
+
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    /// Saves the document if it was edited and the `with` block didn't raise.
+    fn __exit__(
+        &self,
+        exc_type: Option<&PyAny>,
+        _exc_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+    ) -> PyResult<bool> {
+        if exc_type.is_none() && self.modified {
+            self.save(None)?;
+        }
+        Ok(false)
+    }
+}
+
+#[pymethods]
+impl DocumentItem {
+    fn __getitem__(&self, py: Python, key: &PyAny) -> PyResult<PyObject> {
+        self.document
+            .borrow(py)
+            .get(py, &self.document, &self.path, key)
+    }
+
+    fn __setitem__(&self, py: Python, key: &PyAny, value: &PyAny) -> PyResult<()> {
+        self.document.borrow_mut(py).set(py, &self.path, key, value)
+    }
+
+    fn __delitem__(&self, py: Python, key: &PyAny) -> PyResult<()> {
+        self.document.borrow_mut(py).delete(py, &self.path, key)
+    }
+
+    fn __contains__(&self, py: Python, key: &PyAny) -> PyResult<bool> {
+        self.document.borrow(py).contains(py, &self.path, key)
+    }
+
+    fn __len__(&self, py: Python) -> PyResult<usize> {
+        self.document.borrow(py).len(py, &self.path)
+    }
+
+    fn __str__(&self, py: Python) -> PyResult<String> {
+        let document = self.document.borrow(py);
+        Ok(document
+            .node(py, &self.path)?
+            .text(&document.source)
+            .to_string())
+    }
+
+    fn keys(&self, py: Python) -> PyResult<Vec<PyObject>> {
+        self.document.borrow(py).keys(py, &self.path)
+    }
+
+    fn append(&self, py: Python, value: &PyAny) -> PyResult<()> {
+        self.document.borrow_mut(py).append(py, &self.path, value)
+    }
+
+    /// The item converted to Python objects.
+    #[getter]
+    fn value(&self, py: Python) -> PyResult<PyObject> {
+        self.document.borrow(py).value(py, &self.path)
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+
+use crate::lexer::{self, TokenKind};
+use crate::{Converter, Source};
+
+/// Iterates over the parse events of the RON file at `path`, given as a `str` or `os.PathLike` as
+/// for `load`, or of the RON text `text`, as `(kind, value)` tuples:
+///
+/// - `("start_struct", name)` and `("end_struct", None)`, where `name` is `None` for unnamed
+///   structs, with a `("key", field)` event before every field value
+/// - `("start_tuple", name)` and `("end_tuple", None)`
+/// - `("start_seq", None)` and `("end_seq", None)`
+/// - `("start_map", None)` and `("end_map", None)`, where the events of every key are followed by
+///   the events of its value
+/// - `("scalar", value)` for strings, numbers, bools, chars, `None` and `()`
+/// - `("include", path)` for `#include` directives, which aren't followed
+///
+/// `Some(...)` is transparent, as with `load`. Files are read incrementally, so events can be
+/// used to filter or summarize files that are too large to load.
+#[pyfunction]
+#[pyo3(signature = (path = None, *, text = None))]
+pub fn events(path: Option<PathBuf>, text: Option<String>) -> PyResult<EventIterator> {
+    let tokens = match Source::new("events", path, text)? {
+        Source::Text(text) => TokenReader::new(text, None),
+        Source::File(path) => {
+            let reader = BufReader::new(crate::compression::open(&path)?);
+            TokenReader::new(String::new(), Some(reader))
+        }
+    };
+    Ok(EventIterator {
+        tokens,
+        stack: vec![],
+        expect: Expect::Start,
+    })
+}
+
+/// Iterator returned by `events`.
+#[pyclass(module = "pyron")]
+pub struct EventIterator {
+    tokens: TokenReader,
+    stack: Vec<Frame>,
+    expect: Expect,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Frame {
+    Struct,
+    Tuple,
+    Seq,
+    /// `key` is whether the next value is a key rather than a value.
+    Map {
+        key: bool,
+    },
+    Some,
+}
+
+impl Frame {
+    fn close(self) -> char {
+        match self {
+            Frame::Seq => ']',
+            Frame::Map { .. } => '}',
+            _ => ')',
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Expect {
+    /// Attributes or the top-level value.
+    Start,
+    Value,
+    /// An item of the innermost container, or its closing delimiter.
+    Item,
+    /// A separator or closing delimiter after an item.
+    AfterItem,
+    End,
+    Done,
+}
+
+enum Event {
+    StartStruct(Option<String>),
+    EndStruct,
+    StartTuple(Option<String>),
+    EndTuple,
+    StartSeq,
+    EndSeq,
+    StartMap,
+    EndMap,
+    Key(String),
+    Scalar(String),
+    Include(String),
+}
+
+#[pymethods]
+impl EventIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        let event = match self.next_event()? {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+        let none = py.None();
+        let (kind, value) = match event {
+            Event::StartStruct(name) => ("start_struct", name.into_py(py)),
+            Event::EndStruct => ("end_struct", none),
+            Event::StartTuple(name) => ("start_tuple", name.into_py(py)),
+            Event::EndTuple => ("end_tuple", none),
+            Event::StartSeq => ("start_seq", none),
+            Event::EndSeq => ("end_seq", none),
+            Event::StartMap => ("start_map", none),
+            Event::EndMap => ("end_map", none),
+            Event::Key(key) => ("key", key.into_py(py)),
+            Event::Scalar(text) => ("scalar", scalar(py, &text)?),
+            Event::Include(path) => ("include", scalar(py, &path)?),
+        };
+        Ok(Some((kind, value).into_py(py)))
+    }
+}
+
+impl EventIterator {
+    fn next_event(&mut self) -> PyResult<Option<Event>> {
+        loop {
+            match self.expect {
+                Expect::Start => {
+                    let first = self.tokens.peek(0)?.map(|t| t.kind);
+                    let second = self.tokens.peek(1)?.map(|t| t.kind);
+                    if first == Some(TokenKind::Punct('#')) && second == Some(TokenKind::Punct('!'))
+                    {
+                        self.skip_attribute()?;
+                    } else {
+                        self.expect = Expect::Value;
+                    }
+                }
+                Expect::Value => return self.value().map(Some),
+                Expect::Item => {
+                    let frame = *self.stack.last().expect("items are inside a container");
+                    let token = self.tokens.peek(0)?.cloned();
+                    match token {
+                        Some(token) if token.kind == TokenKind::Punct(frame.close()) => {
+                            self.tokens.next()?;
+                            return Ok(Some(self.close()));
+                        }
+                        Some(token) if frame == Frame::Struct => {
+                            if token.kind != TokenKind::Ident {
+                                return Err(self.tokens.error(&token, "expected a field name"));
+                            }
+                            self.tokens.next()?;
+                            self.tokens.expect(':')?;
+                            self.expect = Expect::Value;
+                            return Ok(Some(Event::Key(token.text)));
+                        }
+                        Some(_) => self.expect = Expect::Value,
+                        None => {
+                            return Err(self.tokens.eof(&format!("expected `{}`", frame.close())))
+                        }
+                    }
+                }
+                Expect::AfterItem => {
+                    let frame = self.stack.last_mut().expect("items are inside a container");
+                    let close = frame.close();
+                    let token = self.tokens.next()?;
+                    match token {
+                        Some(token) if *frame == Frame::Map { key: true } => {
+                            if token.kind != TokenKind::Punct(':') {
+                                return Err(self.tokens.error(&token, "expected `:`"));
+                            }
+                            *frame = Frame::Map { key: false };
+                            self.expect = Expect::Value;
+                        }
+                        Some(token) if token.kind == TokenKind::Punct(close) => {
+                            if *frame == Frame::Some {
+                                self.stack.pop();
+                                self.after_value();
+                            } else {
+                                return Ok(Some(self.close()));
+                            }
+                        }
+                        Some(token)
+                            if token.kind == TokenKind::Punct(',') && *frame != Frame::Some =>
+                        {
+                            if let Frame::Map { key } = frame {
+                                *key = true;
+                            }
+                            self.expect = Expect::Item;
+                        }
+                        Some(token) => {
+                            let message = format!("expected `,` or `{}`", close);
+                            return Err(self.tokens.error(&token, &message));
+                        }
+                        None => {
+                            let message = format!("expected `,` or `{}`", close);
+                            return Err(self.tokens.eof(&message));
+                        }
+                    }
+                }
+                Expect::End => match self.tokens.next()? {
+                    Some(token) => {
+                        return Err(self.tokens.error(&token, "expected end of document"));
+                    }
+                    None => self.expect = Expect::Done,
+                },
+                Expect::Done => return Ok(None),
+            }
+        }
+    }
+
+    fn skip_attribute(&mut self) -> PyResult<()> {
+        self.tokens.next()?;
+        self.tokens.next()?;
+        let mut depth = 0;
+        while let Some(token) = self.tokens.next()? {
+            match token.kind {
+                TokenKind::Punct('[') => depth += 1,
+                TokenKind::Punct(']') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(self.tokens.eof("expected `]`"))
+    }
+
+    /// Parses the start of a value, returning the event for a scalar or the start of a container.
+    fn value(&mut self) -> PyResult<Event> {
+        let token = match self.tokens.next()? {
+            Some(token) => token,
+            None => return Err(self.tokens.eof("expected a value")),
+        };
+        match token.kind {
+            TokenKind::Punct('[') => Ok(self.open(Frame::Seq, Event::StartSeq)),
+            TokenKind::Punct('{') => Ok(self.open(Frame::Map { key: true }, Event::StartMap)),
+            TokenKind::Punct('(') => self.parens(None),
+            TokenKind::Ident => {
+                if self.tokens.peek(0)?.map(|t| t.kind) != Some(TokenKind::Punct('(')) {
+                    self.after_value();
+                    return Ok(Event::Scalar(token.text));
+                }
+                self.tokens.next()?;
+                if token.text == "Some" {
+                    self.stack.push(Frame::Some);
+                    self.value()
+                } else {
+                    self.parens(Some(token.text))
+                }
+            }
+            TokenKind::String | TokenKind::Char | TokenKind::Number => {
+                self.after_value();
+                Ok(Event::Scalar(token.text))
+            }
+            TokenKind::Punct('-') | TokenKind::Punct('+') => match self.tokens.next()? {
+                Some(next) if matches!(next.kind, TokenKind::Number | TokenKind::Ident) => {
+                    self.after_value();
+                    Ok(Event::Scalar(format!("{}{}", token.text, next.text)))
+                }
+                Some(next) => Err(self.tokens.error(&next, "expected a number")),
+                None => Err(self.tokens.eof("expected a number")),
+            },
+            TokenKind::Punct('#') => {
+                match self.tokens.next()? {
+                    Some(next) if next.text == "include" => {}
+                    Some(next) => return Err(self.tokens.error(&next, "expected `include`")),
+                    None => return Err(self.tokens.eof("expected `include`")),
+                }
+                self.tokens.expect('(')?;
+                let path = match self.tokens.next()? {
+                    Some(path) if path.kind == TokenKind::String => path,
+                    Some(path) => return Err(self.tokens.error(&path, "expected a string")),
+                    None => return Err(self.tokens.eof("expected a string")),
+                };
+                self.tokens.expect(')')?;
+                self.after_value();
+                Ok(Event::Include(path.text))
+            }
+            _ => Err(self.tokens.error(&token, "expected a value")),
+        }
+    }
+
+    /// Parses the contents of parentheses as a struct if they start with `ident:`, and as a tuple
+    /// otherwise, like `document::Parser::parens`. The opening parenthesis has been consumed.
+    fn parens(&mut self, name: Option<String>) -> PyResult<Event> {
+        let first = self.tokens.peek(0)?.map(|t| t.kind);
+        let second = self.tokens.peek(1)?.map(|t| t.kind);
+        match (first, second) {
+            (Some(TokenKind::Punct(')')), _) if name.is_none() => {
+                self.tokens.next()?;
+                self.after_value();
+                Ok(Event::Scalar("()".into()))
+            }
+            (Some(TokenKind::Punct(')')), _)
+            | (Some(TokenKind::Ident), Some(TokenKind::Punct(':'))) => {
+                Ok(self.open(Frame::Struct, Event::StartStruct(name)))
+            }
+            _ => Ok(self.open(Frame::Tuple, Event::StartTuple(name))),
+        }
+    }
+
+    fn open(&mut self, frame: Frame, event: Event) -> Event {
+        self.stack.push(frame);
+        self.expect = Expect::Item;
+        event
+    }
+
+    /// Pops the innermost container, whose closing delimiter has been consumed.
+    fn close(&mut self) -> Event {
+        let event = match self.stack.pop() {
+            Some(Frame::Struct) => Event::EndStruct,
+            Some(Frame::Tuple) => Event::EndTuple,
+            Some(Frame::Seq) => Event::EndSeq,
+            Some(Frame::Map { .. }) => Event::EndMap,
+            _ => unreachable!("`Some` frames are closed without an event"),
+        };
+        self.after_value();
+        event
+    }
+
+    fn after_value(&mut self) {
+        self.expect = if self.stack.is_empty() {
+            Expect::End
+        } else {
+            Expect::AfterItem
+        };
+    }
+}
+
+/// Converts the text of a scalar to Python, handling the common cases without invoking the parser.
+fn scalar(py: Python, text: &str) -> PyResult<PyObject> {
+    match text {
+        "true" => return Ok(true.into_py(py)),
+        "false" => return Ok(false.into_py(py)),
+        "None" => return Ok(py.None()),
+        _ => {}
+    }
+    if let Ok(i) = text.parse::<i64>() {
+        return Ok(i.into_py(py));
+    }
+    if text.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+') {
+        if let Ok(f) = text.parse::<f64>() {
+            return Ok(f.into_py(py));
+        }
+    }
+    if text.len() >= 2 && text.starts_with('"') && !text.contains('\\') {
+        return Ok(text[1..text.len() - 1].into_py(py));
+    }
+    match ron_parser::parse(text, None) {
+        Ok(value) => Converter::new(py).convert(&value),
+        Err(_) => Err(exceptions::PyValueError::new_err(format!(
+            "Invalid RON: invalid value `{}`",
+            text
+        ))),
+    }
+}
+
+#[derive(Clone)]
+struct OwnedToken {
+    kind: TokenKind,
+    text: String,
+    line: usize,
+}
+
+/// Lexes non-trivia tokens from a string, or from a file that is read line by line.
+struct TokenReader {
+    buffer: String,
+    pos: usize,
+    /// Line number of `buffer[pos]`.
+    line: usize,
+    reader: Option<BufReader<Box<dyn Read + Send>>>,
+    lookahead: VecDeque<OwnedToken>,
+}
+
+impl TokenReader {
+    fn new(buffer: String, reader: Option<BufReader<Box<dyn Read + Send>>>) -> TokenReader {
+        TokenReader {
+            buffer,
+            pos: 0,
+            line: 1,
+            reader,
+            lookahead: VecDeque::new(),
+        }
+    }
+
+    fn peek(&mut self, n: usize) -> PyResult<Option<&OwnedToken>> {
+        while self.lookahead.len() <= n {
+            match self.lex()? {
+                Some(token) => self.lookahead.push_back(token),
+                None => break,
+            }
+        }
+        Ok(self.lookahead.get(n))
+    }
+
+    fn next(&mut self) -> PyResult<Option<OwnedToken>> {
+        match self.lookahead.pop_front() {
+            Some(token) => Ok(Some(token)),
+            None => self.lex(),
+        }
+    }
+
+    fn expect(&mut self, c: char) -> PyResult<OwnedToken> {
+        match self.next()? {
+            Some(token) if token.kind == TokenKind::Punct(c) => Ok(token),
+            Some(token) => Err(self.error(&token, &format!("expected `{}`", c))),
+            None => Err(self.eof(&format!("expected `{}`", c))),
+        }
+    }
+
+    fn lex(&mut self) -> PyResult<Option<OwnedToken>> {
+        loop {
+            if self.pos == self.buffer.len() && !self.fill()? {
+                return Ok(None);
+            }
+            let (kind, end) = lexer::next_token(&self.buffer, self.pos);
+            // A token that reaches the end of the buffer might continue on the next line.
+            if end == self.buffer.len() && self.fill()? {
+                continue;
+            }
+            let text = &self.buffer[self.pos..end];
+            let token = OwnedToken {
+                kind,
+                text: text.to_string(),
+                line: self.line,
+            };
+            self.line += text.matches('\n').count();
+            self.pos = end;
+            if !matches!(
+                kind,
+                TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment
+            ) {
+                return Ok(Some(token));
+            }
+        }
+    }
+
+    /// Appends the next line of the file to the buffer, dropping the lexed part of the buffer.
+    /// Returns false at the end of the input.
+    fn fill(&mut self) -> PyResult<bool> {
+        let reader = match &mut self.reader {
+            Some(reader) => reader,
+            None => return Ok(false),
+        };
+        self.buffer.drain(..self.pos);
+        self.pos = 0;
+        if reader.read_line(&mut self.buffer)? == 0 {
+            self.reader = None;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn error(&self, token: &OwnedToken, message: &str) -> PyErr {
+        exceptions::PyValueError::new_err(format!(
+            "Invalid RON on line {}: {}, found `{}`",
+            token.line, message, token.text
+        ))
+    }
+
+    fn eof(&self, message: &str) -> PyErr {
+        exceptions::PyValueError::new_err(format!(
+            "Invalid RON: {}, found end of document",
+            message
+        ))
+    }
+}
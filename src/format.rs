@@ -0,0 +1,260 @@
+use pyo3::prelude::*;
+
+use crate::lexer::{self, Token, TokenKind};
+
+/// Rewrites named structs whose fields are `_0`, `_1`, ... in order, which is how tuple structs
+/// like `Point(1, 2)` are loaded, back into tuple structs by removing the field names.
+pub fn positional_structs(text: &str) -> String {
+    let tokens = lexer::tokenize(text);
+    // For every open bracket, whether it opens a named struct and the byte ranges of the field
+    // names (including the `:` and the whitespace after it) found in it so far, or `None` once a
+    // field that breaks the `_0`, `_1`, ... sequence was found.
+    let mut frames: Vec<(bool, Option<Vec<(usize, usize)>>)> = vec![];
+    let mut removed = vec![];
+    let mut previous = None;
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::Punct('(') | TokenKind::Punct('[') | TokenKind::Punct('{') => {
+                let named = token.kind == TokenKind::Punct('(')
+                    && matches!(previous, Some(TokenKind::Ident));
+                frames.push((named, Some(vec![])));
+            }
+            TokenKind::Punct(')') | TokenKind::Punct(']') | TokenKind::Punct('}') => {
+                if let Some((true, Some(fields))) = frames.pop() {
+                    removed.extend(fields);
+                }
+            }
+            TokenKind::Ident => {
+                let next = tokens[i + 1..].iter().find(|token| !token.is_trivia());
+                let colon = match next {
+                    Some(next) if next.kind == TokenKind::Punct(':') => next,
+                    _ => {
+                        previous = Some(token.kind);
+                        continue;
+                    }
+                };
+                if let Some((true, fields)) = frames.last_mut() {
+                    let index = fields.as_ref().map_or(0, |fields| fields.len());
+                    if token.text(text) == format!("_{}", index) {
+                        let end = colon.end + text[colon.end..].len()
+                            - text[colon.end..].trim_start_matches(' ').len();
+                        if let Some(fields) = fields {
+                            fields.push((token.start, end));
+                        }
+                    } else {
+                        *fields = None;
+                    }
+                }
+            }
+            _ => {}
+        }
+        if !token.is_trivia() {
+            previous = Some(token.kind);
+        }
+    }
+    if removed.is_empty() {
+        return text.to_string();
+    }
+    removed.sort_unstable();
+    let mut result = String::with_capacity(text.len());
+    let mut start = 0;
+    for (from, to) in removed {
+        result.push_str(&text[start..from]);
+        start = to;
+    }
+    result.push_str(&text[start..]);
+    result
+}
+
+/// Removes the commas after the last element of structs, tuples, sequences and maps.
+pub fn remove_trailing_commas(text: &str) -> String {
+    let tokens = lexer::tokenize(text);
+    let mut result = String::with_capacity(text.len());
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind == TokenKind::Punct(',') {
+            let next = tokens[i + 1..].iter().find(|token| !token.is_trivia());
+            if next.map_or(false, |next| is_close(next.kind)) {
+                continue;
+            }
+        }
+        result.push_str(token.text(text));
+    }
+    result
+}
+
+/// The number of columns a tab counts as, as in `rustfmt`.
+const TAB_WIDTH: usize = 4;
+
+/// Writes structs, tuples, sequences and maps on one line if they fit within `max_width` columns,
+/// counting the text before them on their line and the comma after them. Collections are tried
+/// from the outside in, so that the outermost collection that fits is inlined as a whole.
+pub fn inline_collections(text: &str, max_width: usize) -> String {
+    let tokens = lexer::tokenize(text);
+    // The index of the matching close bracket of every open bracket.
+    let mut closes = vec![None; tokens.len()];
+    let mut opens = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::Punct('(') | TokenKind::Punct('[') | TokenKind::Punct('{') => opens.push(i),
+            kind if is_close(kind) => {
+                if let Some(open) = opens.pop() {
+                    closes[open] = Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Some(close) = closes[i] {
+            if let Some(line) = single_line(text, &tokens[i..=close]) {
+                let line_start = result.rfind('\n').map_or(0, |newline| newline + 1);
+                let column = width(&result[line_start..]);
+                let comma = matches!(
+                    tokens[close + 1..].iter().find(|token| !token.is_trivia()),
+                    Some(token) if token.kind == TokenKind::Punct(',')
+                );
+                if column + width(&line) + comma as usize <= max_width {
+                    result.push_str(&line);
+                    i = close + 1;
+                    continue;
+                }
+            }
+        }
+        result.push_str(tokens[i].text(text));
+        i += 1;
+    }
+    result
+}
+
+/// The number of columns taken up by text on one line, with tabs counting as `TAB_WIDTH`.
+fn width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+/// Pads the fields of structs and the entries of maps that are written one per line, so that
+/// their values start in the same column, as in a table.
+pub fn align_fields(text: &str) -> String {
+    let tokens = lexer::tokenize(text);
+    // For every open bracket, the offsets of the colons after the keys that start a line, with the
+    // width of the key and colon.
+    let mut frames: Vec<Vec<(usize, usize)>> = vec![];
+    let mut padding = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::Punct('(') | TokenKind::Punct('[') | TokenKind::Punct('{') => {
+                frames.push(vec![])
+            }
+            kind if is_close(kind) => {
+                let colons = frames.pop().unwrap_or_default();
+                if colons.len() > 1 {
+                    let widest = colons.iter().map(|&(_, width)| width).max().unwrap_or(0);
+                    padding.extend(colons.iter().map(|&(end, width)| (end, widest - width)));
+                }
+            }
+            TokenKind::Punct(':') => {
+                let key = match tokens[..i].iter().rev().find(|token| !token.is_trivia()) {
+                    Some(key) if matches!(key.kind, TokenKind::Ident | TokenKind::String) => key,
+                    _ => continue,
+                };
+                let before = &text[..key.start];
+                let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+                if !text[line_start..key.start].trim().is_empty() {
+                    continue;
+                }
+                if let Some(colons) = frames.last_mut() {
+                    let width = text[key.start..token.end].chars().count();
+                    colons.push((token.end, width));
+                }
+            }
+            _ => {}
+        }
+    }
+    if padding.is_empty() {
+        return text.to_string();
+    }
+    padding.sort_unstable();
+    let mut result = String::with_capacity(text.len());
+    let mut start = 0;
+    for (end, pad) in padding {
+        result.push_str(&text[start..end]);
+        result.extend(std::iter::repeat(' ').take(pad));
+        start = end;
+    }
+    result.push_str(&text[start..]);
+    result
+}
+
+/// Serializes a value to RON on a single line.
+pub fn inline(py: Python, value: &PyAny) -> PyResult<String> {
+    let text = crate::serialize(py, value)?;
+    let tokens = lexer::tokenize(&text);
+    // Serialized values contain neither comments nor line breaks within strings.
+    Ok(single_line(&text, &tokens).unwrap_or(text))
+}
+
+/// Joins the tokens of a collection into one line without a trailing comma, or returns `None` if
+/// they include comments or line breaks within strings, which can't be moved onto one line.
+fn single_line(text: &str, tokens: &[Token]) -> Option<String> {
+    let tokens = tokens
+        .iter()
+        .filter(|token| token.kind != TokenKind::Whitespace)
+        .collect::<Vec<_>>();
+    let mut line = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let token_text = token.text(text);
+        if token.is_trivia() || token_text.contains('\n') {
+            return None;
+        }
+        match token.kind {
+            TokenKind::Punct(',') => {
+                if !tokens.get(i + 1).map_or(false, |next| is_close(next.kind)) {
+                    line.push_str(", ");
+                }
+            }
+            TokenKind::Punct(':') => line.push_str(": "),
+            _ => line.push_str(token_text),
+        }
+    }
+    Some(line)
+}
+
+/// Whether a token closes a struct, tuple, sequence or map.
+fn is_close(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Punct(')') | TokenKind::Punct(']') | TokenKind::Punct('}')
+    )
+}
+
+/// Replaces string literals consisting of `prefix` (the serialized form of a marker, including the
+/// opening quote) followed by an index into `numbers` with that number literal.
+pub fn number_literals(text: &str, prefix: &str, numbers: &[String]) -> String {
+    let mut result = String::with_capacity(text.len());
+    for token in lexer::tokenize(text) {
+        let literal = token.text(text);
+        let number = match literal.strip_prefix(prefix) {
+            Some(rest) if token.kind == TokenKind::String => rest
+                .strip_suffix('"')
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| numbers.get(index)),
+            _ => None,
+        };
+        match number {
+            Some(number) if is_number(number) => result.push_str(number),
+            _ => result.push_str(literal),
+        }
+    }
+    result
+}
+
+/// Whether text can be written as a number literal without changing the surrounding syntax.
+fn is_number(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | '-'))
+}
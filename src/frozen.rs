@@ -0,0 +1,45 @@
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyTuple};
+
+/// Returns a read-only copy of a loaded value for `load` with `frozen`, with lists replaced by
+/// tuples and dicts by `types.MappingProxyType`s. Namedtuples keep their class, and instances of
+/// registered classes are returned as is.
+pub fn freeze(py: Python, value: &PyAny) -> PyResult<PyObject> {
+    if let Ok(dict) = value.downcast::<PyDict>() {
+        let frozen = PyDict::new(py);
+        for (key, value) in dict {
+            frozen.set_item(key, freeze(py, value)?)?;
+        }
+        Ok(mapping_proxy(py)?.call1((frozen,))?.into())
+    } else if let Ok(list) = value.downcast::<PyList>() {
+        let items = list
+            .iter()
+            .map(|value| freeze(py, value))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(PyTuple::new(py, items).into())
+    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+        let items = tuple
+            .iter()
+            .map(|value| freeze(py, value))
+            .collect::<PyResult<Vec<_>>>()?;
+        if crate::is_namedtuple(tuple) {
+            Ok(tuple.get_type().call1(PyTuple::new(py, items))?.into())
+        } else {
+            Ok(PyTuple::new(py, items).into())
+        }
+    } else {
+        Ok(value.into())
+    }
+}
+
+/// Returns the contents of a `types.MappingProxyType`, or `None` for other values.
+pub fn proxied(py: Python, value: &PyAny) -> PyResult<Option<&PyDict>> {
+    if !value.is_instance(mapping_proxy(py)?)? {
+        return Ok(None);
+    }
+    Ok(Some(value.call_method0("copy")?.downcast::<PyDict>()?))
+}
+
+fn mapping_proxy(py: Python) -> PyResult<&PyAny> {
+    PyModule::import(py, "types")?.getattr("MappingProxyType")
+}
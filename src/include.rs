@@ -0,0 +1,343 @@
+use std::path::{Path, PathBuf};
+
+use pyo3::{exceptions, PyObject, PyResult, Python};
+
+/// Locates the sources referenced by `#include("...")` directives and keeps track of the chain of
+/// includes currently being converted.
+pub struct IncludeResolver {
+    search_paths: Vec<PathBuf>,
+    /// Python callable mapping an include path to its text, used instead of the filesystem.
+    callback: Option<PyObject>,
+    /// URL includes are only permitted if they start with one of these prefixes.
+    url_prefixes: Vec<String>,
+    /// Python callable mapping an allowed URL to its text.
+    url_fetcher: Option<PyObject>,
+    /// When false, any include directive is an error and the filesystem is never touched.
+    allowed: bool,
+    /// Canonical paths of the files (or the include paths passed to `callback`, or URLs) being
+    /// converted, innermost include last.
+    stack: Vec<PathBuf>,
+    /// Canonical paths of all files that were read, in the order they were first entered.
+    files: Vec<PathBuf>,
+    /// Whether any source was supplied by `callback` or fetched from a URL.
+    read_non_files: bool,
+}
+
+impl IncludeResolver {
+    pub fn new(
+        search_paths: Vec<PathBuf>,
+        callback: Option<PyObject>,
+        url_prefixes: Vec<String>,
+        url_fetcher: Option<PyObject>,
+        allowed: bool,
+    ) -> IncludeResolver {
+        IncludeResolver {
+            search_paths,
+            callback,
+            url_prefixes,
+            url_fetcher,
+            allowed,
+            stack: vec![],
+            files: vec![],
+            read_non_files: false,
+        }
+    }
+
+    /// Returns a display name and the text of the source referenced by `include`, and pushes it
+    /// onto the include chain. Callers must call `exit` once they are done with the include.
+    pub fn open(&mut self, py: Python, include: &str) -> PyResult<(String, String)> {
+        self.check_allowed(include)?;
+        if let Some(callback) = &self.callback {
+            let callback = callback.clone_ref(py);
+            self.enter(PathBuf::from(include))?;
+            self.read_non_files = true;
+            let source = callback.call1(py, (include,))?.extract::<String>(py)?;
+            Ok((include.to_string(), source))
+        } else if let Some(url) = self.url(include) {
+            self.open_url(py, &url)
+        } else if self.in_url() {
+            // A document from a URL must not be able to read local files.
+            Err(exceptions::PyValueError::new_err(format!(
+                "#include(\"{}\") in a document from a URL must be a relative path or a URL",
+                include
+            )))
+        } else {
+            let file = self.resolve(include)?;
+            self.open_file(&file)
+        }
+    }
+
+    /// Returns the URL referenced by `include`, if any. Relative includes inside a document that
+    /// was itself fetched from a URL are resolved against that URL.
+    fn url(&self, include: &str) -> Option<String> {
+        if is_url(include) {
+            return Some(include.to_string());
+        }
+        if Path::new(include).is_absolute() {
+            return None;
+        }
+        let base = self.stack.last()?.to_str().filter(|base| is_url(base))?;
+        let dir = &base[..base.rfind('/')?];
+        Some(format!("{}/{}", dir, include))
+    }
+
+    /// Whether the innermost document being converted was fetched from a URL.
+    fn in_url(&self) -> bool {
+        self.stack
+            .last()
+            .and_then(|key| key.to_str())
+            .map_or(false, is_url)
+    }
+
+    fn open_url(&mut self, py: Python, url: &str) -> PyResult<(String, String)> {
+        // `..` segments are resolved first, so that they can't lead out of an allowed prefix.
+        let url = &normalize_url(url);
+        if !self
+            .url_prefixes
+            .iter()
+            .any(|prefix| url_matches(url, prefix))
+        {
+            return Err(exceptions::PyValueError::new_err(format!(
+                "#include(\"{}\") does not match any of the allowed URL prefixes",
+                url
+            )));
+        }
+        let fetcher = match &self.url_fetcher {
+            Some(fetcher) => fetcher.clone_ref(py),
+            None => {
+                return Err(exceptions::PyValueError::new_err(format!(
+                    "#include(\"{}\") requires a url_fetcher",
+                    url
+                )))
+            }
+        };
+        self.enter(PathBuf::from(url))?;
+        self.read_non_files = true;
+        let source = fetcher.call1(py, (url,))?.extract::<String>(py)?;
+        Ok((url.to_string(), source))
+    }
+
+    /// Like `open`, but for a file that has already been resolved.
+    pub fn open_file(&mut self, file: &Path) -> PyResult<(String, String)> {
+        self.enter_file(file)?;
+        let source = crate::compression::read_to_string(file)?;
+        Ok((file.display().to_string(), source))
+    }
+
+    /// Whether `include` is a glob pattern that should be expanded with `expand`. Includes
+    /// supplied by a resolver callback are never expanded.
+    pub fn is_glob(&self, include: &str) -> bool {
+        self.callback.is_none() && !is_url(include) && include.contains(&['*', '?', '['][..])
+    }
+
+    /// Expands a glob pattern into the matching files in lexical order. Relative patterns are
+    /// matched against the directories that `resolve` would search, stopping at the first one
+    /// that contains any matches.
+    pub fn expand(&self, pattern: &str) -> PyResult<Vec<PathBuf>> {
+        self.check_allowed(pattern)?;
+        if self.in_url() {
+            return Err(exceptions::PyValueError::new_err(format!(
+                "#include(\"{}\") in a document fetched from a URL can't be a glob pattern",
+                pattern
+            )));
+        }
+        let patterns = if Path::new(pattern).is_absolute() {
+            vec![pattern.to_string()]
+        } else {
+            self.search_dirs()
+                .filter_map(|dir| dir.to_str())
+                .map(|dir| {
+                    Path::new(&glob::Pattern::escape(dir))
+                        .join(pattern)
+                        .display()
+                        .to_string()
+                })
+                .collect()
+        };
+        for pattern in patterns {
+            let paths = glob::glob(&pattern)
+                .map_err(|e| exceptions::PyValueError::new_err(format!("{}", e)))?;
+            let mut files = vec![];
+            for path in paths {
+                let path = path.map_err(|e| exceptions::PyIOError::new_err(format!("{}", e)))?;
+                if path.is_file() {
+                    files.push(path);
+                }
+            }
+            if !files.is_empty() {
+                files.sort();
+                return Ok(files);
+            }
+        }
+        Err(exceptions::PyValueError::new_err(format!(
+            "#include(\"{}\") did not match any files",
+            pattern
+        )))
+    }
+
+    fn check_allowed(&self, include: &str) -> PyResult<()> {
+        if self.allowed {
+            Ok(())
+        } else {
+            Err(exceptions::PyValueError::new_err(format!(
+                "Found #include(\"{}\") directive but includes are disabled (allow_includes=False)",
+                include
+            )))
+        }
+    }
+
+    fn search_dirs(&self) -> impl Iterator<Item = &Path> {
+        self.stack
+            .last()
+            .and_then(|file| file.parent())
+            .into_iter()
+            .chain(self.search_paths.iter().map(PathBuf::as_path))
+    }
+
+    /// Relative includes are looked up next to the including file first and then in each
+    /// of the search paths, in order.
+    fn resolve(&self, include: &str) -> PyResult<PathBuf> {
+        let path = Path::new(include);
+        if path.is_absolute() {
+            if path.is_file() {
+                return Ok(path.to_path_buf());
+            }
+        } else {
+            for dir in self.search_dirs() {
+                let candidate = dir.join(path);
+                if candidate.is_file() {
+                    return Ok(candidate);
+                }
+            }
+        }
+        Err(exceptions::PyValueError::new_err(format!(
+            "Unresolved #include(\"{}\") directive",
+            include
+        )))
+    }
+
+    /// Pushes `file` onto the include chain, failing if it is already being included.
+    pub fn enter_file(&mut self, file: &Path) -> PyResult<()> {
+        let file = file.canonicalize()?;
+        if !self.files.contains(&file) {
+            self.files.push(file.clone());
+        }
+        self.enter(file)
+    }
+
+    /// The files that were read so far, or `None` if some sources weren't files.
+    pub fn files(&self) -> Option<&[PathBuf]> {
+        if self.read_non_files {
+            None
+        } else {
+            Some(&self.files)
+        }
+    }
+
+    fn enter(&mut self, key: PathBuf) -> PyResult<()> {
+        if let Some(start) = self.stack.iter().position(|k| *k == key) {
+            let chain = self.stack[start..]
+                .iter()
+                .chain(std::iter::once(&key))
+                .map(|k| k.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(exceptions::PyValueError::new_err(format!(
+                "Include cycle detected: {}",
+                chain
+            )));
+        }
+        self.stack.push(key);
+        Ok(())
+    }
+
+    pub fn exit(&mut self) {
+        self.stack.pop();
+    }
+}
+
+fn is_url(include: &str) -> bool {
+    include.contains("://")
+}
+
+/// Whether `url` is under an allowed URL `prefix`: it has the same scheme, host and port, and its
+/// path is the path of `prefix` or below it, so that `https://configs.internal` allows neither
+/// `https://configs.internal.example.com` nor `https://configs.internal@example.com`.
+fn url_matches(url: &str, prefix: &str) -> bool {
+    let (url, prefix) = match (UrlParts::parse(url), UrlParts::parse(prefix)) {
+        (Some(url), Some(prefix)) => (url, prefix),
+        _ => return false,
+    };
+    let prefix_path = prefix.path.trim_end_matches('/');
+    url.scheme == prefix.scheme
+        && url.host == prefix.host
+        && url.port == prefix.port
+        && url.path.strip_prefix(prefix_path).map_or(false, |rest| {
+            rest.is_empty() || rest.starts_with(&['/', '?', '#'][..])
+        })
+}
+
+/// The parts of a URL that `url_matches` compares.
+struct UrlParts<'a> {
+    /// Lowercased, as is `host`.
+    scheme: String,
+    host: String,
+    /// The explicit port, or the default port of `http` and `https`.
+    port: String,
+    /// Everything after the authority, which is empty or starts with `/`, `?` or `#`.
+    path: &'a str,
+}
+
+impl<'a> UrlParts<'a> {
+    fn parse(url: &'a str) -> Option<UrlParts<'a>> {
+        let (scheme, rest) = url.split_once("://")?;
+        let authority_end = rest
+            .find(|c| matches!(c, '/' | '?' | '#'))
+            .unwrap_or(rest.len());
+        let (authority, path) = rest.split_at(authority_end);
+        // Credentials before `@` don't change which host is contacted.
+        let host = authority
+            .rsplit_once('@')
+            .map_or(authority, |(_, host)| host);
+        // The colons of IPv6 addresses are inside brackets.
+        let (host, port) = match host.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => (host, port),
+            _ => (host, ""),
+        };
+        let scheme = scheme.to_ascii_lowercase();
+        let port = match (port, scheme.as_str()) {
+            ("", "http") => "80",
+            ("", "https") => "443",
+            (port, _) => port,
+        };
+        Some(UrlParts {
+            scheme,
+            host: host.to_ascii_lowercase(),
+            port: port.to_string(),
+            path,
+        })
+    }
+}
+
+/// Removes the `.` and `..` segments from the path of a URL. `..` segments at the root are
+/// dropped, as by browsers.
+fn normalize_url(url: &str) -> String {
+    let path_start = match url.find("://") {
+        Some(scheme_end) => match url[scheme_end + 3..].find('/') {
+            Some(authority_end) => scheme_end + 3 + authority_end,
+            None => return url.to_string(),
+        },
+        None => return url.to_string(),
+    };
+    let mut segments: Vec<&str> = vec![];
+    for segment in url[path_start + 1..].split('/') {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    format!("{}/{}", &url[..path_start], segments.join("/"))
+}
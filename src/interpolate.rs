@@ -0,0 +1,377 @@
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyList, PyString, PyTuple};
+
+/// Replaces every `${NAME}` in `s` with the value of the environment variable `NAME`. A default
+/// can be given as `${NAME:-default}`, and `$${` produces a literal `${`.
+pub fn interpolate_env(s: &str) -> PyResult<String> {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            result.push_str(&rest[..start - 1]);
+            result.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        result.push_str(&rest[..start]);
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => {
+                return Err(exceptions::PyValueError::new_err(format!(
+                    "Unterminated \"${{\" in string \"{}\"",
+                    s
+                )))
+            }
+        };
+        let expr = &rest[start + 2..end];
+        let (name, default) = match expr.find(":-") {
+            Some(i) => (&expr[..i], Some(&expr[i + 2..])),
+            None => (expr, None),
+        };
+        match (std::env::var(name), default) {
+            (Ok(value), _) => result.push_str(&value),
+            (Err(_), Some(default)) => result.push_str(default),
+            (Err(_), None) => {
+                return Err(exceptions::PyValueError::new_err(format!(
+                    "Environment variable {} referenced in \"{}\" is not set",
+                    name, s
+                )))
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Resolves `${path.to.value}` references to other values of the document `root`. A string that
+/// consists of a single reference is replaced by the referenced value, a string combining numeric
+/// references with arithmetic (`"${model.hidden_size} * 4"`) is evaluated, and any other string is
+/// interpolated, with `$${` producing a literal `${`. If `env_fallback` is set, references that
+/// don't exist in the document are looked up as environment variables instead.
+pub fn resolve_references(py: Python, root: &PyAny, env_fallback: bool) -> PyResult<PyObject> {
+    References {
+        root,
+        env_fallback,
+        stack: vec![],
+    }
+    .resolve(py, root)
+}
+
+struct References<'py> {
+    root: &'py PyAny,
+    env_fallback: bool,
+    /// References currently being resolved, used to detect cycles.
+    stack: Vec<String>,
+}
+
+impl<'py> References<'py> {
+    fn resolve(&mut self, py: Python<'py>, value: &'py PyAny) -> PyResult<PyObject> {
+        if let Ok(s) = value.downcast::<PyString>() {
+            let s = s.to_str()?;
+            if s.contains("${") {
+                return self.interpolate(py, s);
+            }
+        } else if let Ok(dict) = value.downcast::<PyDict>() {
+            let resolved = PyDict::new(py);
+            for (key, value) in dict {
+                resolved.set_item(key, self.resolve(py, value)?)?;
+            }
+            return Ok(resolved.into());
+        } else if let Ok(list) = value.downcast::<PyList>() {
+            let mut items = vec![];
+            for value in list {
+                items.push(self.resolve(py, value)?);
+            }
+            return Ok(PyList::new(py, items).into());
+        } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+            let mut items = vec![];
+            for value in tuple {
+                items.push(self.resolve(py, value)?);
+            }
+            return if crate::is_namedtuple(tuple) {
+                Ok(tuple.call_method1("_make", (items,))?.into())
+            } else {
+                Ok(PyTuple::new(py, items).into())
+            };
+        }
+        Ok(value.into())
+    }
+
+    fn interpolate(&mut self, py: Python<'py>, s: &str) -> PyResult<PyObject> {
+        let segments = split_references(s)?;
+        if let [Segment::Reference(path)] = segments[..] {
+            return self.lookup(py, path);
+        }
+        let mut values = vec![];
+        for segment in &segments {
+            values.push(match segment {
+                Segment::Text(_) => None,
+                Segment::Reference(path) => Some(self.lookup(py, path)?),
+            });
+        }
+        if let Some(number) = evaluate(py, &segments, &values) {
+            return Ok(number);
+        }
+        let mut result = String::new();
+        for (segment, value) in segments.iter().zip(values) {
+            match (segment, value) {
+                (Segment::Text(text), _) => result.push_str(text),
+                (_, Some(value)) => result.push_str(value.as_ref(py).str()?.to_str()?),
+                (_, None) => unreachable!(),
+            }
+        }
+        Ok(result.into_py(py))
+    }
+
+    fn lookup(&mut self, py: Python<'py>, path: &str) -> PyResult<PyObject> {
+        if let Some(start) = self.stack.iter().position(|p| p == path) {
+            let chain = self.stack[start..]
+                .iter()
+                .map(String::as_str)
+                .chain(std::iter::once(path))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(exceptions::PyValueError::new_err(format!(
+                "Reference cycle detected: {}",
+                chain
+            )));
+        }
+        let value = match crate::path::get(self.root, path)? {
+            Some(value) => value,
+            None if self.env_fallback => {
+                return Ok(interpolate_env(&format!("${{{}}}", path))?.into_py(py))
+            }
+            None => {
+                return Err(exceptions::PyValueError::new_err(format!(
+                    "Unresolved reference ${{{}}}",
+                    path
+                )))
+            }
+        };
+        self.stack.push(path.to_string());
+        let result = self.resolve(py, value);
+        self.stack.pop();
+        result
+    }
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Reference(&'a str),
+}
+
+fn split_references(s: &str) -> PyResult<Vec<Segment>> {
+    let mut segments = vec![];
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            segments.push(Segment::Text(&rest[..start - 1]));
+            segments.push(Segment::Text("${"));
+            rest = &rest[start + 2..];
+            continue;
+        }
+        if start > 0 {
+            segments.push(Segment::Text(&rest[..start]));
+        }
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => {
+                return Err(exceptions::PyValueError::new_err(format!(
+                    "Unterminated \"${{\" in string \"{}\"",
+                    s
+                )))
+            }
+        };
+        segments.push(Segment::Reference(rest[start + 2..end].trim()));
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    Ok(segments)
+}
+
+#[derive(Clone, Copy)]
+enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn float(self) -> f64 {
+        match self {
+            Number::Integer(i) => i as f64,
+            Number::Float(f) => f,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Token {
+    Number(Number),
+    Op(char),
+    FloorDiv,
+    Open,
+    Close,
+}
+
+/// Evaluates a string of numeric references combined with `+ - * / // %` and parentheses.
+/// Returns `None` if the string isn't such an expression.
+fn evaluate(py: Python, segments: &[Segment], values: &[Option<PyObject>]) -> Option<PyObject> {
+    let mut tokens = vec![];
+    for (segment, value) in segments.iter().zip(values) {
+        match (segment, value) {
+            (Segment::Text(text), _) => tokenize(text, &mut tokens)?,
+            (_, Some(value)) => {
+                let value = value.as_ref(py);
+                if value.downcast::<PyBool>().is_ok() {
+                    return None;
+                } else if let Ok(i) = value.extract::<i64>() {
+                    tokens.push(Token::Number(Number::Integer(i)));
+                } else if let Ok(f) = value.extract::<f64>() {
+                    tokens.push(Token::Number(Number::Float(f)));
+                } else {
+                    return None;
+                }
+            }
+            (_, None) => return None,
+        }
+    }
+    let mut parser = Parser { tokens, pos: 0 };
+    let result = parser.expr()?;
+    if parser.pos != parser.tokens.len() {
+        return None;
+    }
+    Some(match result {
+        Number::Integer(i) => i.into_py(py),
+        Number::Float(f) => f.into_py(py),
+    })
+}
+
+fn tokenize(text: &str, tokens: &mut Vec<Token>) -> Option<()> {
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            ' ' | '\t' => {}
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '/' if matches!(chars.peek(), Some((_, '/'))) => {
+                chars.next();
+                tokens.push(Token::FloorDiv);
+            }
+            '+' | '-' | '*' | '/' | '%' => tokens.push(Token::Op(c)),
+            '0'..='9' | '.' => {
+                let mut end = i + c.len_utf8();
+                while let Some(&(j, c)) = chars.peek() {
+                    let exponent_sign =
+                        (c == '-' || c == '+') && text[..j].ends_with(&['e', 'E'][..]);
+                    if c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign {
+                        end = j + c.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let literal = &text[i..end];
+                let number = match literal.parse::<i64>() {
+                    Ok(i) => Number::Integer(i),
+                    Err(_) => Number::Float(literal.parse::<f64>().ok()?),
+                };
+                tokens.push(Token::Number(number));
+            }
+            _ => return None,
+        }
+    }
+    Some(())
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).copied();
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn expr(&mut self) -> Option<Number> {
+        let mut lhs = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek() {
+            self.pos += 1;
+            lhs = apply(op, lhs, self.term()?)?;
+        }
+        Some(lhs)
+    }
+
+    fn term(&mut self) -> Option<Number> {
+        let mut lhs = self.factor()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Op(op @ ('*' | '/' | '%'))) => op,
+                Some(Token::FloorDiv) => 'f',
+                _ => return Some(lhs),
+            };
+            self.pos += 1;
+            lhs = apply(op, lhs, self.factor()?)?;
+        }
+    }
+
+    fn factor(&mut self) -> Option<Number> {
+        match self.next()? {
+            Token::Number(n) => Some(n),
+            Token::Op('-') => apply('-', Number::Integer(0), self.factor()?),
+            Token::Open => {
+                let n = self.expr()?;
+                match self.next()? {
+                    Token::Close => Some(n),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Applies a binary operator with Python semantics; `'f'` denotes floor division.
+fn apply(op: char, lhs: Number, rhs: Number) -> Option<Number> {
+    use Number::*;
+    Some(match (op, lhs, rhs) {
+        ('+', Integer(a), Integer(b)) => Integer(a.checked_add(b)?),
+        ('-', Integer(a), Integer(b)) => Integer(a.checked_sub(b)?),
+        ('*', Integer(a), Integer(b)) => Integer(a.checked_mul(b)?),
+        ('f', Integer(a), Integer(b)) if b != 0 => {
+            let (q, r) = (a.checked_div(b)?, a % b);
+            Integer(if r != 0 && (r < 0) != (b < 0) {
+                q - 1
+            } else {
+                q
+            })
+        }
+        ('%', Integer(a), Integer(b)) if b != 0 => {
+            let r = a.checked_rem(b)?;
+            Integer(if r != 0 && (r < 0) != (b < 0) {
+                r + b
+            } else {
+                r
+            })
+        }
+        ('+', a, b) => Float(a.float() + b.float()),
+        ('-', a, b) => Float(a.float() - b.float()),
+        ('*', a, b) => Float(a.float() * b.float()),
+        ('/', a, b) if b.float() != 0.0 => Float(a.float() / b.float()),
+        ('f', a, b) if b.float() != 0.0 => Float((a.float() / b.float()).floor()),
+        ('%', a, b) if b.float() != 0.0 => {
+            Float(a.float() - b.float() * (a.float() / b.float()).floor())
+        }
+        _ => return None,
+    })
+}
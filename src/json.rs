@@ -0,0 +1,196 @@
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyLong, PyString, PyTuple};
+use serde::Serialize;
+
+use crate::wrappers::Unit;
+use crate::Converter;
+
+/// Struct names are stored under this key of JSON objects, as with `preserve_class_names`.
+pub const NAME_KEY: &str = "!__name__";
+
+/// Serializes a value, or a RON file given as `os.PathLike`, to JSON. Struct names (of RON
+/// structs, namedtuples, dataclasses and `Unit`s) are kept in a `"!__name__"` key of the object,
+/// and tuples become arrays. `indent` pretty-prints the output with that many spaces.
+#[pyfunction]
+#[pyo3(signature = (value_or_path, indent = None))]
+pub fn to_json(py: Python, value_or_path: &PyAny, indent: Option<usize>) -> PyResult<String> {
+    let value = load_named_if_path(py, value_or_path)?;
+    let value = to_value(py, value.as_ref(py))?;
+    let mut output = vec![];
+    let result = match indent {
+        Some(indent) => {
+            let indent = " ".repeat(indent);
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+            value.serialize(&mut serde_json::Serializer::with_formatter(
+                &mut output,
+                formatter,
+            ))
+        }
+        None => value.serialize(&mut serde_json::Serializer::new(&mut output)),
+    };
+    result.map_err(|e| exceptions::PyValueError::new_err(e.to_string()))?;
+    Ok(String::from_utf8(output).expect("serde_json produces UTF-8"))
+}
+
+/// Parses JSON text into Python objects. Objects keep their `"!__name__"` key, so values
+/// converted by `to_json` come back as if loaded with `preserve_class_names`.
+#[pyfunction]
+pub fn from_json(py: Python, text: &str) -> PyResult<PyObject> {
+    let value: serde_json::Value = serde_json::from_str(text)
+        .map_err(|e| exceptions::PyValueError::new_err(format!("Invalid JSON: {}", e)))?;
+    from_value(py, &value)
+}
+
+/// Loads `value` with `preserve_class_names` if it is an `os.PathLike`, and returns it unchanged
+/// otherwise.
+pub fn load_named_if_path(py: Python, value: &PyAny) -> PyResult<PyObject> {
+    if value.downcast::<PyString>().is_err() && value.hasattr("__fspath__")? {
+        let path = value.extract::<std::path::PathBuf>()?;
+        let document = crate::parse_file(py, &path, true)?;
+        let mut converter = Converter::new(py);
+        converter.preserve_class_names = true;
+        converter.includes.enter_file(&path)?;
+        converter.convert_document(&document)
+    } else {
+        Ok(value.into())
+    }
+}
+
+pub fn to_value(py: Python, value: &PyAny) -> PyResult<serde_json::Value> {
+    use serde_json::Value;
+    Ok(if let Ok(dict) = value.downcast::<PyDict>() {
+        let mut object = serde_json::Map::new();
+        for (key, value) in dict {
+            object.insert(key_to_string(key)?, to_value(py, value)?);
+        }
+        Value::Object(object)
+    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+        if crate::is_namedtuple(tuple) {
+            let mut object = serde_json::Map::new();
+            object.insert(
+                NAME_KEY.to_string(),
+                Value::String(tuple.get_type().name()?.to_string()),
+            );
+            for (key, value) in tuple.call_method0("_asdict")?.downcast::<PyDict>()? {
+                object.insert(key.extract()?, to_value(py, value)?);
+            }
+            Value::Object(object)
+        } else {
+            Value::Array(
+                tuple
+                    .iter()
+                    .map(|v| to_value(py, v))
+                    .collect::<PyResult<_>>()?,
+            )
+        }
+    } else if let Ok(list) = value.downcast::<PyList>() {
+        Value::Array(
+            list.iter()
+                .map(|v| to_value(py, v))
+                .collect::<PyResult<_>>()?,
+        )
+    } else if let Ok(s) = value.downcast::<PyString>() {
+        Value::String(s.to_str()?.to_string())
+    } else if let Ok(b) = value.extract::<bool>() {
+        Value::Bool(b)
+    } else if let Ok(i) = value.extract::<i64>() {
+        Value::from(i)
+    } else if let Ok(u) = value.extract::<u64>() {
+        Value::from(u)
+    } else if value.downcast::<PyLong>().is_ok() {
+        return Err(exceptions::PyValueError::new_err(format!(
+            "Integer {} doesn't fit into 64 bits and can't be converted without rounding",
+            value.str()?
+        )));
+    } else if let Ok(f) = value.extract::<f64>() {
+        match serde_json::Number::from_f64(f) {
+            Some(number) => Value::Number(number),
+            None => {
+                return Err(exceptions::PyValueError::new_err(format!(
+                    "{} can't be represented in JSON",
+                    f
+                )))
+            }
+        }
+    } else if value.is_none() {
+        Value::Null
+    } else if let Ok(unit) = value.extract::<PyRef<Unit>>() {
+        // Units are structs without fields.
+        let mut object = serde_json::Map::new();
+        object.insert(NAME_KEY.to_string(), Value::String(unit.name().to_string()));
+        Value::Object(object)
+    } else if PyModule::import(py, "dataclasses")?
+        .call_method1("is_dataclass", (value,))?
+        .extract::<bool>()?
+    {
+        let mut object = serde_json::Map::new();
+        object.insert(
+            NAME_KEY.to_string(),
+            Value::String(value.get_type().name()?.to_string()),
+        );
+        for field in value
+            .getattr("__dataclass_fields__")?
+            .downcast::<PyDict>()?
+            .keys()
+        {
+            let field = field.extract::<String>()?;
+            let value = to_value(py, value.getattr(&*field)?)?;
+            object.insert(field, value);
+        }
+        Value::Object(object)
+    } else {
+        return Err(exceptions::PyValueError::new_err(format!(
+            "Unsupported type: {}",
+            value.get_type().name()?
+        )));
+    })
+}
+
+/// JSON object keys are strings, so other keys are converted like `json.dumps` does, with all the
+/// digits of ints.
+fn key_to_string(key: &PyAny) -> PyResult<String> {
+    if let Ok(key) = key.downcast::<PyString>() {
+        Ok(key.to_str()?.to_string())
+    } else if let Ok(key) = key.extract::<bool>() {
+        Ok(key.to_string())
+    } else if key.extract::<i64>().is_ok()
+        || key.downcast::<PyLong>().is_ok()
+        || key.extract::<f64>().is_ok()
+    {
+        Ok(key.str()?.to_str()?.to_string())
+    } else {
+        Err(exceptions::PyValueError::new_err(format!(
+            "JSON keys must be strings or numbers, not {}",
+            key.get_type().name()?
+        )))
+    }
+}
+
+fn from_value(py: Python, value: &serde_json::Value) -> PyResult<PyObject> {
+    use serde_json::Value;
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.into_py(py),
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => i.into_py(py),
+            (None, Some(u)) => u.into_py(py),
+            _ => n.as_f64().into_py(py),
+        },
+        Value::String(s) => s.into_py(py),
+        Value::Array(elements) => {
+            let elements = elements
+                .iter()
+                .map(|element| from_value(py, element))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, elements).into()
+        }
+        Value::Object(object) => {
+            let dict = PyDict::new(py);
+            for (key, value) in object {
+                dict.set_item(key, from_value(py, value)?)?;
+            }
+            dict.into()
+        }
+    })
+}
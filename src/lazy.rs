@@ -0,0 +1,268 @@
+use std::path::PathBuf;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+use ron_parser::Value;
+
+use crate::include::IncludeResolver;
+use crate::{Converter, NonFinite, SeqType};
+
+/// A parsed document shared by the `LazyValue` proxies into it, with the options of `load` that
+/// apply when converting its values.
+#[pyclass(module = "pyron")]
+pub struct LazyTree {
+    pub value: Value,
+    pub path: PathBuf,
+    pub preserve_structs: bool,
+    pub mutable_structs: bool,
+    pub preserve_class_names: bool,
+    pub tag_key: String,
+    pub seq_type: SeqType,
+    pub intern_strings: bool,
+    pub numpy_arrays: bool,
+    pub non_finite: NonFinite,
+    pub use_decimal: bool,
+    pub preserve_numbers: bool,
+    pub preserve_chars: bool,
+    pub preserve_options: bool,
+    pub unwrap_newtypes: bool,
+    pub preserve_units: bool,
+    pub print_errors: bool,
+    pub include_paths: Vec<PathBuf>,
+    pub allowed_urls: Vec<String>,
+    pub allow_includes: bool,
+}
+
+/// A struct, tuple, sequence or map of a document loaded with `lazy=True`. Items are converted to
+/// Python objects only when they are accessed, and containers are returned as further proxies.
+/// Structs and maps behave like dicts (struct fields can also be read as attributes), sequences
+/// and tuples like lists. `materialize` converts the whole container as `load` would.
+#[pyclass(module = "pyron")]
+pub struct LazyValue {
+    tree: Py<LazyTree>,
+    /// Indices of the items leading from the root of the tree to this container.
+    path: Vec<usize>,
+}
+
+/// Returns a proxy for the root of `tree`, or its value if it isn't a container.
+pub fn load(py: Python, tree: LazyTree) -> PyResult<PyObject> {
+    if !is_container(&tree.value) {
+        return tree.convert(py, &tree.value);
+    }
+    let lazy = LazyValue {
+        tree: Py::new(py, tree)?,
+        path: vec![],
+    };
+    Ok(lazy.into_py(py))
+}
+
+impl LazyTree {
+    fn node(&self, path: &[usize]) -> &Value {
+        let mut node = unwrap_some(&self.value);
+        for &i in path {
+            node = unwrap_some(item(node, i).expect("proxy paths are valid"));
+        }
+        node
+    }
+
+    fn convert(&self, py: Python, value: &Value) -> PyResult<PyObject> {
+        let mut converter = Converter::new(py);
+        converter.preserve_structs = self.preserve_structs;
+        converter.mutable_structs = self.mutable_structs;
+        converter.preserve_class_names = self.preserve_class_names;
+        converter.tag_key = self.tag_key.clone();
+        converter.seq_type = self.seq_type;
+        converter.intern_strings = self.intern_strings;
+        converter.numpy_arrays = self.numpy_arrays;
+        converter.non_finite = self.non_finite;
+        converter.use_decimal = self.use_decimal;
+        converter.preserve_numbers = self.preserve_numbers;
+        converter.preserve_chars = self.preserve_chars;
+        converter.preserve_options = self.preserve_options;
+        converter.unwrap_newtypes = self.unwrap_newtypes;
+        converter.preserve_units = self.preserve_units;
+        converter.print_errors = self.print_errors;
+        converter.includes = IncludeResolver::new(
+            self.include_paths.clone(),
+            None,
+            self.allowed_urls.clone(),
+            None,
+            self.allow_includes,
+        );
+        converter.includes.enter_file(&self.path)?;
+        converter.convert(value)
+    }
+}
+
+#[pymethods]
+impl LazyValue {
+    fn __getitem__(&self, py: Python, key: &PyAny) -> PyResult<PyObject> {
+        let tree = self.tree.borrow(py);
+        let node = tree.node(&self.path);
+        match node {
+            Value::Struct(_) | Value::Map(_) => match find(py, &tree, node, key)? {
+                Some(i) => self.child(py, &tree, i),
+                None => Err(exceptions::PyKeyError::new_err(key.to_object(py))),
+            },
+            _ => {
+                let len = len(node) as isize;
+                let i = key.extract::<isize>()?;
+                let i = if i < 0 { i + len } else { i };
+                if i < 0 || i >= len {
+                    return Err(exceptions::PyIndexError::new_err("index out of range"));
+                }
+                self.child(py, &tree, i as usize)
+            }
+        }
+    }
+
+    fn __getattr__(&self, py: Python, name: &str) -> PyResult<PyObject> {
+        let tree = self.tree.borrow(py);
+        let node = tree.node(&self.path);
+        let index = match node {
+            Value::Struct(_) => find(py, &tree, node, name.into_py(py).as_ref(py))?,
+            _ => None,
+        };
+        match index {
+            Some(i) => self.child(py, &tree, i),
+            None => Err(exceptions::PyAttributeError::new_err(name.to_string())),
+        }
+    }
+
+    fn __len__(&self, py: Python) -> usize {
+        len(self.tree.borrow(py).node(&self.path))
+    }
+
+    fn __contains__(&self, py: Python, key: &PyAny) -> PyResult<bool> {
+        let tree = self.tree.borrow(py);
+        let node = tree.node(&self.path);
+        match node {
+            Value::Struct(_) | Value::Map(_) => Ok(find(py, &tree, node, key)?.is_some()),
+            _ => tree.convert(py, node)?.as_ref(py).contains(key),
+        }
+    }
+
+    /// Iterates over the keys of structs and maps, and over the items of sequences and tuples.
+    fn __iter__(&self, py: Python) -> PyResult<PyObject> {
+        let tree = self.tree.borrow(py);
+        let node = tree.node(&self.path);
+        let items = match node {
+            Value::Struct(_) | Value::Map(_) => keys(py, &tree, node)?,
+            _ => (0..len(node))
+                .map(|i| self.child(py, &tree, i))
+                .collect::<PyResult<_>>()?,
+        };
+        Ok(PyList::new(py, items).call_method0("__iter__")?.into())
+    }
+
+    fn keys(&self, py: Python) -> PyResult<Vec<PyObject>> {
+        let tree = self.tree.borrow(py);
+        match tree.node(&self.path) {
+            node @ (Value::Struct(_) | Value::Map(_)) => keys(py, &tree, node),
+            _ => Err(exceptions::PyTypeError::new_err(
+                "only structs and maps have keys",
+            )),
+        }
+    }
+
+    /// The name of a struct or tuple, or `None` if it is unnamed or not a struct or tuple.
+    #[getter]
+    fn name(&self, py: Python) -> Option<String> {
+        match self.tree.borrow(py).node(&self.path) {
+            Value::Struct(s) => s.name.as_ref().map(|name| name.to_string()),
+            Value::Tuple(name, _) => name.as_ref().map(|name| name.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Converts the container and everything in it to Python objects.
+    fn materialize(&self, py: Python) -> PyResult<PyObject> {
+        let tree = self.tree.borrow(py);
+        tree.convert(py, tree.node(&self.path))
+    }
+
+    fn __repr__(&self, py: Python) -> String {
+        let tree = self.tree.borrow(py);
+        let node = tree.node(&self.path);
+        let kind = match node {
+            Value::Struct(_) => "struct",
+            Value::Tuple(..) => "tuple",
+            Value::Seq(_) => "seq",
+            _ => "map",
+        };
+        format!("<lazy {} with {} items>", kind, len(node))
+    }
+}
+
+impl LazyValue {
+    /// Returns the item at index `i` of this container, as a proxy if it is itself a container.
+    fn child(&self, py: Python, tree: &LazyTree, i: usize) -> PyResult<PyObject> {
+        let value = item(tree.node(&self.path), i).expect("index is in bounds");
+        if is_container(value) {
+            let mut path = self.path.clone();
+            path.push(i);
+            let child = LazyValue {
+                tree: self.tree.clone_ref(py),
+                path,
+            };
+            Ok(child.into_py(py))
+        } else {
+            tree.convert(py, value)
+        }
+    }
+}
+
+/// Looks through `Some(...)`, as the conversion does.
+fn unwrap_some(value: &Value) -> &Value {
+    match value {
+        Value::Option(Some(value)) => unwrap_some(value),
+        _ => value,
+    }
+}
+
+fn is_container(value: &Value) -> bool {
+    matches!(
+        unwrap_some(value),
+        Value::Struct(_) | Value::Tuple(..) | Value::Seq(_) | Value::Map(_)
+    )
+}
+
+/// The value of the `i`th field, element or entry of a container.
+fn item(container: &Value, i: usize) -> Option<&Value> {
+    match container {
+        Value::Struct(s) => s.iter().nth(i).map(|(_, value)| value),
+        Value::Tuple(_, t) => t.iter().nth(i),
+        Value::Seq(s) => s.iter().nth(i),
+        Value::Map(m) => m.iter().nth(i).map(|(_, value)| value),
+        _ => None,
+    }
+}
+
+fn len(container: &Value) -> usize {
+    match container {
+        Value::Struct(s) => s.iter().count(),
+        Value::Tuple(_, t) => t.len(),
+        Value::Seq(s) => s.len(),
+        Value::Map(m) => m.iter().count(),
+        _ => 0,
+    }
+}
+
+fn keys(py: Python, tree: &LazyTree, container: &Value) -> PyResult<Vec<PyObject>> {
+    match container {
+        Value::Struct(s) => Ok(s.iter().map(|(key, _)| key.to_object(py)).collect()),
+        Value::Map(m) => m.iter().map(|(key, _)| tree.convert(py, key)).collect(),
+        _ => Ok(vec![]),
+    }
+}
+
+/// The index of the entry of a struct or map with the given key.
+fn find(py: Python, tree: &LazyTree, container: &Value, key: &PyAny) -> PyResult<Option<usize>> {
+    for (i, candidate) in keys(py, tree, container)?.into_iter().enumerate() {
+        if candidate.as_ref(py).eq(key)? {
+            return Ok(Some(i));
+        }
+    }
+    Ok(None)
+}
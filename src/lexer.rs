@@ -0,0 +1,149 @@
+/// A lossless tokenizer for RON source text. Every byte of the input belongs to exactly one token,
+/// including whitespace and comments, so the source can be reproduced or edited exactly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+    /// A string literal, including raw strings and the surrounding quotes.
+    String,
+    Char,
+    Number,
+    Ident,
+    /// Any other single character, e.g. brackets, `,`, `:` or `#`.
+    Punct(char),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Token {
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self.kind,
+            TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment
+        )
+    }
+}
+
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut pos = 0;
+    while pos < source.len() {
+        let (kind, end) = next_token(source, pos);
+        tokens.push(Token {
+            kind,
+            start: pos,
+            end,
+        });
+        pos = end;
+    }
+    tokens
+}
+
+/// Lexes the token starting at `start`, returning its kind and end offset.
+pub fn next_token(source: &str, start: usize) -> (TokenKind, usize) {
+    let rest = &source[start..];
+    let c = rest.chars().next().unwrap();
+    if c.is_whitespace() {
+        let len = rest
+            .find(|c: char| !c.is_whitespace())
+            .unwrap_or(rest.len());
+        (TokenKind::Whitespace, start + len)
+    } else if rest.starts_with("//") {
+        let len = rest.find('\n').unwrap_or(rest.len());
+        (TokenKind::LineComment, start + len)
+    } else if rest.starts_with("/*") {
+        (TokenKind::BlockComment, start + block_comment_len(rest))
+    } else if c == '"' {
+        (TokenKind::String, start + quoted_len(rest, '"'))
+    } else if c == '\'' {
+        (TokenKind::Char, start + quoted_len(rest, '\''))
+    } else if let Some(len) = raw_string_len(rest) {
+        (TokenKind::String, start + len)
+    } else if c.is_ascii_digit() {
+        (TokenKind::Number, start + number_len(rest))
+    } else if c == '_' || c.is_alphabetic() {
+        let len = rest
+            .find(|c: char| !(c == '_' || c.is_alphanumeric()))
+            .unwrap_or(rest.len());
+        (TokenKind::Ident, start + len)
+    } else {
+        (TokenKind::Punct(c), start + c.len_utf8())
+    }
+}
+
+/// Block comments nest, as in Rust.
+fn block_comment_len(rest: &str) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < rest.len() {
+        if rest[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if rest[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += rest[i..].chars().next().unwrap().len_utf8();
+        }
+    }
+    rest.len()
+}
+
+fn quoted_len(rest: &str, quote: char) -> usize {
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c == quote {
+            return i + 1;
+        }
+    }
+    rest.len()
+}
+
+/// Length of a raw string literal such as `r#"..."#` at the start of `rest`, if there is one.
+fn raw_string_len(rest: &str) -> Option<usize> {
+    let hashes = rest
+        .strip_prefix('r')?
+        .chars()
+        .take_while(|&c| c == '#')
+        .count();
+    if !rest[1 + hashes..].starts_with('"') {
+        return None;
+    }
+    let terminator = format!("\"{}", "#".repeat(hashes));
+    let body = 2 + hashes;
+    Some(match rest[body..].find(&terminator) {
+        Some(i) => body + i + terminator.len(),
+        None => rest.len(),
+    })
+}
+
+fn number_len(rest: &str) -> usize {
+    let hex = rest.starts_with("0x") || rest.starts_with("0X");
+    let mut prev = '\0';
+    for (i, c) in rest.char_indices() {
+        let continues = c.is_ascii_alphanumeric()
+            || c == '_'
+            || (c == '.' && rest[i + 1..].starts_with(|c: char| c.is_ascii_digit()))
+            || ((c == '+' || c == '-') && !hex && (prev == 'e' || prev == 'E'));
+        if !continues {
+            return i;
+        }
+        prev = c;
+    }
+    rest.len()
+}
@@ -1,127 +1,1026 @@
-use pyo3::types::{PyDict, PyList, PyTuple};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use pyo3::types::{IntoPyDict, PyByteArray, PyBytes, PyDict, PyList, PyString, PyTuple};
 use pyo3::{exceptions, prelude::*, wrap_pyfunction};
+use sha2::{Digest, Sha256};
+
+mod args;
+mod asyncio;
+mod cache;
+mod classes;
+mod cli;
+mod columns;
+mod compression;
+mod diff;
+mod direct;
+mod document;
+mod events;
+mod format;
+mod frozen;
+mod include;
+mod interpolate;
+mod json;
+mod lazy;
+mod lexer;
+mod literals;
+mod marks;
+mod merge;
+mod migrations;
+mod msgpack;
+mod multi;
+mod numbers;
+mod openers;
+mod parallel;
+mod path;
+mod progress;
+mod query;
+mod record;
+mod records;
+mod schema;
+mod ser;
+mod stream;
+mod sweeps;
+mod toml_format;
+mod validators;
+mod variables;
+mod visit;
+mod watch;
+mod wrappers;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+use include::IncludeResolver;
+use marks::Marks;
+use progress::Progress;
+
+/// The extensions that RON files can enable with `#![enable(...)]`.
+const EXTENSIONS: [&str; 4] = [
+    "implicit_some",
+    "unwrap_newtypes",
+    "unwrap_variant_newtypes",
+    "explicit_struct_names",
+];
+
+/// Serializes a value to a pretty-printed RON string. `comments` maps dotted paths (as used by
+/// `flatten`) to comments that are written as `//` lines above the corresponding items. With
+/// `field_docs`, the documentation of dataclass fields is written as comments as well, taken from
+/// `field(metadata={"doc": ...})` or from string metadata of `Annotated` type hints. `redact`
+/// replaces secrets with `"***"`: it is either a list of dotted paths, which can contain `fnmatch`
+/// wildcards and list indices such as `[0]` or `[*]`, or a callable `redact(path, value)`
+/// returning whether to replace a value. Dicts with a string `tag_key` entry, as returned by
+/// `load` with `preserve_class_names`, are written as structs named by that entry. With
+/// `allow_nan` set to false, NaN and infinite floats raise a `ValueError` instead of being written,
+/// like `json.dumps`, since other tools may not read them.
+/// Floats are written with the fewest digits that read back as the same float, rounded to
+/// `float_precision` digits after the decimal point if given. With `float_format="fixed"`, they
+/// have exactly `float_precision` digits after the decimal point instead. Floats without
+/// fractional digits end in `.0`, or are written in exponent notation such as `1e0` with
+/// `float_trailing_zero` set to false, so that they never load as integers. Nonzero floats whose
+/// decimal exponent is at least `float_exponent_threshold` (or 16, as by `repr`) in absolute value
+/// are written in exponent notation, so that with a threshold of 4, `0.00001` is written as `1e-5`
+/// but `0.001` is not. `int_formats` maps dotted paths, which can contain `fnmatch` wildcards, to
+/// `"hex"`, `"binary"` or `"octal"`, to write the integers at those paths as `0x...`, `0b...` or
+/// `0o...` literals, such as for bitmasks. With `int_separators`, the digits of integers with at
+/// least five digits are separated into groups of three with `_`, as in `10_000_000`. With
+/// `implicit_some`, `Some` values (see `load` with `preserve_options`) are written without
+/// `Some(...)`, after an `#![enable(implicit_some)]` attribute, as many Rust projects configure
+/// `ron`. `extensions` lists further RON extensions to enable with an `#![enable(...)]` attribute,
+/// such as those returned by `extensions` for the file being rewritten. With `unwrap_newtypes`,
+/// tuple structs with one element, like `Seconds(5.0)`, are written as their element after an
+/// `#![enable(unwrap_newtypes)]` attribute. With `enum_repr`, dicts tagged with the name of a
+/// struct or enum variant as by `load` with the same `enum_repr` are written as that struct:
+/// `"external"` writes `{"Adam": {"lr": 0.1}}` as `Adam(lr: 0.1)`, and `"adjacent"` does the same
+/// for `{tag_key: "Adam", content_key: {"lr": 0.1}}`. Tuples as the content are tuple structs.
+/// Only capitalized identifiers are taken as names, but dicts of that shape that aren't variants,
+/// such as `{"Linux": {"cc": "gcc"}}` with `"external"`, are written as structs as well. Unless
+/// `trailing_commas` is false, the last item of every collection spread over multiple lines is
+/// followed by a comma, so that appending an item changes a single line. With `max_width`, structs,
+/// tuples, sequences and maps that fit on one line within `max_width` columns are written on one
+/// line, as by `rustfmt`, instead of with an item per line. With `align_fields`, the values of
+/// struct fields and map entries written one per line start in the same column. `indent` is written
+/// once per level of nesting, such as `"\t"` or two spaces instead of the default four.
+#[pyfunction]
+#[pyo3(signature = (
+    value,
+    comments = None,
+    field_docs = false,
+    redact = None,
+    tag_key = "!__name__",
+    allow_nan = true,
+    float_format = "shortest",
+    float_precision = None,
+    float_trailing_zero = true,
+    float_exponent_threshold = None,
+    int_formats = None,
+    int_separators = false,
+    implicit_some = false,
+    extensions = None,
+    unwrap_newtypes = false,
+    enum_repr = "untagged",
+    content_key = "content",
+    trailing_commas = true,
+    max_width = None,
+    align_fields = false,
+    indent = "    ",
+))]
+pub fn to_string(
+    py: Python,
+    value: &PyAny,
+    comments: Option<HashMap<String, String>>,
+    field_docs: bool,
+    redact: Option<&PyAny>,
+    tag_key: &str,
+    allow_nan: bool,
+    float_format: &str,
+    float_precision: Option<usize>,
+    float_trailing_zero: bool,
+    float_exponent_threshold: Option<i32>,
+    int_formats: Option<&PyDict>,
+    int_separators: bool,
+    implicit_some: bool,
+    extensions: Option<Vec<String>>,
+    unwrap_newtypes: bool,
+    enum_repr: &str,
+    content_key: &str,
+    trailing_commas: bool,
+    max_width: Option<usize>,
+    align_fields: bool,
+    indent: &str,
+) -> PyResult<String> {
+    if indent.contains(|c| c != ' ' && c != '\t') {
+        return Err(exceptions::PyValueError::new_err(format!(
+            "indent must consist of spaces and tabs, not {:?}",
+            indent
+        )));
+    }
+    let mut extensions = extensions.unwrap_or_default();
+    for extension in &extensions {
+        if !EXTENSIONS.contains(&extension.as_str()) {
+            return Err(exceptions::PyValueError::new_err(format!(
+                "Unknown RON extension \"{}\"",
+                extension
+            )));
+        }
+    }
+    for (enabled, extension) in [
+        (implicit_some, "implicit_some"),
+        (unwrap_newtypes, "unwrap_newtypes"),
+    ] {
+        if enabled && !extensions.iter().any(|e| e == extension) {
+            extensions.push(extension.to_string());
+        }
+    }
+    let mut format = ser::Format {
+        tag_key,
+        allow_nan,
+        floats: ser::Floats::new(
+            float_format,
+            float_precision,
+            float_trailing_zero,
+            float_exponent_threshold,
+        )?,
+        int_separators,
+        extensions: &extensions,
+        enum_repr: EnumRepr::parse(enum_repr)?,
+        content_key,
+        trailing_commas,
+        max_width,
+        align_fields,
+        indent,
+        ..ser::Format::default()
+    };
+    let redacted;
+    let value = match redact {
+        Some(redact) => {
+            redacted = visit::redact(py, value, redact, tag_key)?;
+            redacted.as_ref(py)
+        }
+        None => value,
+    };
+    let formatted;
+    let value = match int_formats {
+        Some(int_formats) => {
+            formatted = visit::int_formats(py, value, int_formats, tag_key)?;
+            formatted.as_ref(py)
+        }
+        None => value,
+    };
+    let mut all_comments = HashMap::new();
+    if field_docs {
+        collect_field_docs(py, value, "", &mut all_comments)?;
+    }
+    all_comments.extend(comments.unwrap_or_default());
+    if all_comments.is_empty() {
+        return ser::to_string_pretty(py, value, &format);
+    }
+    // Collections are inlined after the comments are inserted, so that commented items keep their
+    // own lines instead of having their comments written above the line they were inlined into.
+    format.max_width = None;
+    let text = ser::to_string_pretty(py, value, &format)?;
+    let text = document::insert_comments(py, &text, &all_comments)?;
+    Ok(match max_width {
+        Some(max_width) => crate::format::inline_collections(&text, max_width),
+        None => text,
+    })
+}
+
+/// Serializes a value like `to_string` with the default options.
+fn serialize(py: Python, value: &PyAny) -> PyResult<String> {
+    ser::to_string_pretty(py, value, &ser::Format::default())
+}
+
+/// Produces a normalized RON string for a value, or for RON text if given a `str`, so that
+/// semantically identical configs serialize identically regardless of key order or formatting.
+/// Map keys and struct fields are sorted, and named structs in RON text are kept as named structs.
+#[pyfunction]
+pub fn canonicalize(py: Python, text_or_value: &PyAny) -> PyResult<String> {
+    let value = match text_or_value.downcast::<PyString>() {
+        Ok(text) => {
+            let text = text.to_str()?;
+            let mut converter = Converter::new(py);
+            converter.preserve_structs = true;
+            converter.convert_document(&parse_str(py, text, text, true)?)?
+        }
+        Err(_) => text_or_value.into(),
+    };
+    let format = ser::Format {
+        sort_keys: true,
+        ..ser::Format::default()
+    };
+    ser::to_string_pretty(py, value.as_ref(py), &format)
+}
 
-#[pyfunction()]
-pub fn to_string(py: Python, value: &PyAny) -> PyResult<String> {
-    let value = extract(py, value)?;
-    value
-        .to_string_pretty(
-            ron::ser::PrettyConfig::default()
-                .struct_names(true)
-                .decimal_floats(true),
-        )
-        .map_err(|e| exceptions::PyValueError::new_err(format!("{}", e)))
-}
-
-#[pyfunction(
-    preserve_structs = "false",
-    preserve_class_names = "false",
-    print_errors = "true"
-)]
+/// Computes a stable content hash of a value, or of RON text if given a `str`, as the hex SHA-256
+/// digest of its canonical form. Formatting and key order don't affect the result, which makes it
+/// usable as an experiment or run ID. `length` truncates the digest to that many hex digits.
+#[pyfunction]
+#[pyo3(signature = (text_or_value, length = None))]
+pub fn fingerprint(py: Python, text_or_value: &PyAny, length: Option<usize>) -> PyResult<String> {
+    let mut digest = format!("{:x}", Sha256::digest(canonicalize(py, text_or_value)?));
+    if let Some(length) = length {
+        digest.truncate(length);
+    }
+    Ok(digest)
+}
+
+#[pyfunction]
+#[pyo3(signature = (
+    path,
+    preserve_structs = false,
+    preserve_class_names = false,
+    mutable_structs = false,
+    tag_key = "!__name__",
+    print_errors = true,
+    include_paths = None,
+    include_resolver = None,
+    allowed_urls = None,
+    url_fetcher = None,
+    allow_includes = true,
+    interpolate_env = false,
+    interpolate_refs = false,
+    comments = None,
+    cache = false,
+    lazy = false,
+    mmap = false,
+    progress = None,
+    profile = None,
+    migrate = false,
+    validators = None,
+    frozen = false,
+    seq_type = "list",
+    intern_strings = false,
+    numpy_arrays = false,
+    non_finite = "float",
+    use_decimal = false,
+    preserve_numbers = false,
+    preserve_chars = false,
+    preserve_options = false,
+    unwrap_newtypes = false,
+    preserve_units = false,
+    enum_repr = "untagged",
+    content_key = "content",
+))]
 pub fn load(
     py: Python,
-    path: &str,
+    path: &PyAny,
     preserve_structs: bool,
     preserve_class_names: bool,
+    mutable_structs: bool,
+    tag_key: &str,
     print_errors: bool,
+    include_paths: Option<Vec<PathBuf>>,
+    include_resolver: Option<PyObject>,
+    allowed_urls: Option<Vec<String>>,
+    url_fetcher: Option<PyObject>,
+    allow_includes: bool,
+    interpolate_env: bool,
+    interpolate_refs: bool,
+    comments: Option<&PyDict>,
+    cache: bool,
+    lazy: bool,
+    mmap: bool,
+    progress: Option<PyObject>,
+    profile: Option<&str>,
+    migrate: bool,
+    validators: Option<&PyDict>,
+    frozen: bool,
+    seq_type: &str,
+    intern_strings: bool,
+    numpy_arrays: bool,
+    non_finite: &str,
+    use_decimal: bool,
+    preserve_numbers: bool,
+    preserve_chars: bool,
+    preserve_options: bool,
+    unwrap_newtypes: bool,
+    preserve_units: bool,
+    enum_repr: &str,
+    content_key: &str,
 ) -> PyResult<PyObject> {
-    let parse = ron_parser::load(path)?;
     if preserve_structs && preserve_class_names {
         return Err(exceptions::PyValueError::new_err(
             "preserve_structs and preserve_class_names cannot be true at the same time",
         ));
     }
-    if !parse.errors.is_empty() {
-        if print_errors {
-            parse.emit();
+    if mutable_structs && !preserve_structs {
+        return Err(exceptions::PyValueError::new_err(
+            "mutable_structs requires preserve_structs",
+        ));
+    }
+    if mutable_structs && frozen {
+        return Err(exceptions::PyValueError::new_err(
+            "mutable_structs and frozen cannot be true at the same time",
+        ));
+    }
+    if numpy_arrays && use_decimal {
+        return Err(exceptions::PyValueError::new_err(
+            "numpy_arrays and use_decimal cannot be true at the same time",
+        ));
+    }
+    if preserve_numbers && (numpy_arrays || use_decimal) {
+        return Err(exceptions::PyValueError::new_err(
+            "preserve_numbers cannot be combined with numpy_arrays or use_decimal",
+        ));
+    }
+    let marks = Marks {
+        // Numbers are parsed from their source text for these options.
+        exact_numbers: use_decimal || preserve_numbers,
+        units: preserve_units,
+    };
+    if enum_repr != "untagged" && (preserve_structs || preserve_class_names) {
+        return Err(exceptions::PyValueError::new_err(
+            "enum_repr cannot be combined with preserve_structs or preserve_class_names",
+        ));
+    }
+    // A memory-mapped file is read by the parser as it goes, which can't report progress.
+    if mmap && progress.is_some() {
+        return Err(exceptions::PyValueError::new_err(
+            "progress cannot be combined with mmap",
+        ));
+    }
+    // `-` is standard input, as is customary for command line arguments.
+    let path = match path.extract::<&str>() {
+        Ok("-") => PyModule::import(py, "sys")?.getattr("stdin")?,
+        _ => path,
+    };
+    // Like `json.load`, accept file-like objects and parse what they read. URLs with a registered
+    // scheme are read by their opener.
+    let text = match path.downcast::<PyString>() {
+        Ok(url) => openers::open(py, url.to_str()?)?,
+        Err(_) if path.hasattr("read")? => Some(path.call_method0("read")?),
+        Err(_) => None,
+    };
+    if let Some(text) = text {
+        if cache || lazy || mmap || progress.is_some() || profile.is_some() {
+            return Err(exceptions::PyValueError::new_err(
+                "cache, lazy, mmap, progress and profile require a path",
+            ));
         }
-        return Err(exceptions::PyValueError::new_err(format!(
-            "Fail to parse: {}",
-            path
-        )));
+        return loads(
+            py,
+            text,
+            preserve_structs,
+            preserve_class_names,
+            mutable_structs,
+            tag_key,
+            print_errors,
+            include_paths,
+            include_resolver,
+            allowed_urls,
+            url_fetcher,
+            allow_includes,
+            interpolate_env,
+            interpolate_refs,
+            comments,
+            migrate,
+            validators,
+            frozen,
+            seq_type,
+            intern_strings,
+            numpy_arrays,
+            non_finite,
+            use_decimal,
+            preserve_numbers,
+            preserve_chars,
+            preserve_options,
+            unwrap_newtypes,
+            preserve_units,
+            enum_repr,
+            content_key,
+        );
     }
-    try_val_to_py(py, &parse.value, preserve_structs, preserve_class_names)
+    let seq_type = SeqType::parse(seq_type)?;
+    let non_finite = NonFinite::parse(non_finite)?;
+    let enum_repr = EnumRepr::parse(enum_repr)?;
+    let path = path.extract::<PathBuf>()?;
+    let path = path.as_path();
+    if lazy {
+        // Proxies convert values long after loading, so they only support options that can be
+        // applied to each value on its own.
+        if include_resolver.is_some()
+            || url_fetcher.is_some()
+            || interpolate_env
+            || interpolate_refs
+            || comments.is_some()
+            || cache
+            || profile.is_some()
+            || migrate
+            || validators.is_some()
+            || frozen
+            // Proxies of structs would have to be tagged with their name as well.
+            || enum_repr != EnumRepr::Untagged
+        {
+            return Err(exceptions::PyValueError::new_err(
+                "lazy cannot be combined with include_resolver, url_fetcher, interpolate_env, \
+                 interpolate_refs, comments, cache, profile, migrate, validators, frozen \
+                 or enum_repr",
+            ));
+        }
+        let tree = lazy::LazyTree {
+            value: parse_for_load(py, path, print_errors, marks, mmap, progress)?,
+            path: path.to_path_buf(),
+            preserve_structs,
+            mutable_structs,
+            preserve_class_names,
+            tag_key: tag_key.to_string(),
+            seq_type,
+            intern_strings,
+            numpy_arrays,
+            non_finite,
+            use_decimal,
+            preserve_numbers,
+            preserve_chars,
+            preserve_options,
+            unwrap_newtypes,
+            preserve_units,
+            print_errors,
+            include_paths: include_paths.unwrap_or_default(),
+            allowed_urls: allowed_urls.unwrap_or_default(),
+            allow_includes,
+        };
+        return lazy::load(py, tree);
+    }
+    // Values depending on callbacks or environment variables can change without any file
+    // changing, so they aren't cached. Neither are records, instances of registered classes, numpy
+    // arrays, decimals and the wrappers of the `preserve_*` options other than `Unit`, which the
+    // cache can't store.
+    let cache_key = if cache
+        && include_resolver.is_none()
+        && url_fetcher.is_none()
+        && !interpolate_env
+        && !mutable_structs
+        && !(preserve_structs && classes::any())
+        && !numpy_arrays
+        && !use_decimal
+        && !preserve_numbers
+        && !preserve_chars
+        && !preserve_options
+    {
+        let options = format!(
+            "{:?}",
+            (
+                preserve_structs,
+                preserve_class_names,
+                tag_key,
+                seq_type,
+                intern_strings,
+                // `Debug` is only implemented for tuples of up to 12 elements.
+                (
+                    non_finite,
+                    unwrap_newtypes,
+                    enum_repr,
+                    content_key,
+                    preserve_units
+                ),
+                &include_paths,
+                &allowed_urls,
+                allow_includes,
+                interpolate_refs,
+                profile,
+            )
+        );
+        Some((path.canonicalize()?, options))
+    } else {
+        None
+    };
+    let cached = match &cache_key {
+        Some((canonical, options)) => cache::get(py, canonical, options, intern_strings)?,
+        None => None,
+    };
+    // The source is kept for `comments` if it's read anyway.
+    let mut source = None;
+    let value = match cached {
+        Some(value) => value,
+        None => {
+            let mut converter = Converter {
+                py,
+                preserve_structs,
+                mutable_structs,
+                preserve_class_names,
+                tag_key: tag_key.to_string(),
+                seq_type,
+                intern_strings,
+                numpy_arrays,
+                non_finite,
+                use_decimal,
+                preserve_numbers,
+                preserve_chars,
+                preserve_options,
+                unwrap_newtypes,
+                preserve_units,
+                enum_repr,
+                content_key: content_key.to_string(),
+                print_errors,
+                // With references enabled, environment variables are substituted as a fallback
+                // while resolving references instead.
+                interpolate_env: interpolate_env && !interpolate_refs,
+                interpolate_refs,
+                env_fallback: interpolate_env,
+                includes: IncludeResolver::new(
+                    include_paths.unwrap_or_default(),
+                    include_resolver,
+                    allowed_urls.unwrap_or_default(),
+                    url_fetcher,
+                    allow_includes,
+                ),
+            };
+            converter.includes.enter_file(path)?;
+            let mut value = if mmap || progress.is_some() {
+                let value = parse_for_load(py, path, print_errors, marks, mmap, progress)?;
+                converter.convert(&value)?
+            } else {
+                let text = compression::read_to_string(path)?;
+                let value = converter.convert_source(&text, &path.display().to_string())?;
+                source = Some(text);
+                value
+            };
+            if let Some(profile) = profile {
+                let overlay_path = profile_path(path, profile);
+                let overlay = parse_for_load(py, &overlay_path, print_errors, marks, mmap, None)?;
+                converter.includes.exit();
+                converter.includes.enter_file(&overlay_path)?;
+                let overlay = converter.convert(&overlay)?;
+                value = merge::merge_values(
+                    py,
+                    value.as_ref(py),
+                    overlay.as_ref(py),
+                    merge::ListMerge::Replace,
+                )?;
+            }
+            let value = converter.finish(value)?;
+            if let Some((canonical, options)) = cache_key {
+                let files = converter.includes.files();
+                cache::insert(py, canonical, options, files, value.as_ref(py));
+            }
+            value
+        }
+    };
+    // Migrations are applied after caching, since they can be registered at any time.
+    let value = if migrate {
+        migrations::migrate_loaded(py, value, &path.display().to_string())?
+    } else {
+        value
+    };
+    if let Some(validators) = validators {
+        validators::check(py, value.as_ref(py), validators, tag_key)?;
+    }
+    let value = if frozen {
+        frozen::freeze(py, value.as_ref(py))?
+    } else {
+        value
+    };
+    if let Some(comments) = comments {
+        let source = match source {
+            Some(source) => source,
+            None => compression::read_to_string(path)?,
+        };
+        collect_comments(py, comments, &source)?;
+    }
+    Ok(value)
 }
 
-#[pyfunction(
-    preserve_structs = "false",
-    preserve_class_names = "false",
-    print_errors = "true"
-)]
+#[pyfunction]
+#[pyo3(signature = (
+    s,
+    preserve_structs = false,
+    preserve_class_names = false,
+    mutable_structs = false,
+    tag_key = "!__name__",
+    print_errors = true,
+    include_paths = None,
+    include_resolver = None,
+    allowed_urls = None,
+    url_fetcher = None,
+    allow_includes = true,
+    interpolate_env = false,
+    interpolate_refs = false,
+    comments = None,
+    migrate = false,
+    validators = None,
+    frozen = false,
+    seq_type = "list",
+    intern_strings = false,
+    numpy_arrays = false,
+    non_finite = "float",
+    use_decimal = false,
+    preserve_numbers = false,
+    preserve_chars = false,
+    preserve_options = false,
+    unwrap_newtypes = false,
+    preserve_units = false,
+    enum_repr = "untagged",
+    content_key = "content",
+))]
 pub fn loads(
     py: Python,
-    s: &str,
+    s: &PyAny,
     preserve_structs: bool,
     preserve_class_names: bool,
+    mutable_structs: bool,
+    tag_key: &str,
     print_errors: bool,
+    include_paths: Option<Vec<PathBuf>>,
+    include_resolver: Option<PyObject>,
+    allowed_urls: Option<Vec<String>>,
+    url_fetcher: Option<PyObject>,
+    allow_includes: bool,
+    interpolate_env: bool,
+    interpolate_refs: bool,
+    comments: Option<&PyDict>,
+    migrate: bool,
+    validators: Option<&PyDict>,
+    frozen: bool,
+    seq_type: &str,
+    intern_strings: bool,
+    numpy_arrays: bool,
+    non_finite: &str,
+    use_decimal: bool,
+    preserve_numbers: bool,
+    preserve_chars: bool,
+    preserve_options: bool,
+    unwrap_newtypes: bool,
+    preserve_units: bool,
+    enum_repr: &str,
+    content_key: &str,
 ) -> PyResult<PyObject> {
-    let value = match ron_parser::parse(s, None) {
-        Ok(value) => value,
-        Err(parse) => {
-            if print_errors {
-                parse.emit();
-            }
-            return Err(exceptions::PyValueError::new_err(format!(
-                "Fail to parse: {}",
-                s
-            )));
-        }
+    if mutable_structs && !preserve_structs {
+        return Err(exceptions::PyValueError::new_err(
+            "mutable_structs requires preserve_structs",
+        ));
+    }
+    if mutable_structs && frozen {
+        return Err(exceptions::PyValueError::new_err(
+            "mutable_structs and frozen cannot be true at the same time",
+        ));
+    }
+    if numpy_arrays && use_decimal {
+        return Err(exceptions::PyValueError::new_err(
+            "numpy_arrays and use_decimal cannot be true at the same time",
+        ));
+    }
+    if preserve_numbers && (numpy_arrays || use_decimal) {
+        return Err(exceptions::PyValueError::new_err(
+            "preserve_numbers cannot be combined with numpy_arrays or use_decimal",
+        ));
+    }
+    if enum_repr != "untagged" && (preserve_structs || preserve_class_names) {
+        return Err(exceptions::PyValueError::new_err(
+            "enum_repr cannot be combined with preserve_structs or preserve_class_names",
+        ));
+    }
+    let seq_type = SeqType::parse(seq_type)?;
+    let non_finite = NonFinite::parse(non_finite)?;
+    let enum_repr = EnumRepr::parse(enum_repr)?;
+    let s = source_text(s)?;
+    let s = s.as_ref();
+    let mut converter = Converter {
+        py,
+        preserve_structs,
+        mutable_structs,
+        preserve_class_names,
+        tag_key: tag_key.to_string(),
+        seq_type,
+        intern_strings,
+        numpy_arrays,
+        non_finite,
+        use_decimal,
+        preserve_numbers,
+        preserve_chars,
+        preserve_options,
+        unwrap_newtypes,
+        preserve_units,
+        enum_repr,
+        content_key: content_key.to_string(),
+        print_errors,
+        // With references enabled, environment variables are substituted as a fallback while
+        // resolving references instead.
+        interpolate_env: interpolate_env && !interpolate_refs,
+        interpolate_refs,
+        env_fallback: interpolate_env,
+        includes: IncludeResolver::new(
+            include_paths.unwrap_or_default(),
+            include_resolver,
+            allowed_urls.unwrap_or_default(),
+            url_fetcher,
+            allow_includes,
+        ),
+    };
+    let value = converter.convert_source(s, s)?;
+    let value = converter.finish(value)?;
+    let value = if migrate {
+        migrations::migrate_loaded(py, value, "<string>")?
+    } else {
+        value
     };
-    try_val_to_py(py, &value, preserve_structs, preserve_class_names)
+    if let Some(validators) = validators {
+        validators::check(py, value.as_ref(py), validators, tag_key)?;
+    }
+    let value = if frozen {
+        frozen::freeze(py, value.as_ref(py))?
+    } else {
+        value
+    };
+    if let Some(comments) = comments {
+        collect_comments(py, comments, s)?;
+    }
+    Ok(value)
 }
 
-#[pymodule]
-fn pyron(_py: Python, m: &PyModule) -> PyResult<()> {
-    m.add_function(wrap_pyfunction!(to_string, m)?).unwrap();
-    m.add_function(wrap_pyfunction!(load, m)?).unwrap();
-    m.add_function(wrap_pyfunction!(loads, m)?).unwrap();
+/// The overlay of a profile, such as `config.prod.ron` for `config.ron` and the profile `prod`.
+fn profile_path(path: &Path, profile: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let name = match name.rfind(".ron") {
+        Some(i) => format!("{}.{}{}", &name[..i], profile, &name[i..]),
+        None => format!("{}.{}", name, profile),
+    };
+    path.with_file_name(name)
+}
+
+/// Returns the text of a `str`, or of UTF-8 encoded `bytes` or `bytearray` without a leading byte
+/// order mark.
+fn source_text(value: &PyAny) -> PyResult<Cow<str>> {
+    let bytes = if let Ok(s) = value.downcast::<PyString>() {
+        return Ok(Cow::Borrowed(s.to_str()?));
+    } else if let Ok(bytes) = value.downcast::<PyBytes>() {
+        Cow::Borrowed(bytes.as_bytes())
+    } else if let Ok(bytes) = value.downcast::<PyByteArray>() {
+        Cow::Owned(bytes.to_vec())
+    } else {
+        return Err(exceptions::PyTypeError::new_err(format!(
+            "Expected str, bytes or bytearray, found {}",
+            value.get_type().name()?
+        )));
+    };
+    let decode = |bytes: &[u8]| {
+        let bytes = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+        std::str::from_utf8(bytes).map_err(|e| {
+            exceptions::PyValueError::new_err(format!("Invalid UTF-8 in RON source: {}", e))
+        })
+    };
+    Ok(match bytes {
+        Cow::Borrowed(bytes) => Cow::Borrowed(decode(bytes)?),
+        Cow::Owned(bytes) => Cow::Owned(decode(&bytes)?.to_string()),
+    })
+}
+
+/// Adds the comments of `source` to the `comments` dict passed to `load` or `loads`, keyed by the
+/// path of the commented value.
+fn collect_comments(py: Python, comments: &PyDict, source: &str) -> PyResult<()> {
+    for (path, comment) in document::comments(py, source)? {
+        comments.set_item(path, comment)?;
+    }
     Ok(())
 }
 
-fn extract(py: Python, value: &PyAny) -> Result<ron::Value, PyErr> {
-    if let Ok(dict) = value.downcast::<PyDict>() {
-        let mut map = ron::Map::new();
-        for (key, value) in dict {
-            map.insert(extract(py, key)?, extract(py, value)?);
+/// Serializes a value like `to_string` and writes it to `path`, or to a file-like object with a
+/// `write` method. `comments` can also be the comments collected by `load` with `comments`, to
+/// preserve them when regenerating a file. With `atomic`, the output is written to a temporary
+/// file that is then renamed to `path`, so `path` never contains a partially written file.
+#[pyfunction]
+#[pyo3(signature = (
+    value,
+    path,
+    comments = None,
+    field_docs = false,
+    atomic = false,
+    redact = None,
+    tag_key = "!__name__",
+    allow_nan = true,
+    float_format = "shortest",
+    float_precision = None,
+    float_trailing_zero = true,
+    float_exponent_threshold = None,
+    int_formats = None,
+    int_separators = false,
+    implicit_some = false,
+    extensions = None,
+    unwrap_newtypes = false,
+    enum_repr = "untagged",
+    content_key = "content",
+    trailing_commas = true,
+    max_width = None,
+    align_fields = false,
+    indent = "    ",
+))]
+pub fn dump(
+    py: Python,
+    value: &PyAny,
+    path: &PyAny,
+    comments: Option<HashMap<String, String>>,
+    field_docs: bool,
+    atomic: bool,
+    redact: Option<&PyAny>,
+    tag_key: &str,
+    allow_nan: bool,
+    float_format: &str,
+    float_precision: Option<usize>,
+    float_trailing_zero: bool,
+    float_exponent_threshold: Option<i32>,
+    int_formats: Option<&PyDict>,
+    int_separators: bool,
+    implicit_some: bool,
+    extensions: Option<Vec<String>>,
+    unwrap_newtypes: bool,
+    enum_repr: &str,
+    content_key: &str,
+    trailing_commas: bool,
+    max_width: Option<usize>,
+    align_fields: bool,
+    indent: &str,
+) -> PyResult<()> {
+    let text = to_string(
+        py,
+        value,
+        comments,
+        field_docs,
+        redact,
+        tag_key,
+        allow_nan,
+        float_format,
+        float_precision,
+        float_trailing_zero,
+        float_exponent_threshold,
+        int_formats,
+        int_separators,
+        implicit_some,
+        extensions,
+        unwrap_newtypes,
+        enum_repr,
+        content_key,
+        trailing_commas,
+        max_width,
+        align_fields,
+        indent,
+    )?;
+    if path.downcast::<PyString>().is_err() && path.hasattr("write")? {
+        if atomic {
+            return Err(exceptions::PyValueError::new_err("atomic requires a path"));
         }
-        Ok(ron::Value::Map(map))
-    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
-        if is_namedtuple(tuple) {
-            extract_namedtuple(py, tuple)
+        path.call_method1("write", (text,))?;
+    } else {
+        let path = path.extract::<PathBuf>()?;
+        let contents = compression::encode(&path, &text)?;
+        if atomic {
+            document::write_atomic(&path, contents)?;
         } else {
-            let mut seq = vec![];
-            for value in tuple.iter() {
-                seq.push(extract(py, value)?);
-            }
-            Ok(ron::Value::Tuple(seq))
+            std::fs::write(&path, contents)?;
         }
-    } else if let Ok(list) = value.downcast::<PyList>() {
-        let mut seq = vec![];
-        for value in list.iter() {
-            seq.push(extract(py, value)?);
-        }
-        Ok(ron::Value::Seq(seq))
-    } else if let Ok(str) = value.extract::<String>() {
-        Ok(ron::Value::String(str))
-    } else if let Ok(bool) = value.extract::<bool>() {
-        Ok(ron::Value::Bool(bool))
-    } else if let Ok(int) = value.extract::<i64>() {
-        Ok(ron::Value::Number(ron::Number::Integer(int)))
-    } else if let Ok(float) = value.extract::<f64>() {
-        Ok(ron::Value::Number(ron::Number::from(float)))
-    } else if let Ok(None) = value.extract::<Option<PyObject>>() {
-        Ok(ron::Value::Option(None))
-    } else if PyModule::import(py, "dataclasses")?
-        .call_method1("is_dataclass", (value,))?
-        .extract::<bool>()?
+    }
+    Ok(())
+}
+
+#[pymodule]
+fn pyron(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(to_string, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(load, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(loads, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(merge::merge, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(path::apply_overrides, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(diff::diff, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(diff::patch, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(canonicalize, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(fingerprint, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(path::flatten, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(path::unflatten, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(query::get, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(visit::transform, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(document::parse_document, m)?)
+        .unwrap();
+    m.add_class::<document::Document>()?;
+    m.add_function(wrap_pyfunction!(document::extensions, m)?)
+        .unwrap();
+    m.add_class::<document::DocumentItem>()?;
+    m.add_function(wrap_pyfunction!(dump, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(document::edit, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(document::open, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(cli::main, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(json::to_json, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(json::from_json, m)?)
+        .unwrap();
+    #[cfg(feature = "yaml")]
     {
-        extract_dataclass(py, value)
-    } else {
-        Err(exceptions::PyValueError::new_err(format!(
-            "Unsupported type: {}",
-            value.get_type().name()?
-        )))
+        m.add_function(wrap_pyfunction!(yaml::to_yaml, m)?).unwrap();
+        m.add_function(wrap_pyfunction!(yaml::from_yaml, m)?)
+            .unwrap();
     }
+    m.add_function(wrap_pyfunction!(toml_format::to_toml, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(toml_format::from_toml, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(msgpack::to_msgpack, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(msgpack::from_msgpack, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(msgpack::compile, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(msgpack::load_compiled, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(cache::clear_cache, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(parallel::load_many, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(stream::iter_load, m)?)
+        .unwrap();
+    m.add_class::<stream::SeqIterator>()?;
+    m.add_function(wrap_pyfunction!(columns::load_columns, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(columns::to_arrow, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(events::events, m)?)
+        .unwrap();
+    m.add_class::<events::EventIterator>()?;
+    m.add_class::<lazy::LazyValue>()?;
+    m.add_function(wrap_pyfunction!(asyncio::load_async, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(openers::register_opener, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(records::append_record, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(records::iter_records, m)?)
+        .unwrap();
+    m.add_class::<records::RecordIterator>()?;
+    m.add_function(wrap_pyfunction!(multi::load_all, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(multi::dump_all, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(merge::load_dir, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(watch::watch, m)?).unwrap();
+    m.add_class::<watch::Watcher>()?;
+    m.add_class::<record::Record>()?;
+    m.add_class::<wrappers::Char>()?;
+    m.add_class::<wrappers::SomeValue>()?;
+    m.add_class::<wrappers::Unit>()?;
+    m.add_function(wrap_pyfunction!(classes::register, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(classes::make_struct, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(migrations::register_migration, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(migrations::migrate, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(sweeps::expand_sweeps, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(sweeps::grid, m)?).unwrap();
+    m.add_function(wrap_pyfunction!(args::argparser, m)?)
+        .unwrap();
+    m.add_function(wrap_pyfunction!(args::from_args, m)?)
+        .unwrap();
+    m.add(
+        "ValidationError",
+        py.get_type::<validators::ValidationError>(),
+    )?;
+    let (literal_int, literal_float) = literals::classes(py)?;
+    m.add("LiteralInt", literal_int)?;
+    m.add("LiteralFloat", literal_float)?;
+    Ok(())
 }
 
 fn is_namedtuple(value: &PyTuple) -> bool {
@@ -144,155 +1043,680 @@ fn is_namedtuple(value: &PyTuple) -> bool {
     fields.downcast::<PyTuple>().is_ok()
 }
 
-fn extract_namedtuple(py: Python, value: &PyTuple) -> Result<ron::Value, PyErr> {
-    let name = value
-        .getattr("__class__")?
-        .getattr("__name__")?
-        .extract::<String>()?;
-    let mut s = ron::value::Struct::new(Some(name));
-    for (name, value) in value
-        .call_method("_asdict", (), None)?
-        .downcast::<PyDict>()?
-    {
-        let name = name.extract::<String>()?;
-        let value = extract(py, value)?;
-        s.insert(name, value);
-    }
-    Ok(ron::Value::Struct(s))
-}
-
-fn extract_dataclass(py: Python, value: &PyAny) -> Result<ron::Value, PyErr> {
-    let name = value
-        .getattr("__class__")?
-        .getattr("__name__")?
-        .extract::<String>()?;
-    let mut s = ron::value::Struct::new(Some(name));
-    // for field in mydataclass.__dataclass_fields__:
-    //   value = getattr(mydataclass, field)
-    //   ..
-    for field in value
-        .getattr("__dataclass_fields__")?
-        .downcast::<PyDict>()?
-        .keys()
+/// Collects the documentation of the fields of all dataclasses nested in `value`, keyed by the
+/// dotted path of the field.
+fn collect_field_docs(
+    py: Python,
+    value: &PyAny,
+    path: &str,
+    docs: &mut HashMap<String, String>,
+) -> PyResult<()> {
+    let dataclasses = PyModule::import(py, "dataclasses")?;
+    if let Ok(dict) = value.downcast::<PyDict>() {
+        for (key, value) in dict {
+            collect_field_docs(py, value, &diff::join(path, key)?, docs)?;
+        }
+    } else if let Ok(list) = value.downcast::<PyList>() {
+        for (i, value) in list.iter().enumerate() {
+            collect_field_docs(py, value, &format!("{}[{}]", path, i), docs)?;
+        }
+    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+        if is_namedtuple(tuple) {
+            let fields = tuple.getattr("_fields")?.extract::<Vec<&str>>()?;
+            for (field, value) in fields.iter().zip(tuple.iter()) {
+                let child = diff::join(path, PyString::new(py, field))?;
+                collect_field_docs(py, value, &child, docs)?;
+            }
+        } else {
+            for (i, value) in tuple.iter().enumerate() {
+                collect_field_docs(py, value, &format!("{}[{}]", path, i), docs)?;
+            }
+        }
+    } else if dataclasses
+        .call_method1("is_dataclass", (value,))?
+        .extract::<bool>()?
     {
-        let field = field.extract::<String>()?;
-        let value = value.getattr(&*field)?;
-        let value = extract(py, value)?;
-        s.insert(field, value);
+        let typing = PyModule::import(py, "typing")?;
+        let kwargs = [("include_extras", true)].into_py_dict(py);
+        // Type hints can fail to resolve, e.g. for forward references to undefined names, in
+        // which case only the field metadata is used.
+        let hints = typing
+            .call_method("get_type_hints", (value.get_type(),), Some(kwargs))
+            .ok();
+        for field in dataclasses
+            .call_method1("fields", (value,))?
+            .downcast::<PyTuple>()?
+        {
+            let name = field.getattr("name")?.extract::<&str>()?;
+            let child = diff::join(path, PyString::new(py, name))?;
+            let metadata = field.getattr("metadata")?;
+            let mut doc = metadata
+                .call_method1("get", ("doc",))?
+                .extract::<Option<String>>()?;
+            if doc.is_none() {
+                if let Some(hint) = hints.and_then(|hints| hints.get_item(name).ok()) {
+                    if let Ok(extras) = hint.getattr("__metadata__") {
+                        for extra in extras.iter()? {
+                            if let Ok(extra) = extra?.extract::<String>() {
+                                doc = Some(extra);
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+            if let Some(doc) = doc {
+                docs.insert(child.clone(), doc);
+            }
+            collect_field_docs(py, value.getattr(name)?, &child, docs)?;
+        }
+    }
+    Ok(())
+}
+
+fn sort_entries<K, V>(
+    entries: &mut Vec<(K, V)>,
+    sort_key: impl Fn(&K) -> PyResult<String>,
+) -> PyResult<()> {
+    let mut keyed = vec![];
+    for entry in entries.drain(..) {
+        keyed.push((sort_key(&entry.0)?, entry));
+    }
+    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+    entries.extend(keyed.into_iter().map(|(_, entry)| entry));
+    Ok(())
+}
+
+/// Loads a RON file with the default options of `load`.
+fn load_path(py: Python, path: &Path) -> PyResult<PyObject> {
+    let value = parse_file(py, path, true)?;
+    let mut converter = Converter::new(py);
+    converter.includes.enter_file(path)?;
+    converter.convert_document(&value)
+}
+
+/// The RON source of functions that take the path of a file as a `str` or `os.PathLike`, as `load`
+/// does, or RON text as the keyword argument `text`.
+pub enum Source {
+    File(PathBuf),
+    Text(String),
+}
+
+impl Source {
+    /// Returns the source given to `function`, which must be either `file` or `text`.
+    pub fn new(function: &str, file: Option<PathBuf>, text: Option<String>) -> PyResult<Source> {
+        match (file, text) {
+            (Some(file), None) => Ok(Source::File(file)),
+            (None, Some(text)) => Ok(Source::Text(text)),
+            (file, _) => Err(exceptions::PyTypeError::new_err(format!(
+                "{}() takes either the path of a file or text=, not {}",
+                function,
+                if file.is_some() { "both" } else { "neither" }
+            ))),
+        }
+    }
+}
+
+/// Loads `value` with `load_path` if it is an `os.PathLike`, and returns it unchanged otherwise.
+fn load_if_path(py: Python, value: &PyAny) -> PyResult<PyObject> {
+    if value.downcast::<PyString>().is_err() && value.hasattr("__fspath__")? {
+        load_path(py, &value.extract::<PathBuf>()?)
+    } else {
+        Ok(value.into())
+    }
+}
+
+/// Parses a RON string with the default options of `loads`.
+fn load_str(py: Python, source: &str, print_errors: bool) -> PyResult<PyObject> {
+    let value = parse_str(py, source, source, print_errors)?;
+    Converter::new(py).convert_document(&value)
+}
+
+/// Reads and parses a file. The GIL is released while parsing, so other Python threads can run.
+fn parse_file(py: Python, path: &Path, print_errors: bool) -> PyResult<ron_parser::Value> {
+    py.allow_threads(|| read_and_parse(path, print_errors, Marks::default()))
+}
+
+/// Parses a file by memory-mapping it instead of reading it into a string, which avoids holding a
+/// copy of a large file in memory while parsing.
+fn parse_mapped(
+    py: Python,
+    path: &Path,
+    print_errors: bool,
+    marks: Marks,
+) -> PyResult<ron_parser::Value> {
+    let file = std::fs::File::open(path)?;
+    // Safety: the mapping is only read while parsing, and files that are modified concurrently
+    // would produce garbled input with `read_to_string` as well.
+    let map = unsafe { memmap2::Mmap::map(&file)? };
+    py.allow_threads(|| {
+        let source = compression::decode(path, &map)?;
+        let name = path.display().to_string();
+        parse_source(&source, &name, print_errors, marks)
+    })
+}
+
+/// Parses the file of `load`, memory-mapping it with `mmap` or reporting the bytes read to the
+/// `progress` callback. `marks` is passed to `marks::mark`.
+fn parse_for_load(
+    py: Python,
+    path: &Path,
+    print_errors: bool,
+    marks: Marks,
+    mmap: bool,
+    progress: Option<PyObject>,
+) -> PyResult<ron_parser::Value> {
+    match progress {
+        Some(callback) => {
+            let source = progress::read(py, path, &mut Progress::new(callback))?;
+            let name = path.display().to_string();
+            py.allow_threads(|| parse_source(&source, &name, print_errors, marks))
+        }
+        None if mmap => parse_mapped(py, path, print_errors, marks),
+        None => py.allow_threads(|| read_and_parse(path, print_errors, marks)),
     }
-    Ok(ron::Value::Struct(s))
 }
 
-fn try_val_to_py(
+/// Parses a string, releasing the GIL while parsing.
+fn parse_str(
     py: Python,
-    value: &ron_parser::Value,
-    preserve_structs: bool,
-    preserve_class_names: bool,
+    source: &str,
+    name: &str,
+    print_errors: bool,
+) -> PyResult<ron_parser::Value> {
+    py.allow_threads(|| parse_source(source, name, print_errors, Marks::default()))
+}
+
+/// Like `parse_file`, for callers that don't hold the GIL.
+fn read_and_parse(path: &Path, print_errors: bool, marks: Marks) -> PyResult<ron_parser::Value> {
+    let bytes = std::fs::read(path)?;
+    let source = compression::decode(path, &bytes)?;
+    let name = path.display().to_string();
+    parse_source(&source, &name, print_errors, marks)
+}
+
+/// Parses a document with `ron_parser`, after expanding variables and marking literals with
+/// `marks::mark`.
+fn parse_source(
+    source: &str,
+    name: &str,
+    print_errors: bool,
+    marks: Marks,
+) -> PyResult<ron_parser::Value> {
+    let source = variables::expand(source)?;
+    match ron_parser::parse(&marks::mark(&source, marks), None) {
+        Ok(value) => Ok(value),
+        Err(parse) => {
+            if print_errors {
+                // Errors are reported in the document as written rather than with marked numbers.
+                match ron_parser::parse(&source, None) {
+                    Err(unmarked) => unmarked.emit(),
+                    Ok(_) => parse.emit(),
+                }
+            }
+            Err(exceptions::PyValueError::new_err(format!(
+                "Fail to parse: {}",
+                name
+            )))
+        }
+    }
+}
+
+/// The Python type that sequences are converted to.
+#[derive(Clone, Copy, Debug)]
+enum SeqType {
+    List,
+    /// Tuples take less memory than lists and are hashable.
+    Tuple,
+}
+
+impl SeqType {
+    fn parse(seq_type: &str) -> PyResult<SeqType> {
+        match seq_type {
+            "list" => Ok(SeqType::List),
+            "tuple" => Ok(SeqType::Tuple),
+            _ => Err(exceptions::PyValueError::new_err(format!(
+                "seq_type must be \"list\" or \"tuple\", not \"{}\"",
+                seq_type
+            ))),
+        }
+    }
+}
+
+/// How NaN and infinities are loaded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum NonFinite {
+    Float,
+    /// Raise a `ValueError`, for data that is exported to formats without them, such as JSON.
+    Error,
+    None,
+}
+
+impl NonFinite {
+    fn parse(non_finite: &str) -> PyResult<NonFinite> {
+        match non_finite {
+            "float" => Ok(NonFinite::Float),
+            "error" => Ok(NonFinite::Error),
+            "none" => Ok(NonFinite::None),
+            _ => Err(exceptions::PyValueError::new_err(format!(
+                "non_finite must be \"float\", \"error\" or \"none\", not \"{}\"",
+                non_finite
+            ))),
+        }
+    }
+}
+
+/// How named structs and tuple structs, such as enum variants, are loaded and written.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EnumRepr {
+    /// As their fields, without their name.
+    Untagged,
+    /// As a dict with the name as its only key, as in `{"Adam": {"lr": 0.1}}`.
+    External,
+    /// As a dict with the name under `tag_key` and the fields under `content_key`.
+    Adjacent,
+}
+
+impl EnumRepr {
+    fn parse(enum_repr: &str) -> PyResult<EnumRepr> {
+        match enum_repr {
+            "untagged" => Ok(EnumRepr::Untagged),
+            "external" => Ok(EnumRepr::External),
+            "adjacent" => Ok(EnumRepr::Adjacent),
+            _ => Err(exceptions::PyValueError::new_err(format!(
+                "enum_repr must be \"untagged\", \"external\" or \"adjacent\", not \"{}\"",
+                enum_repr
+            ))),
+        }
+    }
+}
+
+/// Whether a value is anything but a NaN or infinite float.
+fn is_finite(value: &ron_parser::Value) -> bool {
+    match value {
+        ron_parser::Value::Number(ron_parser::Number::Float(f)) => f.get().is_finite(),
+        _ => true,
+    }
+}
+
+/// Whether a sequence is non-empty and contains only integers or only floats, for `numpy_arrays`.
+fn numeric_seq<'a>(mut seq: impl Iterator<Item = &'a ron_parser::Value> + Clone) -> bool {
+    use ron_parser::{Number, Value};
+    let integer = |value: &Value| matches!(value, Value::Number(Number::Integer(_)));
+    let float = |value: &Value| matches!(value, Value::Number(Number::Float(_)));
+    seq.clone().next().is_some() && (seq.clone().all(integer) || seq.all(float))
+}
+
+/// Converts a sequence accepted by `numeric_seq` to an `int64` or `float64` numpy array. The
+/// elements are written to a `bytearray` that backs the array, so no Python object is created per
+/// element.
+fn numpy_array<'a>(
+    py: Python,
+    seq: impl Iterator<Item = &'a ron_parser::Value>,
 ) -> PyResult<PyObject> {
-    use ron_parser::Value;
-    let p = match value {
-        Value::String(s) => s.into_py(py),
-        Value::Number(ron_parser::Number::Float(f)) => f.get().into_py(py),
-        Value::Number(ron_parser::Number::Integer(i)) => i.into_py(py),
-        Value::Bool(b) => b.into_py(py),
-        Value::Struct(s) => {
-            let dict = PyDict::new(py);
-            for (key, value) in s.iter() {
-                dict.set_item(
-                    key,
-                    try_val_to_py(py, value, preserve_structs, preserve_class_names)?,
-                )?;
+    use ron_parser::{Number, Value};
+    let mut bytes = Vec::with_capacity(seq.size_hint().0 * 8);
+    let mut dtype = "int64";
+    for value in seq {
+        match value {
+            Value::Number(Number::Integer(i)) => bytes.extend_from_slice(&i.to_ne_bytes()),
+            Value::Number(Number::Float(f)) => {
+                dtype = "float64";
+                bytes.extend_from_slice(&f.get().to_ne_bytes());
             }
-            match &s.name {
-                Some(name) if preserve_structs => {
-                    let namedtuple = PyModule::import(py, "collections")?
-                        .call_method1("namedtuple", (name.to_string(), dict.keys()))?;
-                    namedtuple.call((), Some(dict))?.into()
+            _ => unreachable!("numeric_seq only accepts numbers"),
+        }
+    }
+    let numpy = PyModule::import(py, "numpy")?;
+    let buffer = PyByteArray::new(py, &bytes);
+    Ok(numpy
+        .call_method1("frombuffer", (buffer, numpy.getattr(dtype)?))?
+        .into())
+}
+
+struct Converter<'py> {
+    py: Python<'py>,
+    preserve_structs: bool,
+    /// Convert named structs to `Record`s instead of namedtuples when preserving structs.
+    mutable_structs: bool,
+    preserve_class_names: bool,
+    /// The key of the struct name in dicts of named structs with `preserve_class_names`.
+    tag_key: String,
+    seq_type: SeqType,
+    /// Intern strings with `sys.intern`, so that equal strings share one object.
+    intern_strings: bool,
+    /// Convert sequences of only integers or only floats to numpy arrays.
+    numpy_arrays: bool,
+    non_finite: NonFinite,
+    /// Convert numbers to `decimal.Decimal`s.
+    use_decimal: bool,
+    /// Return numbers as `LiteralInt`s and `LiteralFloat`s that remember their source text.
+    preserve_numbers: bool,
+    /// Return chars as `Char`s instead of strings.
+    preserve_chars: bool,
+    /// Return `Some(x)` as `SomeValue`s instead of the values they wrap.
+    preserve_options: bool,
+    /// Convert tuple structs with one element, like `Seconds(5.0)`, to their element.
+    unwrap_newtypes: bool,
+    /// Return unit structs and enum variants without fields, like `Paused`, as `Unit`s.
+    preserve_units: bool,
+    /// How named structs and tuple structs are tagged with their name.
+    enum_repr: EnumRepr,
+    /// The key of the fields of structs tagged with `EnumRepr::Adjacent`.
+    content_key: String,
+    print_errors: bool,
+    /// Substitute `${VAR}` in strings with environment variables.
+    interpolate_env: bool,
+    /// Resolve `${path}` references to other values in the document.
+    interpolate_refs: bool,
+    /// Fall back to environment variables for references that aren't in the document.
+    env_fallback: bool,
+    includes: IncludeResolver,
+}
+
+impl<'py> Converter<'py> {
+    /// A converter with the default options of `load` and `loads`.
+    fn new(py: Python<'py>) -> Converter<'py> {
+        Converter {
+            py,
+            preserve_structs: false,
+            mutable_structs: false,
+            preserve_class_names: false,
+            tag_key: "!__name__".to_string(),
+            seq_type: SeqType::List,
+            intern_strings: false,
+            numpy_arrays: false,
+            non_finite: NonFinite::Float,
+            use_decimal: false,
+            preserve_numbers: false,
+            preserve_chars: false,
+            preserve_options: false,
+            unwrap_newtypes: false,
+            preserve_units: false,
+            enum_repr: EnumRepr::Untagged,
+            content_key: "content".to_string(),
+            print_errors: true,
+            interpolate_env: false,
+            interpolate_refs: false,
+            env_fallback: false,
+            includes: IncludeResolver::new(vec![], None, vec![], None, true),
+        }
+    }
+
+    /// Converts a top-level document and applies any post-processing passes.
+    fn convert_document(&mut self, value: &ron_parser::Value) -> PyResult<PyObject> {
+        let value = self.convert(value)?;
+        self.finish(value)
+    }
+
+    /// Applies the post-processing passes to a converted document.
+    fn finish(&self, value: PyObject) -> PyResult<PyObject> {
+        if self.interpolate_refs {
+            interpolate::resolve_references(self.py, value.as_ref(self.py), self.env_fallback)
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn string(&self, s: &str) -> PyObject {
+        if self.intern_strings {
+            PyString::intern(self.py, s).into()
+        } else {
+            s.into_py(self.py)
+        }
+    }
+
+    /// Converts the contents of a string literal.
+    fn text(&self, s: &str) -> PyResult<PyObject> {
+        if self.interpolate_env {
+            Ok(self.string(&interpolate::interpolate_env(s)?))
+        } else {
+            Ok(self.string(s))
+        }
+    }
+
+    /// Converts a struct with the given fields.
+    fn structure(&self, name: Option<&str>, dict: &PyDict) -> PyResult<PyObject> {
+        let py = self.py;
+        Ok(match name {
+            Some(name) if self.preserve_structs => match classes::get(py, name) {
+                Some(class) => class.call(py, (), Some(dict))?,
+                None if self.mutable_structs => {
+                    Py::new(py, record::Record::new(name.to_string(), dict))?.into_py(py)
                 }
-                Some(name) if preserve_class_names => {
-                    dict.set_item("!__name__", name)?;
-                    dict.into()
+                None => {
+                    let fields = dict.keys().extract::<Vec<String>>()?;
+                    classes::namedtuple(py, name, fields)?
+                        .call1(py, PyTuple::new(py, dict.values()))?
                 }
-                _ => dict.into(),
+            },
+            Some(name) if self.preserve_class_names => {
+                dict.set_item(self.string(&self.tag_key), self.string(name))?;
+                dict.into()
             }
-        }
-        Value::Tuple(name, t) => {
-            let mut elements = vec![];
-            for value in t.iter() {
-                elements.push(try_val_to_py(
-                    py,
-                    value,
-                    preserve_structs,
-                    preserve_class_names,
-                )?);
+            Some(name) if self.enum_repr != EnumRepr::Untagged => {
+                self.variant(name, dict.into())?
             }
+            _ => dict.into(),
+        })
+    }
 
-            match name {
-                Some(name) if preserve_structs => {
-                    let namedtuple = PyModule::import(py, "collections")?.call_method1(
-                        "namedtuple",
-                        (
-                            name.to_string(),
-                            (0..t.len()).map(|i| format!("_{}", i)).collect::<Vec<_>>(),
-                        ),
-                    )?;
+    /// Converts a tuple, or a tuple struct if it has a name.
+    fn tuple(&self, name: Option<&str>, mut elements: Vec<PyObject>) -> PyResult<PyObject> {
+        let py = self.py;
+        Ok(match name {
+            Some(_) if self.unwrap_newtypes && elements.len() == 1 => elements.pop().unwrap(),
+            Some(name) if self.preserve_structs => match classes::get(py, name) {
+                Some(class) => class.call1(py, PyTuple::new(py, elements))?,
+                None if self.mutable_structs => {
                     let dict = PyDict::new(py);
-                    for (i, value) in t.iter().enumerate() {
-                        dict.set_item(
-                            format!("_{}", i),
-                            try_val_to_py(py, value, preserve_structs, preserve_class_names)?,
-                        )?;
+                    for (i, value) in elements.into_iter().enumerate() {
+                        dict.set_item(format!("_{}", i), value)?;
                     }
-                    namedtuple.call((), Some(dict))?.into()
+                    Py::new(py, record::Record::new(name.to_string(), dict))?.into_py(py)
                 }
-                Some(name) if preserve_class_names => {
-                    let dict = PyDict::new(py);
-                    for (i, value) in t.iter().enumerate() {
-                        dict.set_item(
-                            format!("_{}", i),
-                            try_val_to_py(py, value, preserve_structs, preserve_class_names)?,
-                        )?;
-                    }
-                    dict.set_item("!__name__", name)?;
-                    dict.into()
+                None => {
+                    let fields = (0..elements.len()).map(|i| format!("_{}", i)).collect();
+                    classes::namedtuple(py, name, fields)?.call1(py, PyTuple::new(py, elements))?
                 }
-                _ => PyTuple::new(py, elements).into(),
+            },
+            Some(name) if self.preserve_class_names => {
+                let dict = PyDict::new(py);
+                for (i, value) in elements.into_iter().enumerate() {
+                    dict.set_item(self.string(&format!("_{}", i)), value)?;
+                }
+                dict.set_item(self.string(&self.tag_key), self.string(name))?;
+                dict.into()
             }
-        }
-        Value::Seq(s) => {
-            let mut list = vec![];
-            for value in s {
-                list.push(try_val_to_py(
-                    py,
-                    value,
-                    preserve_structs,
-                    preserve_class_names,
-                )?);
+            Some(name) if self.enum_repr != EnumRepr::Untagged => {
+                self.variant(name, PyTuple::new(py, elements).into())?
             }
-            PyList::new(py, list).into()
-        }
-        Value::Map(m) => {
-            let dict = PyDict::new(py);
-            for (key, value) in m.iter() {
-                dict.set_item(
-                    try_val_to_py(py, key, preserve_structs, preserve_class_names)?,
-                    try_val_to_py(py, value, preserve_structs, preserve_class_names)?,
-                )?;
+            _ => PyTuple::new(py, elements).into(),
+        })
+    }
+
+    /// Tags the fields of a named struct, or the elements of a tuple struct, with its name.
+    fn variant(&self, name: &str, payload: PyObject) -> PyResult<PyObject> {
+        let dict = PyDict::new(self.py);
+        match self.enum_repr {
+            EnumRepr::External => dict.set_item(self.string(name), payload)?,
+            _ => {
+                dict.set_item(self.string(&self.tag_key), self.string(name))?;
+                dict.set_item(self.string(&self.content_key), payload)?;
             }
-            dict.into()
         }
-        Value::Char(c) => c.into_py(py),
-        Value::Option(Some(value)) => {
-            try_val_to_py(py, value.as_ref(), preserve_structs, preserve_class_names)?
+        Ok(dict.into())
+    }
+
+    /// Converts a sequence with the given elements.
+    fn seq(&self, elements: Vec<PyObject>) -> PyObject {
+        match self.seq_type {
+            SeqType::List => PyList::new(self.py, elements).into(),
+            SeqType::Tuple => PyTuple::new(self.py, elements).into(),
         }
-        Value::Option(None) => None::<()>.into_py(py),
-        Value::Unit => ().into_py(py),
-        Value::Include(path) => {
-            return Err(exceptions::PyValueError::new_err(format!(
-                "Unresolved #include(\"{}\") directive",
-                path
-            )))
+    }
+
+    /// Converts the value of `Some(...)`.
+    fn some(&self, value: PyObject) -> PyResult<PyObject> {
+        if self.preserve_options {
+            Ok(Py::new(self.py, wrappers::SomeValue::new(value))?.into_py(self.py))
+        } else {
+            Ok(value)
         }
-    };
-    Ok(p)
+    }
+
+    /// Converts a unit struct or an enum variant without fields.
+    fn unit(&self, name: &str) -> PyResult<PyObject> {
+        if self.preserve_units {
+            Ok(Py::new(self.py, wrappers::Unit::new(name.to_string()))?.into_py(self.py))
+        } else {
+            Ok(self.py.None())
+        }
+    }
+
+    /// Converts a float, applying the `non_finite` policy to NaN and infinities.
+    fn float(&self, f: f64) -> PyResult<PyObject> {
+        if !f.is_finite() {
+            match self.non_finite {
+                NonFinite::Float => {}
+                NonFinite::None => return Ok(self.py.None()),
+                NonFinite::Error => {
+                    return Err(exceptions::PyValueError::new_err(format!(
+                        "Non-finite float {} is not allowed with non_finite=\"error\"",
+                        f
+                    )))
+                }
+            }
+        }
+        let float = f.into_py(self.py);
+        if self.use_decimal {
+            // Without the source text, the shortest representation of the float is used, which is
+            // the literal itself for literals with up to 15 significant digits.
+            self.decimal(float.as_ref(self.py).repr()?.to_str()?)
+        } else {
+            Ok(float)
+        }
+    }
+
+    /// Converts the text of a number to a `decimal.Decimal`.
+    fn decimal(&self, text: &str) -> PyResult<PyObject> {
+        let decimal = PyModule::import(self.py, "decimal")?.getattr("Decimal")?;
+        Ok(decimal.call1((text,))?.into())
+    }
+
+    /// Converts RON source, constructing the Python objects while parsing with `direct::convert`
+    /// when it supports the document, and from the `ron_parser` tree of the document otherwise.
+    fn convert_source(&mut self, source: &str, name: &str) -> PyResult<PyObject> {
+        if !self.numpy_arrays {
+            if let Some(value) = direct::convert(self, source)? {
+                return Ok(value);
+            }
+        }
+        let value = self.parse(source, name)?;
+        self.convert(&value)
+    }
+
+    /// Parses a document with `ron_parser`, marking the literals that the options need to see as
+    /// written.
+    fn parse(&self, source: &str, name: &str) -> PyResult<ron_parser::Value> {
+        let print_errors = self.print_errors;
+        let marks = Marks {
+            exact_numbers: self.use_decimal || self.preserve_numbers,
+            units: self.preserve_units,
+        };
+        self.py
+            .allow_threads(|| parse_source(source, name, print_errors, marks))
+    }
+
+    fn convert(&mut self, value: &ron_parser::Value) -> PyResult<PyObject> {
+        use ron_parser::Value;
+        let py = self.py;
+        let p = match value {
+            Value::String(s) => match marks::unmark(self, s)? {
+                Some(number) => number,
+                None => self.text(s)?,
+            },
+            Value::Number(ron_parser::Number::Float(f)) => self.float(f.get())?,
+            Value::Number(ron_parser::Number::Integer(i)) if self.use_decimal => {
+                self.decimal(&i.to_string())?
+            }
+            Value::Number(ron_parser::Number::Integer(i)) => i.into_py(py),
+            Value::Bool(b) => b.into_py(py),
+            Value::Struct(s) => {
+                let dict = PyDict::new(py);
+                for (key, value) in s.iter() {
+                    dict.set_item(self.string(key), self.convert(value)?)?;
+                }
+                let name = s.name.as_ref().map(|name| name.to_string());
+                self.structure(name.as_deref(), dict)?
+            }
+            Value::Tuple(name, t) => {
+                let mut elements = vec![];
+                for value in t.iter() {
+                    elements.push(self.convert(value)?);
+                }
+                let name = name.as_ref().map(|name| name.to_string());
+                self.tuple(name.as_deref(), elements)?
+            }
+            // Non-finite floats that aren't loaded as floats can't be stored in an array.
+            Value::Seq(s)
+                if self.numpy_arrays
+                    && numeric_seq(s.iter())
+                    && (self.non_finite == NonFinite::Float || s.iter().all(is_finite)) =>
+            {
+                numpy_array(py, s.iter())?
+            }
+            Value::Seq(s) => {
+                let mut list = vec![];
+                for value in s {
+                    list.push(self.convert(value)?);
+                }
+                self.seq(list)
+            }
+            Value::Map(m) => {
+                let dict = PyDict::new(py);
+                for (key, value) in m.iter() {
+                    dict.set_item(self.convert(key)?, self.convert(value)?)?;
+                }
+                dict.into()
+            }
+            Value::Char(c) if self.preserve_chars => {
+                Py::new(py, wrappers::Char::new(*c))?.into_py(py)
+            }
+            Value::Char(c) => c.into_py(py),
+            Value::Option(Some(value)) => {
+                let value = self.convert(value.as_ref())?;
+                self.some(value)?
+            }
+            Value::Option(None) => None::<()>.into_py(py),
+            Value::Unit => ().into_py(py),
+            Value::Include(pattern) if self.includes.is_glob(pattern) => {
+                let mut merged: Option<PyObject> = None;
+                for file in self.includes.expand(pattern)? {
+                    let (name, source) = self.includes.open_file(&file)?;
+                    let value = self.parse(&source, &name)?;
+                    let value = self.convert(&value)?;
+                    self.includes.exit();
+                    merged = Some(match merged {
+                        Some(base) => merge::merge_values(
+                            py,
+                            base.as_ref(py),
+                            value.as_ref(py),
+                            merge::ListMerge::Replace,
+                        )?,
+                        None => value,
+                    });
+                }
+                merged.expect("expand returns at least one file")
+            }
+            Value::Include(path) => {
+                let (name, source) = self.includes.open(py, path)?;
+                let value = self.parse(&source, &name)?;
+                let result = self.convert(&value);
+                self.includes.exit();
+                result?
+            }
+        };
+        Ok(p)
+    }
 }
@@ -0,0 +1,53 @@
+use std::sync::Mutex;
+
+use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyFloat, PyLong, PyType};
+
+/// The `LiteralInt` and `LiteralFloat` classes, created on first use.
+static CLASSES: Mutex<Option<(PyObject, PyObject)>> = Mutex::new(None);
+
+/// Returns the `LiteralInt` and `LiteralFloat` classes: subclasses of `int` and `float` whose
+/// instances are returned for numbers by `load` with `preserve_numbers`, and remember the RON
+/// source text of the number as `source`. `to_string` writes them as that text, so that `0xFF`
+/// or `1_000` aren't normalized when a loaded file is written back. Arithmetic on them returns
+/// plain numbers, which are written as usual.
+pub fn classes(py: Python) -> PyResult<(PyObject, PyObject)> {
+    if let Some((int, float)) = CLASSES.lock().unwrap().as_ref() {
+        return Ok((int.clone_ref(py), float.clone_ref(py)));
+    }
+    let class = |name: &str, base: &PyType| -> PyResult<PyObject> {
+        let namespace = [("__module__", "pyron")].into_py_dict(py);
+        Ok(py
+            .get_type::<PyType>()
+            .call1((name, (base,), namespace))?
+            .into())
+    };
+    let int = class("LiteralInt", py.get_type::<PyLong>())?;
+    let float = class("LiteralFloat", py.get_type::<PyFloat>())?;
+    let mut classes = CLASSES.lock().unwrap();
+    let (int, float) = classes.get_or_insert((int, float));
+    Ok((int.clone_ref(py), float.clone_ref(py)))
+}
+
+/// Returns a number that remembers the source text it was loaded from.
+pub fn literal(py: Python, value: PyObject, source: &str) -> PyResult<PyObject> {
+    let (int, float) = classes(py)?;
+    let class = if value.as_ref(py).downcast::<PyFloat>().is_ok() {
+        float
+    } else {
+        int
+    };
+    let literal = class.call1(py, (value,))?;
+    literal.setattr(py, "source", source)?;
+    Ok(literal)
+}
+
+/// Returns the source text of a number returned by `literal`, or `None` for other values.
+pub fn source<'py>(py: Python<'py>, value: &'py PyAny) -> PyResult<Option<&'py str>> {
+    let (int, float) = classes(py)?;
+    let class = value.get_type();
+    if !class.is(int.as_ref(py)) && !class.is(float.as_ref(py)) {
+        return Ok(None);
+    }
+    Ok(Some(value.getattr("source")?.extract()?))
+}
@@ -0,0 +1,128 @@
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::OnceLock;
+
+use pyo3::prelude::*;
+
+use crate::lexer::{self, TokenKind};
+use crate::numbers::{self, Number};
+use crate::Converter;
+
+/// Which literals `mark` replaces, besides the integers that `ron_parser` can't parse.
+#[derive(Clone, Copy, Default)]
+pub struct Marks {
+    /// All number literals, whose source text `ron_parser` drops.
+    pub exact_numbers: bool,
+    /// Unit structs and enum variants without fields, which `ron_parser` doesn't tell apart from
+    /// structs with empty parentheses.
+    pub units: bool,
+}
+
+/// Replaces literals of `source` that `ron_parser` doesn't parse the way `direct` does with strings
+/// for `unmark`, so that documents get the same values on either path. These are integers with a
+/// radix prefix or digit separators, integers beyond the range of `i64` and the literals selected
+/// by `marks`.
+pub fn mark(source: &str, marks: Marks) -> Cow<str> {
+    let tokens = lexer::tokenize(source)
+        .into_iter()
+        .filter(|token| !token.is_trivia())
+        .collect::<Vec<_>>();
+    let (number_marker, unit_marker) = markers();
+    let mut result = String::new();
+    let mut copied = 0;
+    // The open brackets, with `#` for those of attributes.
+    let mut brackets = vec![];
+    let mut sign = None;
+    for (i, token) in tokens.iter().enumerate() {
+        let text = token.text(source);
+        let previous = i.checked_sub(1).map(|i| tokens[i].kind);
+        let next = tokens.get(i + 1).map(|token| token.kind);
+        let replacement = match token.kind {
+            TokenKind::Punct('[') if previous == Some(TokenKind::Punct('!')) => {
+                brackets.push('#');
+                None
+            }
+            TokenKind::Punct(c @ ('(' | '[' | '{')) => {
+                brackets.push(c);
+                None
+            }
+            TokenKind::Punct(')' | ']' | '}') => {
+                brackets.pop();
+                None
+            }
+            TokenKind::Number => {
+                let literal = match sign {
+                    Some(_) => Cow::Owned(format!("-{}", text)),
+                    None => Cow::Borrowed(text),
+                };
+                let replace = match numbers::parse(&literal) {
+                    Some(Number::Integer { digits, radix }) => {
+                        marks.exact_numbers
+                            || radix != 10
+                            || text.contains('_')
+                            || digits.parse::<i64>().is_err()
+                    }
+                    Some(Number::Float(_)) => marks.exact_numbers,
+                    None => false,
+                };
+                replace.then(|| (sign.unwrap_or(token.start), number_marker, literal))
+            }
+            TokenKind::Ident
+                if marks.units
+                    && !brackets.contains(&'#')
+                    && previous != Some(TokenKind::Punct('#'))
+                    && next != Some(TokenKind::Punct('('))
+                    // Field names of structs, as opposed to keys of maps.
+                    && !(next == Some(TokenKind::Punct(':')) && brackets.last() == Some(&'('))
+                    && !matches!(text, "true" | "false" | "None" | "Some" | "inf" | "NaN") =>
+            {
+                Some((token.start, unit_marker, Cow::Borrowed(text)))
+            }
+            _ => None,
+        };
+        if let Some((start, marker, literal)) = replacement {
+            result.push_str(&source[copied..start]);
+            result.push_str(&format!("\"{}{}\"", marker, literal));
+            copied = token.end;
+        }
+        sign = match token.kind {
+            TokenKind::Punct('-') => Some(token.start),
+            _ => None,
+        };
+    }
+    if copied == 0 {
+        return Cow::Borrowed(source);
+    }
+    result.push_str(&source[copied..]);
+    Cow::Owned(result)
+}
+
+/// Converts a string written by `mark` back to its number or unit like `direct`, and returns
+/// `None` for other strings.
+pub fn unmark(converter: &Converter, s: &str) -> PyResult<Option<PyObject>> {
+    let (number_marker, unit_marker) = markers();
+    if let Some(literal) = s.strip_prefix(number_marker) {
+        if let Some(number) = numbers::parse(literal) {
+            return numbers::convert(converter, literal, number).map(Some);
+        }
+    } else if let Some(name) = s.strip_prefix(unit_marker) {
+        return converter.unit(name).map(Some);
+    }
+    Ok(None)
+}
+
+/// The prefixes of the strings written by `mark` for numbers and units. They include a random
+/// number that is chosen once per process, so that strings in documents can't be mistaken for
+/// marked literals.
+fn markers() -> (&'static str, &'static str) {
+    static MARKERS: OnceLock<(String, String)> = OnceLock::new();
+    let (number, unit) = MARKERS.get_or_init(|| {
+        let nonce = RandomState::new().build_hasher().finish();
+        (
+            format!("pyron-number-{:016x}:", nonce),
+            format!("pyron-unit-{:016x}:", nonce),
+        )
+    });
+    (number, unit)
+}
@@ -0,0 +1,173 @@
+use std::path::PathBuf;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyTuple};
+
+use crate::Converter;
+
+/// How lists are combined when both sides of a merge contain a list at the same path.
+#[derive(Clone, Copy)]
+pub enum ListMerge {
+    /// The list in the override replaces the base list.
+    Replace,
+    /// The list in the override is appended to the base list.
+    Concat,
+}
+
+impl ListMerge {
+    pub fn parse(lists: &str) -> PyResult<ListMerge> {
+        match lists {
+            "replace" => Ok(ListMerge::Replace),
+            "concat" => Ok(ListMerge::Concat),
+            _ => Err(exceptions::PyValueError::new_err(format!(
+                "lists must be \"replace\" or \"concat\", not \"{}\"",
+                lists
+            ))),
+        }
+    }
+}
+
+/// Deep-merges `override` onto `base` and returns the result without modifying either input.
+///
+/// * dicts are merged key by key
+/// * named structs (namedtuples from `preserve_structs`, or dicts tagged with `!__name__` from
+///   `preserve_class_names`) are merged field by field if they have the same name, and replaced
+///   otherwise
+/// * lists are replaced or concatenated, depending on `lists`
+/// * any other value in `override` replaces the value in `base`
+///
+/// `base` and `override` can also be paths (`os.PathLike`) of RON files to load.
+#[pyfunction]
+#[pyo3(signature = (base, r#override, lists = "replace"))]
+pub fn merge(py: Python, base: &PyAny, r#override: &PyAny, lists: &str) -> PyResult<PyObject> {
+    let lists = ListMerge::parse(lists)?;
+    let base = crate::load_if_path(py, base)?;
+    let r#override = crate::load_if_path(py, r#override)?;
+    merge_values(py, base.as_ref(py), r#override.as_ref(py), lists)
+}
+
+/// Loads the RON files (`.ron`, and compressed `.ron.gz` and `.ron.zst`) in a directory in lexical
+/// order of their names and deep-merges each onto the previous ones as by `merge`, so later files
+/// override earlier ones. Files are converted as by `load` with the given options. An empty
+/// directory results in an empty dict.
+#[pyfunction]
+#[pyo3(signature = (
+    path,
+    lists = "replace",
+    preserve_structs = false,
+    preserve_class_names = false,
+))]
+pub fn load_dir(
+    py: Python,
+    path: PathBuf,
+    lists: &str,
+    preserve_structs: bool,
+    preserve_class_names: bool,
+) -> PyResult<PyObject> {
+    if preserve_structs && preserve_class_names {
+        return Err(exceptions::PyValueError::new_err(
+            "preserve_structs and preserve_class_names cannot be true at the same time",
+        ));
+    }
+    let lists = ListMerge::parse(lists)?;
+    let mut files = vec![];
+    for entry in std::fs::read_dir(&path)? {
+        let file = entry?.path();
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        if [".ron", ".ron.gz", ".ron.zst"]
+            .iter()
+            .any(|extension| name.ends_with(extension))
+            && file.is_file()
+        {
+            files.push(file);
+        }
+    }
+    files.sort();
+    let mut merged: PyObject = PyDict::new(py).into();
+    for file in files {
+        let document = crate::parse_file(py, &file, true)?;
+        let mut converter = Converter::new(py);
+        converter.preserve_structs = preserve_structs;
+        converter.preserve_class_names = preserve_class_names;
+        converter.includes.enter_file(&file)?;
+        let value = converter.convert_document(&document)?;
+        merged = merge_values(py, merged.as_ref(py), value.as_ref(py), lists)?;
+    }
+    Ok(merged)
+}
+
+pub fn merge_values(
+    py: Python,
+    base: &PyAny,
+    override_: &PyAny,
+    lists: ListMerge,
+) -> PyResult<PyObject> {
+    if let (Ok(base), Ok(override_)) = (base.downcast::<PyDict>(), override_.downcast::<PyDict>()) {
+        if base.get_item("!__name__").map(|n| n.to_string())
+            != override_.get_item("!__name__").map(|n| n.to_string())
+        {
+            return Ok(override_.into());
+        }
+        return Ok(merge_dicts(py, base, override_, lists)?.into());
+    }
+    if let (Ok(base), Ok(override_)) = (base.downcast::<PyList>(), override_.downcast::<PyList>()) {
+        return Ok(match lists {
+            ListMerge::Replace => override_.into(),
+            ListMerge::Concat => {
+                let items = base.iter().chain(override_.iter()).collect::<Vec<_>>();
+                PyList::new(py, items).into()
+            }
+        });
+    }
+    if let (Ok(base), Ok(override_)) = (base.downcast::<PyTuple>(), override_.downcast::<PyTuple>())
+    {
+        if crate::is_namedtuple(base) && crate::is_namedtuple(override_) {
+            return merge_namedtuples(py, base, override_, lists);
+        }
+    }
+    Ok(override_.into())
+}
+
+fn merge_dicts<'py>(
+    py: Python<'py>,
+    base: &'py PyDict,
+    override_: &'py PyDict,
+    lists: ListMerge,
+) -> PyResult<&'py PyDict> {
+    let merged = base.copy()?;
+    for (key, value) in override_ {
+        let value = match merged.get_item(key) {
+            Some(existing) => merge_values(py, existing, value, lists)?,
+            None => value.into(),
+        };
+        merged.set_item(key, value)?;
+    }
+    Ok(merged)
+}
+
+fn merge_namedtuples(
+    py: Python,
+    base: &PyTuple,
+    override_: &PyTuple,
+    lists: ListMerge,
+) -> PyResult<PyObject> {
+    let base_type = base.get_type();
+    let name = base_type.name()?;
+    if name != override_.get_type().name()? {
+        return Ok(override_.into());
+    }
+    let merged = merge_dicts(
+        py,
+        base.call_method0("_asdict")?.downcast::<PyDict>()?,
+        override_.call_method0("_asdict")?.downcast::<PyDict>()?,
+        lists,
+    )?;
+    // Reuse the class of `base` if the override doesn't add any fields.
+    let class: PyObject = if merged.len() == base.len() {
+        base_type.into()
+    } else {
+        crate::classes::namedtuple(py, name, merged.keys().extract::<Vec<String>>()?)?
+    };
+    class.call1(py, PyTuple::new(py, merged.values()))
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyDict, PyTuple};
+
+use crate::record::Record;
+
+/// The field holding the schema version of a config.
+const VERSION: &str = "version";
+
+/// Migrations registered with `register_migration`, by the version they upgrade from.
+static MIGRATIONS: Mutex<Option<HashMap<i64, PyObject>>> = Mutex::new(None);
+
+/// Registers `migration` to upgrade configs with `version: <version>` to `version + 1`. It is
+/// called with the loaded config and returns the upgraded config, whose `version` field is then
+/// set to `version + 1`. With `migration=None`, the migration is unregistered.
+#[pyfunction]
+#[pyo3(signature = (version, migration))]
+pub fn register_migration(version: i64, migration: Option<PyObject>) {
+    let mut migrations = MIGRATIONS.lock().unwrap();
+    let migrations = migrations.get_or_insert_with(HashMap::new);
+    match migration {
+        Some(migration) => migrations.insert(version, migration),
+        None => migrations.remove(&version),
+    };
+}
+
+/// Upgrades a config by applying the registered migrations, starting at its `version` field, until
+/// there is no migration for its version. Returns the upgraded config and the versions it was
+/// migrated from. The config is a dict, or a struct loaded with `preserve_structs` (a namedtuple or
+/// `Record`) whose `version` field is replaced after each migration. Values without a `version`
+/// field are returned unchanged, and only the top level of a config is versioned.
+#[pyfunction]
+pub fn migrate(py: Python, value: PyObject) -> PyResult<(PyObject, Vec<i64>)> {
+    let mut value = value;
+    let mut applied = vec![];
+    loop {
+        let version = match version(value.as_ref(py))? {
+            Some(version) => version,
+            None => break,
+        };
+        let migration = match MIGRATIONS
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|migrations| migrations.get(&version))
+        {
+            Some(migration) => migration.clone_ref(py),
+            None => break,
+        };
+        value = migration.call1(py, (value,))?;
+        value = match set_version(py, value.as_ref(py), version + 1)? {
+            Some(value) => value,
+            None => {
+                return Err(exceptions::PyTypeError::new_err(format!(
+                    "Migration from version {} returned {} instead of a dict or struct",
+                    version,
+                    value.as_ref(py).get_type().name()?
+                )))
+            }
+        };
+        applied.push(version);
+    }
+    Ok((value, applied))
+}
+
+/// Returns the `version` field of a dict or of a struct loaded with `preserve_structs`.
+fn version(value: &PyAny) -> PyResult<Option<i64>> {
+    let version = if let Ok(dict) = value.downcast::<PyDict>() {
+        dict.get_item(VERSION)
+    } else if is_struct(value) && value.hasattr(VERSION)? {
+        Some(value.getattr(VERSION)?)
+    } else {
+        None
+    };
+    version.map(|version| version.extract()).transpose()
+}
+
+/// Returns `value` with its `version` field set to `version`, or `None` if it isn't a dict or a
+/// struct. Dicts and `Record`s are updated in place, and namedtuples are replaced.
+fn set_version(py: Python, value: &PyAny, version: i64) -> PyResult<Option<PyObject>> {
+    if let Ok(dict) = value.downcast::<PyDict>() {
+        dict.set_item(VERSION, version)?;
+    } else if value.downcast::<PyCell<Record>>().is_ok() {
+        value.setattr(VERSION, version)?;
+    } else if is_struct(value) {
+        let fields = [(VERSION, version)].into_py_dict(py);
+        let value = value.call_method("_replace", (), Some(fields))?;
+        return Ok(Some(value.into()));
+    } else {
+        return Ok(None);
+    }
+    Ok(Some(value.into()))
+}
+
+/// Whether a value is a namedtuple or `Record`, as returned for named structs by `load` with
+/// `preserve_structs`.
+fn is_struct(value: &PyAny) -> bool {
+    match value.downcast::<PyTuple>() {
+        Ok(tuple) => crate::is_namedtuple(tuple),
+        Err(_) => value.downcast::<PyCell<Record>>().is_ok(),
+    }
+}
+
+/// Migrates a config loaded from `source` like `migrate`, emitting a warning that lists the
+/// migrations that were applied so that the file can be updated.
+pub fn migrate_loaded(py: Python, value: PyObject, source: &str) -> PyResult<PyObject> {
+    let (value, applied) = migrate(py, value)?;
+    if let (Some(first), Some(last)) = (applied.first(), applied.last()) {
+        let steps = applied
+            .iter()
+            .map(|version| format!("{} -> {}", version, version + 1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = format!(
+            "{}: migrated from version {} to {} ({})",
+            source,
+            first,
+            last + 1,
+            steps
+        );
+        PyErr::warn(py, py.get_type::<exceptions::PyUserWarning>(), &message, 1)?;
+    }
+    Ok(value)
+}
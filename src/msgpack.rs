@@ -0,0 +1,222 @@
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList, PyLong, PyString, PyTuple};
+use rmpv::Value;
+
+use crate::wrappers::Unit;
+
+/// Extension type of tuples, whose payload is the encoded array of elements.
+const EXT_TUPLE: i8 = 1;
+/// Extension type of named structs (namedtuples and dataclasses), whose payload is the encoded
+/// array `[name, {field: value, ...}]`.
+const EXT_STRUCT: i8 = 2;
+/// Extension type of `Unit`s, whose payload is the UTF-8 name.
+const EXT_UNIT: i8 = 3;
+/// Extension type of integers beyond the range of MessagePack integers, whose payload is their
+/// decimal digits with an optional `-` sign.
+const EXT_INT: i8 = 4;
+
+/// Encodes a value, or a RON file given as `os.PathLike`, as MessagePack. Tuples, named structs,
+/// units and integers beyond 64 bits are encoded as extension types so that `from_msgpack` can
+/// restore them.
+#[pyfunction]
+pub fn to_msgpack(py: Python, value_or_path: &PyAny) -> PyResult<PyObject> {
+    let value = crate::load_if_path(py, value_or_path)?;
+    let bytes = encode(&to_value(py, value.as_ref(py))?);
+    Ok(PyBytes::new(py, &bytes).into())
+}
+
+/// Decodes MessagePack produced by `to_msgpack` (or any other encoder). Tuples come back as tuples,
+/// and named structs as namedtuples.
+#[pyfunction]
+pub fn from_msgpack(py: Python, data: &[u8]) -> PyResult<PyObject> {
+    to_py(py, &decode(data)?, false)
+}
+
+/// Magic bytes and format version at the start of the output of `compile`.
+const COMPILED_HEADER: &[u8] = b"PYRON\x00\x01";
+
+/// Loads a RON file, passing `kwargs` on to `load`, and returns the result in a compact binary
+/// form that `load_compiled` turns back into Python objects much faster than parsing the file.
+/// Includes and interpolation are resolved when compiling.
+#[pyfunction]
+#[pyo3(signature = (path, **kwargs))]
+pub fn compile(py: Python, path: &PyAny, kwargs: Option<&PyDict>) -> PyResult<PyObject> {
+    let load = PyModule::import(py, "pyron")?.getattr("load")?;
+    let value = load.call((path,), kwargs)?;
+    let mut bytes = COMPILED_HEADER.to_vec();
+    bytes.extend(encode(&to_value(py, value)?));
+    Ok(PyBytes::new(py, &bytes).into())
+}
+
+/// Loads a value compiled by `compile`.
+#[pyfunction]
+pub fn load_compiled(py: Python, data: &[u8]) -> PyResult<PyObject> {
+    match data.strip_prefix(COMPILED_HEADER) {
+        Some(data) => to_py(py, &decode(data)?, false),
+        None => Err(exceptions::PyValueError::new_err(
+            "Not a compiled pyron config, or compiled by an incompatible version",
+        )),
+    }
+}
+
+pub fn encode(value: &Value) -> Vec<u8> {
+    let mut bytes = vec![];
+    rmpv::encode::write_value(&mut bytes, value).expect("writing to a Vec can't fail");
+    bytes
+}
+
+pub fn decode(mut data: &[u8]) -> PyResult<Value> {
+    rmpv::decode::read_value(&mut data)
+        .map_err(|e| exceptions::PyValueError::new_err(format!("Invalid MessagePack: {}", e)))
+}
+
+pub fn to_value(py: Python, value: &PyAny) -> PyResult<Value> {
+    Ok(if let Ok(dict) = value.downcast::<PyDict>() {
+        let mut entries = vec![];
+        for (key, value) in dict {
+            entries.push((to_value(py, key)?, to_value(py, value)?));
+        }
+        Value::Map(entries)
+    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+        if crate::is_namedtuple(tuple) {
+            let fields = tuple.call_method0("_asdict")?;
+            named_struct(py, tuple.get_type().name()?, fields.downcast::<PyDict>()?)?
+        } else {
+            let elements = tuple
+                .iter()
+                .map(|element| to_value(py, element))
+                .collect::<PyResult<Vec<_>>>()?;
+            Value::Ext(EXT_TUPLE, encode(&Value::Array(elements)))
+        }
+    } else if let Ok(list) = value.downcast::<PyList>() {
+        Value::Array(
+            list.iter()
+                .map(|element| to_value(py, element))
+                .collect::<PyResult<_>>()?,
+        )
+    } else if let Ok(s) = value.downcast::<PyString>() {
+        Value::from(s.to_str()?)
+    } else if let Ok(bytes) = value.downcast::<PyBytes>() {
+        Value::Binary(bytes.as_bytes().to_vec())
+    } else if let Ok(b) = value.extract::<bool>() {
+        Value::Boolean(b)
+    } else if let Ok(i) = value.extract::<i64>() {
+        Value::from(i)
+    } else if let Ok(u) = value.extract::<u64>() {
+        Value::from(u)
+    } else if value.downcast::<PyLong>().is_ok() {
+        Value::Ext(EXT_INT, value.str()?.to_str()?.as_bytes().to_vec())
+    } else if let Ok(f) = value.extract::<f64>() {
+        Value::F64(f)
+    } else if value.is_none() {
+        Value::Nil
+    } else if let Ok(unit) = value.extract::<PyRef<Unit>>() {
+        Value::Ext(EXT_UNIT, unit.name().as_bytes().to_vec())
+    } else if PyModule::import(py, "dataclasses")?
+        .call_method1("is_dataclass", (value,))?
+        .extract::<bool>()?
+    {
+        let fields = PyDict::new(py);
+        for field in value
+            .getattr("__dataclass_fields__")?
+            .downcast::<PyDict>()?
+            .keys()
+        {
+            fields.set_item(field, value.getattr(field.extract::<&str>()?)?)?;
+        }
+        named_struct(py, value.get_type().name()?, fields)?
+    } else {
+        return Err(exceptions::PyValueError::new_err(format!(
+            "Unsupported type: {}",
+            value.get_type().name()?
+        )));
+    })
+}
+
+fn named_struct(py: Python, name: &str, fields: &PyDict) -> PyResult<Value> {
+    let payload = Value::Array(vec![Value::from(name), to_value(py, fields)?]);
+    Ok(Value::Ext(EXT_STRUCT, encode(&payload)))
+}
+
+/// Converts a decoded value to Python objects, interning strings with `sys.intern` if
+/// `intern_strings` is set, as `load` does.
+pub fn to_py(py: Python, value: &Value, intern_strings: bool) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Nil => py.None(),
+        Value::Boolean(b) => b.into_py(py),
+        Value::Integer(i) => match (i.as_i64(), i.as_u64()) {
+            (Some(i), _) => i.into_py(py),
+            (None, Some(u)) => u.into_py(py),
+            _ => unreachable!("MessagePack integers fit into i64 or u64"),
+        },
+        Value::F32(f) => f.into_py(py),
+        Value::F64(f) => f.into_py(py),
+        Value::String(s) => match s.as_str() {
+            Some(s) if intern_strings => PyString::intern(py, s).into(),
+            Some(s) => s.into_py(py),
+            None => PyBytes::new(py, s.as_bytes()).into(),
+        },
+        Value::Binary(bytes) => PyBytes::new(py, bytes).into(),
+        Value::Array(elements) => {
+            let elements = elements
+                .iter()
+                .map(|element| to_py(py, element, intern_strings))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, elements).into()
+        }
+        Value::Map(entries) => {
+            let dict = PyDict::new(py);
+            for (key, value) in entries {
+                let key = to_py(py, key, intern_strings)?;
+                dict.set_item(key, to_py(py, value, intern_strings)?)?;
+            }
+            dict.into()
+        }
+        Value::Ext(EXT_TUPLE, payload) => match decode(payload)? {
+            Value::Array(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| to_py(py, element, intern_strings))
+                    .collect::<PyResult<Vec<_>>>()?;
+                PyTuple::new(py, elements).into()
+            }
+            _ => return Err(invalid_payload(EXT_TUPLE)),
+        },
+        Value::Ext(EXT_STRUCT, payload) => match decode(payload)? {
+            Value::Array(payload) if payload.len() == 2 => {
+                let name = to_py(py, &payload[0], intern_strings)?;
+                let fields = to_py(py, &payload[1], intern_strings)?;
+                let fields = fields.as_ref(py).downcast::<PyDict>()?;
+                let namedtuple = crate::classes::namedtuple(
+                    py,
+                    name.extract::<&str>(py)?,
+                    fields.keys().extract::<Vec<String>>()?,
+                )?;
+                namedtuple.call1(py, PyTuple::new(py, fields.values()))?
+            }
+            _ => return Err(invalid_payload(EXT_STRUCT)),
+        },
+        Value::Ext(EXT_UNIT, payload) => match std::str::from_utf8(payload) {
+            Ok(name) => Py::new(py, Unit::new(name.to_string()))?.into_py(py),
+            Err(_) => return Err(invalid_payload(EXT_UNIT)),
+        },
+        Value::Ext(EXT_INT, payload) => match std::str::from_utf8(payload) {
+            Ok(digits) => py.get_type::<PyLong>().call1((digits,))?.into(),
+            Err(_) => return Err(invalid_payload(EXT_INT)),
+        },
+        Value::Ext(code, _) => {
+            return Err(exceptions::PyValueError::new_err(format!(
+                "Unsupported MessagePack extension type {}",
+                code
+            )))
+        }
+    })
+}
+
+fn invalid_payload(code: i8) -> PyErr {
+    exceptions::PyValueError::new_err(format!(
+        "Invalid payload of MessagePack extension type {}",
+        code
+    ))
+}
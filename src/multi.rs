@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+
+use crate::lexer::{self, TokenKind};
+use crate::Converter;
+
+/// The line separating the documents of a file read by `load_all` and written by `dump_all`.
+const SEPARATOR: &str = "---";
+
+/// Loads all documents of a RON file containing several documents separated by `---` lines, such
+/// as a file written by `dump_all`. Documents are converted as by `load` with the given options,
+/// and documents without any value (only whitespace and comments) are skipped.
+#[pyfunction]
+#[pyo3(signature = (path, preserve_structs = false, preserve_class_names = false))]
+pub fn load_all(
+    py: Python,
+    path: PathBuf,
+    preserve_structs: bool,
+    preserve_class_names: bool,
+) -> PyResult<PyObject> {
+    if preserve_structs && preserve_class_names {
+        return Err(exceptions::PyValueError::new_err(
+            "preserve_structs and preserve_class_names cannot be true at the same time",
+        ));
+    }
+    let source = crate::compression::read_to_string(&path)?;
+    let mut values = vec![];
+    for (i, document) in split(&source).into_iter().enumerate() {
+        if lexer::tokenize(document)
+            .iter()
+            .all(|token| token.is_trivia())
+        {
+            continue;
+        }
+        let name = format!("{} (document {})", path.display(), i + 1);
+        let document = crate::parse_str(py, document, &name, true)?;
+        let mut converter = Converter::new(py);
+        converter.preserve_structs = preserve_structs;
+        converter.preserve_class_names = preserve_class_names;
+        converter.includes.enter_file(&path)?;
+        values.push(converter.convert_document(&document)?);
+    }
+    Ok(PyList::new(py, values).into())
+}
+
+/// Serializes each of `values` like `to_string` and writes them to `path`, separated by `---`
+/// lines.
+#[pyfunction]
+pub fn dump_all(py: Python, values: Vec<&PyAny>, path: PathBuf) -> PyResult<()> {
+    let mut documents = vec![];
+    for value in values {
+        documents.push(crate::serialize(py, value)?);
+    }
+    let text = format!("{}\n", documents.join(&format!("\n{}\n", SEPARATOR)));
+    std::fs::write(&path, crate::compression::encode(&path, &text)?)?;
+    Ok(())
+}
+
+/// Splits `source` at lines consisting of just `---`, outside of strings and comments.
+fn split(source: &str) -> Vec<&str> {
+    let tokens = lexer::tokenize(source);
+    let mut documents = vec![];
+    let mut start = 0;
+    for token in &tokens {
+        // `-` is always a token of its own, so a `-` token outside of strings and comments that
+        // starts `---` is followed by two more.
+        let is_separator = token.kind == TokenKind::Punct('-')
+            && source[token.start..].starts_with(SEPARATOR)
+            && source[..token.start]
+                .rsplit('\n')
+                .next()
+                .map_or(true, |line| line.trim().is_empty());
+        if !is_separator {
+            continue;
+        }
+        let end = token.start + SEPARATOR.len();
+        let rest_of_line = source[end..].split('\n').next().unwrap_or("");
+        if rest_of_line.trim().is_empty() {
+            documents.push(&source[start..token.start]);
+            start = (end + rest_of_line.len() + 1).min(source.len());
+        }
+    }
+    documents.push(&source[start..]);
+    documents
+}
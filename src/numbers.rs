@@ -0,0 +1,83 @@
+use pyo3::prelude::*;
+use pyo3::types::PyLong;
+
+use crate::Converter;
+
+/// A number literal parsed by `parse`.
+pub enum Number {
+    /// An integer, as its sign and digits without separators in `radix`.
+    Integer {
+        digits: String,
+        radix: u32,
+    },
+    Float(f64),
+}
+
+/// Parses a number literal with an optional `-` sign: an integer in decimal, hex (`0x`), binary
+/// (`0b`) or octal (`0o`) with optional `_` digit separators, or a float with a fraction or an
+/// exponent. Returns `None` for other literals, such as those with type suffixes, which are left
+/// to `ron_parser`.
+pub fn parse(literal: &str) -> Option<Number> {
+    let (sign, text) = match literal.strip_prefix('-') {
+        Some(text) => ("-", text),
+        None => ("", literal),
+    };
+    let (radix, digits) = match text.get(..2) {
+        Some("0x") => (16, &text[2..]),
+        Some("0b") => (2, &text[2..]),
+        Some("0o") => (8, &text[2..]),
+        _ => (10, text),
+    };
+    if radix == 10 && text.contains(|c| matches!(c, '.' | 'e' | 'E')) {
+        if !text
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+        {
+            return None;
+        }
+        return literal.parse().ok().map(Number::Float);
+    }
+    let digits = digits.replace('_', "");
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    Some(Number::Integer {
+        digits: format!("{}{}", sign, digits),
+        radix,
+    })
+}
+
+/// Converts a number literal accepted by `parse`, applying the `use_decimal`, `preserve_numbers`
+/// and `non_finite` options of `converter`.
+pub fn convert(converter: &Converter, literal: &str, number: Number) -> PyResult<PyObject> {
+    let py = converter.py;
+    let value = match number {
+        // Decimals are parsed from the literal, so that they aren't rounded.
+        Number::Float(_) if converter.use_decimal => return converter.decimal(literal),
+        Number::Float(float) if converter.preserve_numbers && float.is_finite() => {
+            float.into_py(py)
+        }
+        // Overflowing exponents are infinite.
+        Number::Float(float) => return converter.float(float),
+        Number::Integer { digits, radix } => {
+            let int = if let Ok(int) = i64::from_str_radix(&digits, radix) {
+                int.into_py(py)
+            } else if let Ok(int) = i128::from_str_radix(&digits, radix) {
+                int.into_py(py)
+            } else if let Ok(int) = u128::from_str_radix(&digits, radix) {
+                int.into_py(py)
+            } else {
+                py.get_type::<PyLong>().call1((digits, radix))?.into()
+            };
+            if converter.use_decimal {
+                return converter.decimal(int.as_ref(py).str()?.to_str()?);
+            }
+            int
+        }
+    };
+    if converter.preserve_numbers {
+        crate::literals::literal(py, value, literal)
+    } else {
+        Ok(value)
+    }
+}
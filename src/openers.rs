@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use pyo3::prelude::*;
+
+/// Openers registered with `register_opener`, by URL scheme.
+static OPENERS: Mutex<Option<HashMap<String, PyObject>>> = Mutex::new(None);
+
+/// Registers `opener` for URLs with the given scheme, such as `s3`, so that `load("s3://...")`
+/// calls `opener(url)` and parses what it returns: the text or bytes of the document, or a
+/// file-like object to read them from. With `opener=None`, the scheme is unregistered.
+#[pyfunction]
+#[pyo3(signature = (scheme, opener))]
+pub fn register_opener(scheme: &str, opener: Option<PyObject>) {
+    let mut openers = OPENERS.lock().unwrap();
+    let openers = openers.get_or_insert_with(HashMap::new);
+    match opener {
+        Some(opener) => openers.insert(scheme.to_string(), opener),
+        None => openers.remove(scheme),
+    };
+}
+
+/// Opens `url` with the opener registered for its scheme, returning the source text (or bytes), or
+/// `None` if `url` isn't a URL with a registered scheme.
+pub fn open<'py>(py: Python<'py>, url: &str) -> PyResult<Option<&'py PyAny>> {
+    let scheme = match url.split_once("://") {
+        Some((scheme, _)) => scheme,
+        None => return Ok(None),
+    };
+    let opener = match OPENERS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|openers| openers.get(scheme))
+    {
+        Some(opener) => opener.clone_ref(py),
+        None => return Ok(None),
+    };
+    let opened = opener.into_ref(py).call1((url,))?;
+    if opened.hasattr("read")? {
+        opened.call_method0("read").map(Some)
+    } else {
+        Ok(Some(opened))
+    }
+}
@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+
+use crate::Converter;
+
+/// Loads several RON files and returns their values in the same order. The files are read and
+/// parsed on `threads` threads (by default, one per CPU) without holding the GIL, and only the
+/// conversion to Python objects happens on the calling thread.
+#[pyfunction]
+#[pyo3(signature = (
+    paths,
+    preserve_structs = false,
+    preserve_class_names = false,
+    print_errors = true,
+    threads = None,
+))]
+pub fn load_many(
+    py: Python,
+    paths: Vec<PathBuf>,
+    preserve_structs: bool,
+    preserve_class_names: bool,
+    print_errors: bool,
+    threads: Option<usize>,
+) -> PyResult<PyObject> {
+    if preserve_structs && preserve_class_names {
+        return Err(exceptions::PyValueError::new_err(
+            "preserve_structs and preserve_class_names cannot be true at the same time",
+        ));
+    }
+    let threads = threads
+        .or_else(|| std::thread::available_parallelism().map(usize::from).ok())
+        .unwrap_or(1)
+        .max(1);
+    let documents = py.allow_threads(|| parse_all(&paths, threads, print_errors));
+    let mut values = vec![];
+    for (path, document) in paths.iter().zip(documents) {
+        let mut converter = Converter::new(py);
+        converter.preserve_structs = preserve_structs;
+        converter.preserve_class_names = preserve_class_names;
+        converter.print_errors = print_errors;
+        converter.includes.enter_file(path)?;
+        values.push(converter.convert_document(&document?)?);
+    }
+    Ok(PyList::new(py, values).into())
+}
+
+/// Parses `paths` on up to `threads` threads, each handling a contiguous chunk of the files.
+fn parse_all(
+    paths: &[PathBuf],
+    threads: usize,
+    print_errors: bool,
+) -> Vec<PyResult<ron_parser::Value>> {
+    if paths.is_empty() {
+        return vec![];
+    }
+    let chunk_size = (paths.len() + threads - 1) / threads;
+    std::thread::scope(|scope| {
+        let handles = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|path| crate::read_and_parse(path, print_errors, Default::default()))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("parser thread panicked"))
+            .collect()
+    })
+}
@@ -0,0 +1,362 @@
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyTuple};
+
+/// Splits a dotted path such as `model.layers[0].dim` into its components.
+pub fn split(path: &str) -> Vec<String> {
+    path.replace('[', ".")
+        .replace(']', "")
+        .split('.')
+        .filter(|part| !part.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Looks up a dotted path in `root`, returning `None` if any component of the path doesn't exist.
+pub fn get<'py>(root: &'py PyAny, path: &str) -> PyResult<Option<&'py PyAny>> {
+    let mut node = root;
+    for part in split(path) {
+        match child(node, &part)? {
+            Some(child) => node = child,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(node))
+}
+
+/// Dict entries are looked up by string key first and by integer key if the part is a number,
+/// namedtuples by field name, and lists and tuples by index.
+fn child<'py>(node: &'py PyAny, part: &str) -> PyResult<Option<&'py PyAny>> {
+    Ok(if let Ok(dict) = node.downcast::<PyDict>() {
+        match (dict.get_item(part), part.parse::<i64>()) {
+            (Some(child), _) => Some(child),
+            (None, Ok(index)) => dict.get_item(index),
+            (None, Err(_)) => None,
+        }
+    } else if let Ok(tuple) = node.downcast::<PyTuple>() {
+        if crate::is_namedtuple(tuple) && node.hasattr(part)? {
+            Some(node.getattr(part)?)
+        } else {
+            part.parse::<usize>()
+                .ok()
+                .and_then(|i| tuple.get_item(i).ok())
+        }
+    } else if let Ok(list) = node.downcast::<PyList>() {
+        part.parse::<usize>()
+            .ok()
+            .and_then(|i| list.get_item(i).ok())
+    } else {
+        None
+    })
+}
+
+/// Returns a copy of `root` with the value at `path` replaced by `value`. Only the containers
+/// along the path are copied. Missing dict entries along the path are created.
+pub fn set(py: Python, root: &PyAny, path: &str, value: PyObject) -> PyResult<PyObject> {
+    set_parts(py, root, &split(path), path, value)
+}
+
+fn set_parts(
+    py: Python,
+    node: &PyAny,
+    parts: &[String],
+    path: &str,
+    value: PyObject,
+) -> PyResult<PyObject> {
+    let (part, rest) = match parts.split_first() {
+        Some(split) => split,
+        None => return Ok(value),
+    };
+    if let Ok(dict) = node.downcast::<PyDict>() {
+        let copy = dict.copy()?;
+        let key = match part.parse::<i64>() {
+            Ok(index) if dict.get_item(part.as_str()).is_none() && dict.contains(index)? => {
+                index.into_py(py)
+            }
+            _ => part.into_py(py),
+        };
+        let child = match copy.get_item(&key) {
+            Some(child) => set_parts(py, child, rest, path, value)?,
+            None => set_parts(py, PyDict::new(py), rest, path, value)?,
+        };
+        copy.set_item(key, child)?;
+        return Ok(copy.into());
+    }
+    if let Ok(tuple) = node.downcast::<PyTuple>() {
+        if crate::is_namedtuple(tuple) && node.hasattr(part.as_str())? {
+            let child = set_parts(py, node.getattr(part.as_str())?, rest, path, value)?;
+            let kwargs = PyDict::new(py);
+            kwargs.set_item(part, child)?;
+            return Ok(node.call_method("_replace", (), Some(kwargs))?.into());
+        }
+        let items = set_item(py, tuple.iter().collect(), part, rest, path, value)?;
+        return Ok(PyTuple::new(py, items).into());
+    }
+    if let Ok(list) = node.downcast::<PyList>() {
+        let items = set_item(py, list.iter().collect(), part, rest, path, value)?;
+        return Ok(PyList::new(py, items).into());
+    }
+    Err(exceptions::PyKeyError::new_err(format!(
+        "Cannot set {}: {} has no field {}",
+        path,
+        node.get_type().name()?,
+        part
+    )))
+}
+
+/// Sets an element of a sequence, where an index one past the end appends a new element.
+fn set_item(
+    py: Python,
+    items: Vec<&PyAny>,
+    part: &str,
+    rest: &[String],
+    path: &str,
+    value: PyObject,
+) -> PyResult<Vec<PyObject>> {
+    let i = index(part, items.len() + 1, path)?;
+    let mut result = items
+        .iter()
+        .map(|&item| item.into())
+        .collect::<Vec<PyObject>>();
+    if i == items.len() {
+        result.push(set_parts(py, PyDict::new(py), rest, path, value)?);
+    } else {
+        result[i] = set_parts(py, items[i], rest, path, value)?;
+    }
+    Ok(result)
+}
+
+/// Returns a copy of `root` with the value at `path` removed. Only the containers along the path
+/// are copied.
+pub fn remove(py: Python, root: &PyAny, path: &str) -> PyResult<PyObject> {
+    remove_parts(py, root, &split(path), path)
+}
+
+fn remove_parts(py: Python, node: &PyAny, parts: &[String], path: &str) -> PyResult<PyObject> {
+    let (part, rest) = match parts.split_first() {
+        Some(split) => split,
+        None => {
+            return Err(exceptions::PyKeyError::new_err(
+                "Cannot remove the root of a value",
+            ))
+        }
+    };
+    let missing = || exceptions::PyKeyError::new_err(format!("Cannot remove {}: not found", path));
+    if let Ok(dict) = node.downcast::<PyDict>() {
+        let copy = dict.copy()?;
+        let key = match child(node, part)? {
+            Some(_) if dict.get_item(part.as_str()).is_some() => part.into_py(py),
+            Some(_) => part.parse::<i64>()?.into_py(py),
+            None => return Err(missing()),
+        };
+        if rest.is_empty() {
+            copy.del_item(&key)?;
+        } else {
+            let child = remove_parts(py, dict.get_item(&key).unwrap(), rest, path)?;
+            copy.set_item(key, child)?;
+        }
+        return Ok(copy.into());
+    }
+    let items = if let Ok(list) = node.downcast::<PyList>() {
+        list.iter().collect::<Vec<_>>()
+    } else if let Ok(tuple) = node.downcast::<PyTuple>() {
+        if crate::is_namedtuple(tuple) && node.hasattr(part.as_str())? {
+            if rest.is_empty() {
+                return Err(exceptions::PyKeyError::new_err(format!(
+                    "Cannot remove {}: fields of {} can't be removed",
+                    path,
+                    node.get_type().name()?
+                )));
+            }
+            let child = remove_parts(py, node.getattr(part.as_str())?, rest, path)?;
+            let kwargs = PyDict::new(py);
+            kwargs.set_item(part, child)?;
+            return Ok(node.call_method("_replace", (), Some(kwargs))?.into());
+        }
+        tuple.iter().collect::<Vec<_>>()
+    } else {
+        return Err(missing());
+    };
+    let i = index(part, items.len(), path)?;
+    let mut result = items
+        .iter()
+        .map(|&item| item.into())
+        .collect::<Vec<PyObject>>();
+    if rest.is_empty() {
+        result.remove(i);
+    } else {
+        result[i] = remove_parts(py, items[i], rest, path)?;
+    }
+    Ok(if node.downcast::<PyList>().is_ok() {
+        PyList::new(py, result).into()
+    } else {
+        PyTuple::new(py, result).into()
+    })
+}
+
+fn index(part: &str, len: usize, path: &str) -> PyResult<usize> {
+    match part.parse::<usize>() {
+        Ok(i) if i < len => Ok(i),
+        _ => Err(exceptions::PyIndexError::new_err(format!(
+            "Invalid index {} in {}",
+            part, path
+        ))),
+    }
+}
+
+/// Sets each dotted path in `overrides` to the corresponding value and returns the result without
+/// modifying `value`. String values are parsed as RON, e.g. `{"optimizer.lr": "3e-4"}` sets a
+/// float; strings that aren't valid RON, and bare identifiers like `resnet`, are kept as strings.
+/// With a dataclass `schema`, string values are instead converted to the annotated type of their
+/// field, raising a `ValueError` for values that don't match it.
+#[pyfunction]
+#[pyo3(signature = (value, overrides, schema = None))]
+pub fn apply_overrides(
+    py: Python,
+    value: &PyAny,
+    overrides: &PyDict,
+    schema: Option<&PyAny>,
+) -> PyResult<PyObject> {
+    let mut result: PyObject = value.into();
+    for (path, value) in overrides {
+        let path = path.extract::<&str>()?;
+        let value = match (value.extract::<&str>(), schema) {
+            (Ok(text), Some(schema)) => {
+                let hint = crate::schema::field_type(py, schema, path)?;
+                crate::schema::coerce(py, text, hint, path)?
+            }
+            (Ok(text), None) => parse_override(py, text),
+            (Err(_), _) => value.into(),
+        };
+        result = set(py, result.as_ref(py), path, value)?;
+    }
+    Ok(result)
+}
+
+pub fn parse_override(py: Python, text: &str) -> PyObject {
+    let trimmed = text.trim();
+    let is_identifier = trimmed.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && trimmed.chars().all(|c| c.is_alphanumeric() || c == '_');
+    let is_keyword = matches!(trimmed, "true" | "false" | "None" | "inf" | "NaN");
+    if is_identifier && !is_keyword {
+        return text.into_py(py);
+    }
+    crate::load_str(py, text, false).unwrap_or_else(|_| text.into_py(py))
+}
+
+/// Flattens nested dicts, lists and namedtuples into a single dict mapping dotted paths such as
+/// `model.layers[0].dim` to leaf values, e.g. for logging hyperparameters to experiment trackers.
+/// Plain tuples, empty containers and scalars are leaves.
+#[pyfunction]
+pub fn flatten(py: Python, value: &PyAny) -> PyResult<PyObject> {
+    let flat = PyDict::new(py);
+    flatten_into(flat, "", value)?;
+    Ok(flat.into())
+}
+
+fn flatten_into(flat: &PyDict, path: &str, value: &PyAny) -> PyResult<()> {
+    if let Ok(dict) = value.downcast::<PyDict>() {
+        if !dict.is_empty() {
+            for (key, value) in dict {
+                flatten_into(flat, &crate::diff::join(path, key)?, value)?;
+            }
+            return Ok(());
+        }
+    } else if let Ok(list) = value.downcast::<PyList>() {
+        if !list.is_empty() {
+            for (i, value) in list.iter().enumerate() {
+                flatten_into(flat, &format!("{}[{}]", path, i), value)?;
+            }
+            return Ok(());
+        }
+    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+        if crate::is_namedtuple(tuple) {
+            for (key, value) in value.call_method0("_asdict")?.downcast::<PyDict>()? {
+                flatten_into(flat, &crate::diff::join(path, key)?, value)?;
+            }
+            return Ok(());
+        }
+    }
+    flat.set_item(path, value)
+}
+
+/// Inverse of `flatten`: builds nested dicts and lists from a dict of dotted paths.
+#[pyfunction]
+pub fn unflatten(py: Python, flat: &PyDict) -> PyResult<PyObject> {
+    let root = PyDict::new(py);
+    for (path, value) in flat {
+        let path = path.extract::<&str>()?;
+        if path.is_empty() {
+            return Ok(value.into());
+        }
+        insert(py, root, &split_indexed(path), value, path)?;
+    }
+    Ok(root.into())
+}
+
+/// Like `split`, but also returns whether each component was a `[i]` index.
+fn split_indexed(path: &str) -> Vec<(String, bool)> {
+    let mut parts = vec![];
+    for segment in path.split('.') {
+        let mut pieces = segment.split('[');
+        if let Some(key) = pieces.next().filter(|key| !key.is_empty()) {
+            parts.push((key.to_string(), false));
+        }
+        for index in pieces {
+            parts.push((index.trim_end_matches(']').to_string(), true));
+        }
+    }
+    parts
+}
+
+fn insert(
+    py: Python,
+    node: &PyAny,
+    parts: &[(String, bool)],
+    value: &PyAny,
+    path: &str,
+) -> PyResult<()> {
+    let ((part, _), rest) = match parts.split_first() {
+        Some(split) => split,
+        None => return Ok(()),
+    };
+    let new_child = || -> PyObject {
+        match rest.first() {
+            Some((_, true)) => PyList::empty(py).into(),
+            _ => PyDict::new(py).into(),
+        }
+    };
+    if let Ok(dict) = node.downcast::<PyDict>() {
+        if rest.is_empty() {
+            return dict.set_item(part, value);
+        }
+        let child = match dict.get_item(part.as_str()) {
+            Some(child) => child,
+            None => {
+                dict.set_item(part, new_child())?;
+                dict.get_item(part.as_str()).unwrap()
+            }
+        };
+        return insert(py, child, rest, value, path);
+    }
+    if let Ok(list) = node.downcast::<PyList>() {
+        let i = index(part, list.len() + 1, path)?;
+        if i == list.len() {
+            list.append(if rest.is_empty() {
+                value.into()
+            } else {
+                new_child()
+            })?;
+        } else if rest.is_empty() {
+            list.set_item(i, value)?;
+        }
+        if !rest.is_empty() {
+            return insert(py, list.get_item(i)?, rest, value, path);
+        }
+        return Ok(());
+    }
+    Err(exceptions::PyValueError::new_err(format!(
+        "Cannot unflatten {}: conflicting value at {}",
+        path, part
+    )))
+}
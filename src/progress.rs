@@ -0,0 +1,65 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use pyo3::prelude::*;
+
+/// Minimum number of bytes processed between two calls of a progress callback.
+const INTERVAL: usize = 1 << 20;
+
+/// Calls a progress callback with the number of bytes processed so far, at most once per
+/// `INTERVAL` bytes and once more when done.
+pub struct Progress {
+    callback: PyObject,
+    bytes: usize,
+    reported: Option<usize>,
+}
+
+impl Progress {
+    pub fn new(callback: PyObject) -> Progress {
+        Progress {
+            callback,
+            bytes: 0,
+            reported: None,
+        }
+    }
+
+    pub fn advance(&mut self, py: Python, bytes: usize) -> PyResult<()> {
+        self.bytes += bytes;
+        if self.bytes - self.reported.unwrap_or(0) >= INTERVAL {
+            self.report(py)?;
+        }
+        Ok(())
+    }
+
+    /// Reports the final count, unless it was already reported.
+    pub fn finish(&mut self, py: Python) -> PyResult<()> {
+        if self.reported != Some(self.bytes) {
+            self.report(py)?;
+        }
+        Ok(())
+    }
+
+    fn report(&mut self, py: Python) -> PyResult<()> {
+        self.callback.call1(py, (self.bytes,))?;
+        self.reported = Some(self.bytes);
+        Ok(())
+    }
+}
+
+/// Reads a file into a string, reporting the bytes read to `progress`.
+pub fn read(py: Python, path: &Path, progress: &mut Progress) -> PyResult<String> {
+    let mut file = File::open(path)?;
+    let mut bytes = vec![];
+    let mut chunk = vec![0; 1 << 16];
+    loop {
+        let n = py.allow_threads(|| file.read(&mut chunk))?;
+        if n == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&chunk[..n]);
+        progress.advance(py, n)?;
+    }
+    progress.finish(py)?;
+    Ok(crate::compression::decode(path, &bytes)?.into_owned())
+}
@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::PyList;
+use ron_parser::Value;
+
+use crate::{Converter, Source};
+
+/// Extracts the value at a dotted path from a RON file or string, converting only the selected
+/// part of the document to Python objects. `*` matches every element of a sequence or every field
+/// of a struct or map, in which case a list of all matches is returned. Returns `default` if the
+/// path doesn't exist.
+///
+/// The file is given by its path as a `str` or `os.PathLike`, as for `load`, and RON text as
+/// `text`, such as `get(text="(lr: 0.1)", path="lr")`.
+#[pyfunction]
+#[pyo3(signature = (file = None, path = None, default = None, *, text = None))]
+pub fn get(
+    py: Python,
+    file: Option<PathBuf>,
+    path: Option<&str>,
+    default: Option<PyObject>,
+    text: Option<String>,
+) -> PyResult<PyObject> {
+    let path = path.ok_or_else(|| {
+        exceptions::PyTypeError::new_err("get() missing the path of the value to extract")
+    })?;
+    let mut converter = Converter::new(py);
+    let document = match Source::new("get", file, text)? {
+        Source::Text(text) => crate::parse_str(py, &text, &text, true)?,
+        Source::File(file) => {
+            converter.includes.enter_file(&file)?;
+            crate::parse_file(py, &file, true)?
+        }
+    };
+    let parts = crate::path::split(path);
+    let mut results = vec![];
+    select(&mut converter, &document, &parts, &mut results)?;
+    if parts.iter().any(|part| part == "*") {
+        Ok(PyList::new(py, results).into())
+    } else {
+        Ok(results
+            .pop()
+            .unwrap_or_else(|| default.unwrap_or_else(|| py.None())))
+    }
+}
+
+/// Converts all values matching `parts` in `value` and appends them to `results`.
+pub fn select(
+    converter: &mut Converter,
+    value: &Value,
+    parts: &[String],
+    results: &mut Vec<PyObject>,
+) -> PyResult<()> {
+    let py = converter.py;
+    let (part, rest) = match parts.split_first() {
+        Some(split) => split,
+        None => {
+            results.push(converter.convert(value)?);
+            return Ok(());
+        }
+    };
+    let wildcard = part == "*";
+    match value {
+        Value::Struct(s) => {
+            for (key, value) in s.iter() {
+                if wildcard || key.to_object(py).extract::<String>(py)? == *part {
+                    select(converter, value, rest, results)?;
+                }
+            }
+        }
+        Value::Map(m) => {
+            for (key, value) in m.iter() {
+                let matches = wildcard
+                    || match key {
+                        Value::String(key) => key[..] == part[..],
+                        Value::Number(ron_parser::Number::Integer(i)) => i.to_string() == *part,
+                        _ => false,
+                    };
+                if matches {
+                    select(converter, value, rest, results)?;
+                }
+            }
+        }
+        Value::Seq(elements) => select_elements(converter, elements.iter(), part, rest, results)?,
+        Value::Tuple(_, elements) => {
+            select_elements(converter, elements.iter(), part, rest, results)?
+        }
+        Value::Option(Some(value)) => select(converter, value, parts, results)?,
+        Value::Include(_) => {
+            // The included document has to be loaded to look inside it, so fall back to
+            // navigating the converted value.
+            if parts.iter().any(|part| part == "*") {
+                return Err(exceptions::PyValueError::new_err(
+                    "Wildcards can't be used to select values inside an #include",
+                ));
+            }
+            let value = converter.convert(value)?;
+            if let Some(value) = crate::path::get(value.as_ref(py), &parts.join("."))? {
+                results.push(value.into());
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn select_elements<'a>(
+    converter: &mut Converter,
+    mut elements: impl Iterator<Item = &'a Value>,
+    part: &str,
+    rest: &[String],
+    results: &mut Vec<PyObject>,
+) -> PyResult<()> {
+    if part == "*" {
+        for element in elements {
+            select(converter, element, rest, results)?;
+        }
+    } else if let Some(element) = part.parse::<usize>().ok().and_then(|i| elements.nth(i)) {
+        select(converter, element, rest, results)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use pyo3::basic::CompareOp;
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+
+/// A mutable named struct, returned for named structs by `load` with `mutable_structs`. Fields are
+/// attributes that can be read, assigned and deleted, and the struct name is `__name__`:
+///
+/// ```python
+/// config = pyron.loads("Optimizer(lr: 0.1)", preserve_structs=True, mutable_structs=True)
+/// config.lr *= 2
+/// assert config.__name__ == "Optimizer"
+/// ```
+#[pyclass(module = "pyron")]
+pub struct Record {
+    name: String,
+    fields: Py<PyDict>,
+}
+
+impl Record {
+    pub fn new(name: String, fields: &PyDict) -> Record {
+        Record {
+            name,
+            fields: fields.into(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn fields<'py>(&self, py: Python<'py>) -> &'py PyDict {
+        self.fields.as_ref(py)
+    }
+}
+
+#[pymethods]
+impl Record {
+    #[new]
+    #[pyo3(signature = (name, /, **fields))]
+    fn py_new(py: Python, name: String, fields: Option<&PyDict>) -> Record {
+        Record::new(name, fields.unwrap_or_else(|| PyDict::new(py)))
+    }
+
+    fn __getattr__(&self, py: Python, name: &str) -> PyResult<PyObject> {
+        if name == "__name__" {
+            return Ok(self.name.clone().into_py(py));
+        }
+        match self.fields(py).get_item(name) {
+            Some(value) => Ok(value.into()),
+            None => Err(exceptions::PyAttributeError::new_err(format!(
+                "{} has no field {}",
+                self.name, name
+            ))),
+        }
+    }
+
+    fn __setattr__(&self, py: Python, name: &str, value: PyObject) -> PyResult<()> {
+        self.fields(py).set_item(name, value)
+    }
+
+    fn __delattr__(&self, py: Python, name: &str) -> PyResult<()> {
+        if self.fields(py).get_item(name).is_none() {
+            return Err(exceptions::PyAttributeError::new_err(format!(
+                "{} has no field {}",
+                self.name, name
+            )));
+        }
+        self.fields(py).del_item(name)
+    }
+
+    /// Returns a new dict mapping field names to values, like `namedtuple._asdict`.
+    fn _asdict<'py>(&self, py: Python<'py>) -> PyResult<&'py PyDict> {
+        self.fields(py).copy()
+    }
+
+    fn __richcmp__(&self, py: Python, other: &PyAny, op: CompareOp) -> PyResult<PyObject> {
+        let other = match other.extract::<PyRef<Record>>() {
+            Ok(other) => other,
+            Err(_) => return Ok(py.NotImplemented()),
+        };
+        let equal = self.name == other.name
+            && self
+                .fields(py)
+                .rich_compare(other.fields(py), CompareOp::Eq)?
+                .is_true()?;
+        Ok(match op {
+            CompareOp::Eq => equal.into_py(py),
+            CompareOp::Ne => (!equal).into_py(py),
+            _ => py.NotImplemented(),
+        })
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        let mut fields = vec![];
+        for (name, value) in self.fields(py) {
+            fields.push(format!("{}={}", name, value.repr()?));
+        }
+        Ok(format!("{}({})", self.name, fields.join(", ")))
+    }
+
+    fn __reduce__<'py>(&self, py: Python<'py>) -> PyResult<(PyObject, &'py PyTuple, &'py PyDict)> {
+        // Fields are restored by `__setstate__`, since they can't be passed to `__new__` by name.
+        Ok((
+            py.get_type::<Record>().into(),
+            PyTuple::new(py, [&self.name]),
+            self.fields(py),
+        ))
+    }
+
+    fn __setstate__(&self, py: Python, state: &PyDict) -> PyResult<()> {
+        self.fields(py).update(state.as_mapping())
+    }
+}
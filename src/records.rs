@@ -0,0 +1,106 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+
+use crate::{Converter, SeqType};
+
+/// Serializes a value to RON on a single line and appends it to the file at `path`, which is
+/// created if it doesn't exist. Compressed files (`.gz`, `.zst`) get a new compressed member per
+/// record.
+#[pyfunction]
+pub fn append_record(py: Python, path: PathBuf, value: &PyAny) -> PyResult<()> {
+    let line = format!("{}\n", crate::format::inline(py, value)?);
+    let contents = crate::compression::encode(&path, &line)?;
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?
+        .write_all(&contents)?;
+    Ok(())
+}
+
+/// Iterates over the records of a file written by `append_record`, parsing one line at a time.
+/// Blank lines and lines containing only a `//` comment are skipped. Records are converted as by
+/// `load` with the given options.
+#[pyfunction]
+#[pyo3(signature = (
+    path,
+    preserve_structs = false,
+    preserve_class_names = false,
+    seq_type = "list",
+    intern_strings = false,
+    numpy_arrays = false,
+))]
+pub fn iter_records(
+    path: PathBuf,
+    preserve_structs: bool,
+    preserve_class_names: bool,
+    seq_type: &str,
+    intern_strings: bool,
+    numpy_arrays: bool,
+) -> PyResult<RecordIterator> {
+    if preserve_structs && preserve_class_names {
+        return Err(exceptions::PyValueError::new_err(
+            "preserve_structs and preserve_class_names cannot be true at the same time",
+        ));
+    }
+    let reader = BufReader::new(crate::compression::open(&path)?);
+    Ok(RecordIterator {
+        path,
+        reader,
+        line: 0,
+        preserve_structs,
+        preserve_class_names,
+        seq_type: SeqType::parse(seq_type)?,
+        intern_strings,
+        numpy_arrays,
+    })
+}
+
+/// Iterator returned by `iter_records`.
+#[pyclass(module = "pyron")]
+pub struct RecordIterator {
+    path: PathBuf,
+    reader: BufReader<Box<dyn Read + Send>>,
+    /// Number of lines read so far.
+    line: usize,
+    preserve_structs: bool,
+    preserve_class_names: bool,
+    seq_type: SeqType,
+    intern_strings: bool,
+    numpy_arrays: bool,
+}
+
+#[pymethods]
+impl RecordIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            let record = line.trim();
+            if record.is_empty() || record.starts_with("//") {
+                continue;
+            }
+            let name = format!("{}:{}", self.path.display(), self.line);
+            let record = crate::parse_str(py, record, &name, true)?;
+            let mut converter = Converter::new(py);
+            converter.preserve_structs = self.preserve_structs;
+            converter.preserve_class_names = self.preserve_class_names;
+            converter.seq_type = self.seq_type;
+            converter.intern_strings = self.intern_strings;
+            converter.numpy_arrays = self.numpy_arrays;
+            converter.includes.enter_file(&self.path)?;
+            return converter.convert_document(&record).map(Some);
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyString, PyTuple, PyType};
+
+/// Imports a class given as `module:Class`, where `Class` can also be a dotted path to a nested
+/// class.
+pub fn import(py: Python, spec: &str) -> PyResult<&PyAny> {
+    let (module, name) = match spec.split_once(':') {
+        Some(split) => split,
+        None => {
+            return Err(exceptions::PyValueError::new_err(format!(
+                "Expected a schema of the form module:Class, got `{}`",
+                spec
+            )))
+        }
+    };
+    let mut class: &PyAny = PyModule::import(py, module)?;
+    for part in name.split('.') {
+        class = class.getattr(part)?;
+    }
+    Ok(class)
+}
+
+/// Checks that a loaded value matches a type, appending a message for every mismatch to
+/// `errors`. Dataclasses are checked field by field (missing fields without default and unknown
+/// fields are errors), and `list`, `dict`, `Optional` and `Union` type hints are followed into
+/// their arguments. Types that can't be checked, like `Any`, accept every value.
+pub fn validate(
+    py: Python,
+    value: &PyAny,
+    schema: &PyAny,
+    path: &str,
+    errors: &mut Vec<String>,
+) -> PyResult<()> {
+    let location = if path.is_empty() { "<root>" } else { path };
+    let dataclasses = PyModule::import(py, "dataclasses")?;
+    let typing = PyModule::import(py, "typing")?;
+    if dataclasses
+        .call_method1("is_dataclass", (schema,))?
+        .extract::<bool>()?
+    {
+        let dict = match value.downcast::<PyDict>() {
+            Ok(dict) => dict,
+            Err(_) => {
+                errors.push(format!(
+                    "{}: expected {}, found {}",
+                    location,
+                    schema.getattr("__name__")?,
+                    value.get_type().name()?
+                ));
+                return Ok(());
+            }
+        };
+        let hints = typing.call_method1("get_type_hints", (schema,)).ok();
+        let missing = dataclasses.getattr("MISSING")?;
+        let mut names = vec![];
+        for field in dataclasses
+            .call_method1("fields", (schema,))?
+            .downcast::<PyTuple>()?
+        {
+            let name = field.getattr("name")?.extract::<&str>()?;
+            names.push(name);
+            let child = crate::diff::join(path, PyString::new(py, name))?;
+            match dict.get_item(name) {
+                Some(value) => {
+                    let hint = match hints {
+                        Some(hints) => hints.get_item(name)?,
+                        None => field.getattr("type")?,
+                    };
+                    validate(py, value, hint, &child, errors)?;
+                }
+                None => {
+                    if field.getattr("default")?.is(missing)
+                        && field.getattr("default_factory")?.is(missing)
+                    {
+                        errors.push(format!("{}: missing field", child));
+                    }
+                }
+            }
+        }
+        for key in dict.keys() {
+            let known = key
+                .extract::<&str>()
+                .map_or(false, |key| key == "!__name__" || names.contains(&key));
+            if !known {
+                errors.push(format!("{}: unknown field", crate::diff::join(path, key)?));
+            }
+        }
+        return Ok(());
+    }
+
+    let origin = typing.call_method1("get_origin", (schema,))?;
+    let args = typing
+        .call_method1("get_args", (schema,))?
+        .downcast::<PyTuple>()?;
+    let union_type = PyModule::import(py, "types")?.getattr("UnionType").ok();
+    if schema.is(typing.getattr("Any")?) {
+        return Ok(());
+    }
+    if origin.is(typing.getattr("Union")?) || union_type.map_or(false, |union| origin.is(union)) {
+        // Accept the value if any of the alternatives accepts it.
+        let mut first_errors = None;
+        for arg in args {
+            let mut arg_errors = vec![];
+            validate(py, value, arg, path, &mut arg_errors)?;
+            if arg_errors.is_empty() {
+                return Ok(());
+            }
+            first_errors.get_or_insert(arg_errors);
+        }
+        errors.extend(first_errors.unwrap_or_default());
+    } else if origin.is(typing.getattr("Literal")?) {
+        if !args.contains(value)? {
+            errors.push(format!(
+                "{}: expected one of {}, found {}",
+                location,
+                args,
+                value.repr()?
+            ));
+        }
+    } else if origin.downcast::<PyType>().is_ok() {
+        if !check_type(value, origin)? {
+            errors.push(mismatch(location, origin, value)?);
+        } else if let Ok(list) = value.downcast::<PyList>() {
+            if let Ok(element) = args.get_item(0) {
+                for (i, value) in list.iter().enumerate() {
+                    validate(py, value, element, &format!("{}[{}]", path, i), errors)?;
+                }
+            }
+        } else if let Ok(dict) = value.downcast::<PyDict>() {
+            if let Ok(element) = args.get_item(1) {
+                for (key, value) in dict {
+                    validate(py, value, element, &crate::diff::join(path, key)?, errors)?;
+                }
+            }
+        }
+    } else if schema.is_none() || schema.is(py.None().as_ref(py).get_type()) {
+        if !value.is_none() {
+            errors.push(format!(
+                "{}: expected None, found {}",
+                location,
+                value.get_type().name()?
+            ));
+        }
+    } else if schema.downcast::<PyType>().is_ok() && !check_type(value, schema)? {
+        errors.push(mismatch(location, schema, value)?);
+    }
+    Ok(())
+}
+
+/// Returns the type hint of the field at a dotted path of `schema`, following dataclass fields,
+/// the elements of `list` and `tuple` hints and the values of `dict` hints, and looking through
+/// `Optional`. Paths below types that can't be followed, like `Any`, have the type `Any`.
+pub fn field_type<'py>(py: Python<'py>, schema: &'py PyAny, path: &str) -> PyResult<&'py PyAny> {
+    let dataclasses = PyModule::import(py, "dataclasses")?;
+    let typing = PyModule::import(py, "typing")?;
+    let builtins = PyModule::import(py, "builtins")?;
+    let mut hint = schema;
+    for part in crate::path::split(path) {
+        hint = without_none(py, hint)?;
+        let origin = typing.call_method1("get_origin", (hint,))?;
+        let args = typing
+            .call_method1("get_args", (hint,))?
+            .downcast::<PyTuple>()?;
+        hint = if dataclasses
+            .call_method1("is_dataclass", (hint,))?
+            .extract::<bool>()?
+        {
+            let hints = typing.call_method1("get_type_hints", (hint,))?;
+            match hints.get_item(part.as_str()) {
+                Ok(hint) => hint,
+                Err(_) => {
+                    return Err(exceptions::PyValueError::new_err(format!(
+                        "Invalid override of {}: {} has no field {}",
+                        path,
+                        hint.getattr("__name__")?,
+                        part
+                    )))
+                }
+            }
+        } else if origin.is(builtins.getattr("list")?) || origin.is(builtins.getattr("tuple")?) {
+            // `Tuple[int, str]` has a hint per element, `Tuple[int, ...]` one for all elements.
+            let any = typing.getattr("Any")?;
+            match part.parse::<usize>() {
+                Ok(i) if args.len() > 1 && !args.get_item(1)?.is(py.Ellipsis()) => {
+                    args.get_item(i).unwrap_or(any)
+                }
+                _ => args.get_item(0).unwrap_or(any),
+            }
+        } else if origin.is(builtins.getattr("dict")?) {
+            args.get_item(1).unwrap_or(typing.getattr("Any")?)
+        } else {
+            typing.getattr("Any")?
+        };
+    }
+    Ok(hint)
+}
+
+/// Converts the string override of a field with the type hint `hint` to that type. Strings are
+/// kept as they are for `str` fields, and otherwise parsed as RON like by `apply_overrides`.
+/// Raises a `ValueError` if the parsed value doesn't match `hint`.
+pub fn coerce(py: Python, text: &str, hint: &PyAny, path: &str) -> PyResult<PyObject> {
+    let builtins = PyModule::import(py, "builtins")?;
+    let inner = without_none(py, hint)?;
+    // `None` is only parsed for `Optional[str]` fields.
+    if inner.is(builtins.getattr("str")?) && (inner.is(hint) || text != "None") {
+        return Ok(text.into_py(py));
+    }
+    let value = crate::path::parse_override(py, text);
+    let mut errors = vec![];
+    validate(py, value.as_ref(py), hint, "", &mut errors)?;
+    if errors.is_empty() {
+        return Ok(value);
+    }
+    let errors = errors
+        .iter()
+        .map(|error| error.trim_start_matches("<root>: "))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(exceptions::PyValueError::new_err(format!(
+        "Invalid override of {}: `{}` is not a valid {} ({})",
+        path,
+        text,
+        type_name(py, hint)?,
+        errors
+    )))
+}
+
+/// The name of a type hint, such as `int` or `List[int]`.
+pub fn type_name(py: Python, hint: &PyAny) -> PyResult<String> {
+    let typing = PyModule::import(py, "typing")?;
+    Ok(
+        if hint.downcast::<PyType>().is_ok()
+            && typing.call_method1("get_origin", (hint,))?.is_none()
+        {
+            hint.getattr("__name__")?.to_string()
+        } else {
+            hint.str()?.to_str()?.replace("typing.", "")
+        },
+    )
+}
+
+/// Returns `T` for `Optional[T]`, and `hint` itself for all other hints.
+fn without_none<'py>(py: Python<'py>, hint: &'py PyAny) -> PyResult<&'py PyAny> {
+    let typing = PyModule::import(py, "typing")?;
+    let origin = typing.call_method1("get_origin", (hint,))?;
+    let union_type = PyModule::import(py, "types")?.getattr("UnionType").ok();
+    if !origin.is(typing.getattr("Union")?) && !union_type.map_or(false, |union| origin.is(union)) {
+        return Ok(hint);
+    }
+    let none = py.None().as_ref(py).get_type();
+    let args = typing
+        .call_method1("get_args", (hint,))?
+        .downcast::<PyTuple>()?
+        .iter()
+        .filter(|arg| !arg.is(none))
+        .collect::<Vec<_>>();
+    Ok(match args.as_slice() {
+        [arg] => arg,
+        _ => hint,
+    })
+}
+
+/// Checks `isinstance(value, class)`, allowing ints where floats are expected (as in type hints)
+/// but not bools where ints are expected.
+fn check_type(value: &PyAny, class: &PyAny) -> PyResult<bool> {
+    let py = value.py();
+    let builtins = PyModule::import(py, "builtins")?;
+    if class.is(builtins.getattr("float")?) && value.get_type().is(builtins.getattr("int")?) {
+        return Ok(true);
+    }
+    if class.is(builtins.getattr("int")?) && value.get_type().is(builtins.getattr("bool")?) {
+        return Ok(false);
+    }
+    value.is_instance(class)
+}
+
+fn mismatch(location: &str, expected: &PyAny, value: &PyAny) -> PyResult<String> {
+    Ok(format!(
+        "{}: expected {}, found {}",
+        location,
+        expected.getattr("__name__")?,
+        value.get_type().name()?
+    ))
+}
@@ -0,0 +1,625 @@
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyLong, PyTuple};
+use serde::ser::{self, SerializeMap, SerializeSeq, SerializeStruct, SerializeTuple};
+use serde::{Serialize, Serializer};
+
+use crate::record::Record;
+use crate::wrappers::{self, Char, SomeValue, Unit};
+use crate::EnumRepr;
+
+/// The prefix of the strings that number literals `serde` can't pass to `ron`, such as integers
+/// outside the range of `i64`, are written as. It is followed by a random number chosen for each
+/// call of `to_string_pretty` and the index of the literal, and the strings are replaced with the
+/// literals afterwards.
+const NUMBER: &str = "\0num:";
+
+/// How values are written by `to_string_pretty`.
+pub struct Format<'a> {
+    /// Sort map entries and struct fields.
+    pub sort_keys: bool,
+    /// Dicts with a string entry with this key are named structs.
+    pub tag_key: &'a str,
+    /// Write NaN and infinite floats instead of raising a `ValueError`.
+    pub allow_nan: bool,
+    /// How floats are written, or `None` to leave them to `ron`.
+    pub floats: Option<Floats>,
+    /// Separate the digits of integers with at least five digits into groups of three with `_`.
+    pub int_separators: bool,
+    /// The RON extensions enabled by an `#![enable(...)]` attribute at the start of the output.
+    /// With `implicit_some`, `Some` values are written without the `Some(...)` around them, and
+    /// with `unwrap_newtypes`, tuple structs with one element are written as the element.
+    pub extensions: &'a [String],
+    /// How named structs loaded with the same `enum_repr` are tagged with their name.
+    pub enum_repr: EnumRepr,
+    /// The key of the fields of structs tagged with `EnumRepr::Adjacent`.
+    pub content_key: &'a str,
+    /// Write a comma after the last item of every struct, tuple, sequence and map that is spread
+    /// over multiple lines.
+    pub trailing_commas: bool,
+    /// Write collections on one line if they fit within this many columns.
+    pub max_width: Option<usize>,
+    /// Pad struct fields and map entries written one per line so that their values line up.
+    pub align_fields: bool,
+    /// The indentation of each level of nesting.
+    pub indent: &'a str,
+}
+
+impl<'a> Default for Format<'a> {
+    fn default() -> Format<'a> {
+        Format {
+            sort_keys: false,
+            tag_key: "!__name__",
+            allow_nan: true,
+            floats: None,
+            int_separators: false,
+            extensions: &[],
+            enum_repr: EnumRepr::Untagged,
+            content_key: "content",
+            trailing_commas: true,
+            max_width: None,
+            align_fields: false,
+            indent: "    ",
+        }
+    }
+}
+
+/// How floats are written.
+pub struct Floats {
+    /// Write exactly `precision` digits after the decimal point, instead of the fewest digits
+    /// that read back as the same float.
+    fixed: bool,
+    /// The number of digits after the decimal point, which the shortest representation is rounded
+    /// to if it has more. In exponent notation, this applies to the digits before the exponent.
+    precision: Option<usize>,
+    /// Write `.0` after floats that have no fractional digits, rather than exponent notation
+    /// (`1e0`), so that they load as floats either way.
+    trailing_zero: bool,
+    /// Write nonzero floats in exponent notation (`1e-5`) if the absolute value of their decimal
+    /// exponent is at least this or `MAX_POSITIONAL_EXPONENT`, and in positional notation
+    /// (`0.00001`) otherwise.
+    exponent_threshold: Option<i32>,
+}
+
+impl Default for Floats {
+    /// The options of `to_string` by default.
+    fn default() -> Floats {
+        Floats {
+            fixed: false,
+            precision: None,
+            trailing_zero: true,
+            exponent_threshold: None,
+        }
+    }
+}
+
+/// The decimal exponent from which floats are written in exponent notation regardless of
+/// `exponent_threshold`, as by Python's `repr`, rather than with dozens of digits.
+const MAX_POSITIONAL_EXPONENT: i32 = 16;
+
+impl Floats {
+    /// Parses the float options of `to_string`, returning `None` for the defaults.
+    pub fn new(
+        format: &str,
+        precision: Option<usize>,
+        trailing_zero: bool,
+        exponent_threshold: Option<i32>,
+    ) -> PyResult<Option<Floats>> {
+        let fixed = match format {
+            "shortest" => false,
+            "fixed" if precision.is_none() => {
+                return Err(exceptions::PyValueError::new_err(
+                    "float_format=\"fixed\" requires float_precision",
+                ))
+            }
+            "fixed" => true,
+            _ => {
+                return Err(exceptions::PyValueError::new_err(format!(
+                    "float_format must be \"shortest\" or \"fixed\", not \"{}\"",
+                    format
+                )))
+            }
+        };
+        if !fixed && precision.is_none() && trailing_zero && exponent_threshold.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Floats {
+            fixed,
+            precision,
+            trailing_zero,
+            exponent_threshold,
+        }))
+    }
+
+    fn literal(&self, float: f64) -> String {
+        if !float.is_finite() {
+            return float.to_string();
+        }
+        if self.exponential(float) {
+            let literal = match self.precision {
+                Some(precision) => format!("{:.*e}", precision, float),
+                None => format!("{:e}", float),
+            };
+            let (mantissa, exponent) = literal.split_once('e').expect("`{:e}` has an exponent");
+            return format!("{}e{}", self.trim(mantissa.to_string()), exponent);
+        }
+        let mut literal = match self.precision {
+            Some(precision) => format!("{:.*}", precision, float),
+            None => float.to_string(),
+        };
+        literal = self.trim(literal);
+        if !literal.contains('.') {
+            // Literals without a decimal point or an exponent would load as integers.
+            if self.trailing_zero {
+                literal.push_str(".0");
+            } else {
+                let rounded = literal.parse::<f64>().expect("`literal` is a float");
+                literal = format!("{:e}", rounded);
+            }
+        }
+        literal
+    }
+
+    /// Whether a float is written in exponent notation.
+    fn exponential(&self, float: f64) -> bool {
+        if float == 0.0 || !float.is_finite() {
+            return false;
+        }
+        let threshold = self
+            .exponent_threshold
+            .map_or(MAX_POSITIONAL_EXPONENT, |threshold| {
+                threshold.min(MAX_POSITIONAL_EXPONENT)
+            });
+        let literal = format!("{:e}", float);
+        let exponent = literal[literal.find('e').expect("`{:e}` has an exponent") + 1..]
+            .parse::<i32>()
+            .expect("`{:e}` has an integer exponent");
+        exponent.abs() >= threshold
+    }
+
+    /// Removes trailing zeros after the decimal point unless the format is fixed.
+    fn trim(&self, mut literal: String) -> String {
+        if !self.fixed && literal.contains('.') {
+            literal.truncate(literal.trim_end_matches('0').trim_end_matches('.').len());
+        }
+        literal
+    }
+}
+
+/// Serializes a value to pretty-printed RON in a single pass, with `ron` writing straight from the
+/// Python objects rather than from a `ron::Value` copy of the whole value.
+pub fn to_string_pretty(py: Python, value: &PyAny, format: &Format) -> PyResult<String> {
+    let error = RefCell::new(None);
+    let options = Options {
+        format,
+        error: &error,
+        numbers: RefCell::new(vec![]),
+        names: RefCell::new(vec![]),
+        nonce: RandomState::new().build_hasher().finish(),
+        implicit_some: format.extensions.iter().any(|e| e == "implicit_some"),
+        unwrap_newtypes: format.extensions.iter().any(|e| e == "unwrap_newtypes"),
+    };
+    let config = ron::ser::PrettyConfig::default()
+        .struct_names(true)
+        .decimal_floats(true)
+        .indentor(format.indent.to_string());
+    let text = match ron::ser::to_string_pretty(&PyValue::new(py, value, &options), config) {
+        Ok(text) if !options.numbers.borrow().is_empty() => {
+            let prefix = ron::ser::to_string(&options.number_prefix())
+                .map_err(|e| exceptions::PyValueError::new_err(format!("{}", e)))?;
+            let prefix = prefix.trim_end_matches('"');
+            let text = crate::format::number_literals(&text, prefix, &options.numbers.borrow());
+            crate::format::positional_structs(&text)
+        }
+        Ok(text) => crate::format::positional_structs(&text),
+        // Errors raised while reading Python objects are passed through `ron` as messages, so the
+        // original exception is kept aside and raised instead.
+        Err(e) => {
+            return Err(error
+                .into_inner()
+                .unwrap_or_else(|| exceptions::PyValueError::new_err(format!("{}", e))))
+        }
+    };
+    // Fields are aligned first so that the padding counts towards the width of inlined lines.
+    let text = if format.align_fields {
+        crate::format::align_fields(&text)
+    } else {
+        text
+    };
+    let text = match format.max_width {
+        Some(max_width) => crate::format::inline_collections(&text, max_width),
+        None => text,
+    };
+    let text = if format.trailing_commas {
+        text
+    } else {
+        crate::format::remove_trailing_commas(&text)
+    };
+    if format.extensions.is_empty() {
+        Ok(text)
+    } else {
+        Ok(format!(
+            "#![enable({})]\n{}",
+            format.extensions.join(", "),
+            text
+        ))
+    }
+}
+
+struct Options<'a> {
+    format: &'a Format<'a>,
+    /// The first exception raised while serializing.
+    error: &'a RefCell<Option<PyErr>>,
+    /// The number literals written as `NUMBER` strings, by index.
+    numbers: RefCell<Vec<String>>,
+    /// The struct and field names handed to `serde`, which requires them to be `'static`.
+    names: RefCell<Vec<Box<str>>>,
+    /// Distinguishes the `NUMBER` strings from strings in the value.
+    nonce: u64,
+    implicit_some: bool,
+    unwrap_newtypes: bool,
+}
+
+impl<'a> Options<'a> {
+    /// The start of the `NUMBER` strings, followed by the index of the literal.
+    fn number_prefix(&self) -> String {
+        format!("{}{:016x}:", NUMBER, self.nonce)
+    }
+
+    /// Returns a copy of a struct or field name that lives until the options are dropped, as
+    /// `'static` for `serde`.
+    fn name(&self, name: &str) -> &'static str {
+        let name = Box::<str>::from(name);
+        // SAFETY: The box isn't dropped until the options are, at the end of `to_string_pretty`,
+        // after `ron` has returned, and `ron` only writes names to its output rather than keeping
+        // them. Moving the box into `names` doesn't move the name it points to.
+        let copy = unsafe { &*(&*name as *const str) };
+        self.names.borrow_mut().push(name);
+        copy
+    }
+}
+
+/// A Python object being serialized, which reads its items only when `serde` asks for them.
+struct PyValue<'a, 'py> {
+    py: Python<'py>,
+    value: &'py PyAny,
+    options: &'a Options<'a>,
+}
+
+/// The items of a Python object, as the RON value they are written as.
+enum Shape<'py> {
+    Struct(String, Vec<(String, &'py PyAny)>),
+    Map(Vec<(&'py PyAny, &'py PyAny)>),
+    Tuple(&'py PyTuple),
+    Seq(&'py PyList),
+    Str(&'py str),
+    Char(char),
+    Some(&'py PyAny),
+    /// A unit struct or an enum variant without fields.
+    Unit(String),
+    Bool(bool),
+    Integer(i64),
+    /// The digits of an integer outside the range of `i64`.
+    BigInt(String),
+    /// The source text of a number loaded with `preserve_numbers`.
+    Literal(&'py str),
+    Float(f64),
+    None,
+}
+
+impl<'a, 'py> PyValue<'a, 'py> {
+    fn new(py: Python<'py>, value: &'py PyAny, options: &'a Options<'a>) -> PyValue<'a, 'py> {
+        PyValue { py, value, options }
+    }
+
+    fn child(&self, value: &'py PyAny) -> PyValue<'a, 'py> {
+        PyValue::new(self.py, value, self.options)
+    }
+
+    fn shape(&self) -> PyResult<Shape<'py>> {
+        let (py, value) = (self.py, self.value);
+        if let Ok(dict) = value.downcast::<PyDict>() {
+            if let Some(shape) = self.variant(dict)? {
+                return Ok(shape);
+            }
+            // Dicts tagged with a struct name by `preserve_class_names` are named structs.
+            if let Some(name) = dict.get_item(self.options.format.tag_key) {
+                if let Ok(name) = name.extract::<String>() {
+                    return self.tagged(dict, name);
+                }
+            }
+            let mut entries = dict.iter().collect::<Vec<_>>();
+            if self.options.format.sort_keys {
+                let format = Format {
+                    sort_keys: true,
+                    tag_key: self.options.format.tag_key,
+                    ..Format::default()
+                };
+                crate::sort_entries(&mut entries, |key| to_string_pretty(py, key, &format))?;
+            }
+            Ok(Shape::Map(entries))
+        } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+            if crate::is_namedtuple(tuple) {
+                let name = value
+                    .getattr("__class__")?
+                    .getattr("__name__")?
+                    .extract::<String>()?;
+                let fields = value.call_method0("_asdict")?.downcast::<PyDict>()?;
+                self.fields(name, fields)
+            } else {
+                Ok(Shape::Tuple(tuple))
+            }
+        } else if let Ok(list) = value.downcast::<PyList>() {
+            Ok(Shape::Seq(list))
+        } else if let Ok(str) = value.extract::<&str>() {
+            Ok(Shape::Str(str))
+        } else if let Ok(bool) = value.extract::<bool>() {
+            Ok(Shape::Bool(bool))
+        } else if let Some(source) = crate::literals::source(py, value)? {
+            Ok(Shape::Literal(source))
+        } else if let Ok(int) = value.extract::<i64>() {
+            Ok(Shape::Integer(int))
+        } else if let Ok(int) = value.extract::<i128>() {
+            Ok(Shape::BigInt(int.to_string()))
+        } else if let Ok(int) = value.extract::<u128>() {
+            Ok(Shape::BigInt(int.to_string()))
+        } else if value.downcast::<PyLong>().is_ok() {
+            // Python ints are unbounded, so larger ones are written with all their digits.
+            let int = py.get_type::<PyLong>().call1((value,))?;
+            Ok(Shape::BigInt(int.str()?.to_str()?.to_string()))
+        } else if let Ok(float) = value.extract::<f64>() {
+            if !float.is_finite() && !self.options.format.allow_nan {
+                return Err(exceptions::PyValueError::new_err(format!(
+                    "Non-finite float {} is not allowed with allow_nan=False",
+                    float
+                )));
+            }
+            Ok(Shape::Float(float))
+        } else if value.is_none() {
+            Ok(Shape::None)
+        } else if let Ok(char) = value.extract::<PyRef<Char>>() {
+            Ok(Shape::Char(char.value()))
+        } else if let Ok(some) = value.extract::<PyRef<SomeValue>>() {
+            Ok(Shape::Some(some.value(py)))
+        } else if let Ok(unit) = value.extract::<PyRef<Unit>>() {
+            Ok(Shape::Unit(unit.name().to_string()))
+        } else if let Ok(record) = value.extract::<PyRef<Record>>() {
+            self.fields(record.name().to_string(), record.fields(py))
+        } else if let Some(dict) = crate::frozen::proxied(py, value)? {
+            self.child(dict).shape()
+        } else if value.get_type().name()? == "ndarray" {
+            self.child(value.call_method0("tolist")?).shape()
+        } else if PyModule::import(py, "dataclasses")?
+            .call_method1("is_dataclass", (value,))?
+            .extract::<bool>()?
+        {
+            let name = value
+                .getattr("__class__")?
+                .getattr("__name__")?
+                .extract::<String>()?;
+            let mut fields = vec![];
+            for field in value
+                .getattr("__dataclass_fields__")?
+                .downcast::<PyDict>()?
+                .keys()
+            {
+                let field = field.extract::<String>()?;
+                let value = value.getattr(&*field)?;
+                fields.push((field, value));
+            }
+            self.sorted(name, fields)
+        } else {
+            Err(exceptions::PyValueError::new_err(format!(
+                "Unsupported type: {}",
+                value.get_type().name()?
+            )))
+        }
+    }
+
+    /// The named struct or tuple struct of a dict tagged with its name as by `load` with
+    /// `enum_repr`, or `None` if the dict isn't tagged that way. Only dicts with a capitalized
+    /// identifier as the name, and a dict with identifier keys or a tuple as the payload, are
+    /// tagged.
+    fn variant(&self, dict: &'py PyDict) -> PyResult<Option<Shape<'py>>> {
+        let (name, payload) = match self.options.format.enum_repr {
+            EnumRepr::External if dict.len() == 1 => dict.iter().next().unwrap(),
+            EnumRepr::Adjacent if dict.len() == 2 => {
+                let format = &self.options.format;
+                let name = dict.get_item(format.tag_key);
+                match (name, dict.get_item(format.content_key)) {
+                    (Some(name), Some(payload)) => (name, payload),
+                    _ => return Ok(None),
+                }
+            }
+            _ => return Ok(None),
+        };
+        let name = match name.extract::<String>() {
+            Ok(name) if is_variant_name(&name) => name,
+            _ => return Ok(None),
+        };
+        if let Ok(tuple) = payload.downcast::<PyTuple>() {
+            let fields = tuple
+                .iter()
+                .enumerate()
+                .map(|(i, value)| (format!("_{}", i), value))
+                .collect();
+            return Ok(Some(Shape::Struct(name, fields)));
+        }
+        let fields = match payload.downcast::<PyDict>() {
+            Ok(fields) => fields,
+            Err(_) => return Ok(None),
+        };
+        let keys = fields.keys();
+        let identifier = |key: &PyAny| key.extract::<&str>().map_or(false, wrappers::is_identifier);
+        if keys.iter().all(identifier) {
+            Ok(Some(self.fields(name, fields)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// A struct with the entries of a dict as fields.
+    fn fields(&self, name: String, fields: &'py PyDict) -> PyResult<Shape<'py>> {
+        let mut named = vec![];
+        for (field, value) in fields {
+            named.push((field.extract::<String>()?, value));
+        }
+        self.sorted(name, named)
+    }
+
+    /// A struct with the entries of a dict tagged with its name as fields.
+    fn tagged(&self, dict: &'py PyDict, name: String) -> PyResult<Shape<'py>> {
+        let mut fields = vec![];
+        for (key, value) in dict {
+            match key.extract::<String>() {
+                Ok(key) if key == self.options.format.tag_key => continue,
+                Ok(key) => fields.push((key, value)),
+                Err(_) => {
+                    return Err(exceptions::PyValueError::new_err(format!(
+                        "Struct {} has a field with the non-string name {}",
+                        name,
+                        key.repr()?
+                    )))
+                }
+            }
+        }
+        self.sorted(name, fields)
+    }
+
+    fn sorted(&self, name: String, mut fields: Vec<(String, &'py PyAny)>) -> PyResult<Shape<'py>> {
+        if !wrappers::is_identifier(&name) {
+            return Err(exceptions::PyValueError::new_err(format!(
+                "Struct name {:?} is not an identifier",
+                name
+            )));
+        }
+        for (field, _) in &fields {
+            if !wrappers::is_identifier(field) {
+                return Err(exceptions::PyValueError::new_err(format!(
+                    "Struct {} has the field {:?}, which is not an identifier",
+                    name, field
+                )));
+            }
+        }
+        if self.options.format.sort_keys {
+            crate::sort_entries(&mut fields, |name| Ok(name.clone()))?;
+        }
+        Ok(Shape::Struct(name, fields))
+    }
+
+    /// Writes a number literal as a `NUMBER` string, to be replaced with the literal.
+    fn number<S: Serializer>(&self, serializer: S, literal: &str) -> Result<S::Ok, S::Error> {
+        let mut numbers = self.options.numbers.borrow_mut();
+        let marker = format!("{}{}", self.options.number_prefix(), numbers.len());
+        numbers.push(literal.to_string());
+        serializer.serialize_str(&marker)
+    }
+
+    /// Keeps `error` to be raised by `to_string_pretty`, and returns an error that aborts `ron`.
+    fn fail<E: ser::Error>(&self, error: PyErr) -> E {
+        let message = error.to_string();
+        self.options.error.borrow_mut().get_or_insert(error);
+        E::custom(message)
+    }
+}
+
+impl<'a, 'py> Serialize for PyValue<'a, 'py> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let shape = match self.shape() {
+            Ok(shape) => shape,
+            Err(e) => return Err(self.fail(e)),
+        };
+        match shape {
+            // Tuple structs are structs with the fields `_0`, `_1`, ... until they are written.
+            Shape::Struct(_, fields)
+                if self.options.unwrap_newtypes && fields.len() == 1 && fields[0].0 == "_0" =>
+            {
+                self.child(fields[0].1).serialize(serializer)
+            }
+            Shape::Struct(name, fields) => {
+                let options = self.options;
+                let mut s = serializer.serialize_struct(options.name(&name), fields.len())?;
+                for (field, value) in fields {
+                    s.serialize_field(options.name(&field), &self.child(value))?;
+                }
+                s.end()
+            }
+            Shape::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(&self.child(key), &self.child(value))?;
+                }
+                map.end()
+            }
+            Shape::Tuple(tuple) => {
+                let mut t = serializer.serialize_tuple(tuple.len())?;
+                for value in tuple {
+                    t.serialize_element(&self.child(value))?;
+                }
+                t.end()
+            }
+            Shape::Seq(list) => {
+                let mut seq = serializer.serialize_seq(Some(list.len()))?;
+                for value in list {
+                    seq.serialize_element(&self.child(value))?;
+                }
+                seq.end()
+            }
+            Shape::Str(str) => serializer.serialize_str(str),
+            Shape::Char(char) => serializer.serialize_char(char),
+            Shape::Bool(bool) => serializer.serialize_bool(bool),
+            Shape::Integer(int)
+                if self.options.format.int_separators && int.unsigned_abs() >= 10_000 =>
+            {
+                self.number(serializer, &separated(&int.to_string()))
+            }
+            Shape::Integer(int) => serializer.serialize_i64(int),
+            Shape::BigInt(digits) if self.options.format.int_separators => {
+                self.number(serializer, &separated(&digits))
+            }
+            Shape::BigInt(digits) => self.number(serializer, &digits),
+            Shape::Literal(source) => self.number(serializer, source),
+            Shape::Float(float) => match &self.options.format.floats {
+                Some(floats) => self.number(serializer, &floats.literal(float)),
+                // `ron` would write every digit of floats with large exponents.
+                None if Floats::default().exponential(float) => {
+                    self.number(serializer, &Floats::default().literal(float))
+                }
+                None => serializer.serialize_f64(float),
+            },
+            Shape::Some(value) if self.options.implicit_some => {
+                self.child(value).serialize(serializer)
+            }
+            Shape::Some(value) => serializer.serialize_some(&self.child(value)),
+            Shape::Unit(name) => serializer.serialize_unit_struct(self.options.name(&name)),
+            Shape::None => serializer.serialize_none(),
+        }
+    }
+}
+
+/// Inserts `_` between groups of three digits of an integer, as in `10_000_000`.
+fn separated(digits: &str) -> String {
+    let (sign, digits) = match digits.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", digits),
+    };
+    let mut result = sign.to_string();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            result.push('_');
+        }
+        result.push(digit);
+    }
+    result
+}
+
+/// Whether a dict key is the name of an enum variant, by the Rust convention of capitalized names.
+fn is_variant_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_uppercase())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -0,0 +1,295 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+
+use crate::progress::Progress;
+use crate::{Converter, SeqType};
+
+/// Iterates over the elements of a RON file containing a top-level sequence, reading the file
+/// incrementally and converting one element at a time, so that memory use doesn't grow with the
+/// size of the file. Elements are converted as by `load` with the given options. `progress` is
+/// called with the number of bytes read so far as the file is consumed.
+#[pyfunction]
+#[pyo3(signature = (
+    path,
+    preserve_structs = false,
+    preserve_class_names = false,
+    progress = None,
+    seq_type = "list",
+    intern_strings = false,
+    numpy_arrays = false,
+))]
+pub fn iter_load(
+    path: PathBuf,
+    preserve_structs: bool,
+    preserve_class_names: bool,
+    progress: Option<PyObject>,
+    seq_type: &str,
+    intern_strings: bool,
+    numpy_arrays: bool,
+) -> PyResult<SeqIterator> {
+    if preserve_structs && preserve_class_names {
+        return Err(exceptions::PyValueError::new_err(
+            "preserve_structs and preserve_class_names cannot be true at the same time",
+        ));
+    }
+    let reader = BufReader::new(crate::compression::open(&path)?);
+    Ok(SeqIterator {
+        path,
+        reader,
+        splitter: Splitter::default(),
+        ready: VecDeque::new(),
+        preserve_structs,
+        preserve_class_names,
+        seq_type: SeqType::parse(seq_type)?,
+        intern_strings,
+        numpy_arrays,
+        progress: progress.map(Progress::new),
+    })
+}
+
+/// Iterator returned by `iter_load`.
+#[pyclass(module = "pyron")]
+pub struct SeqIterator {
+    path: PathBuf,
+    reader: BufReader<Box<dyn Read + Send>>,
+    splitter: Splitter,
+    /// Source text of elements that were split off but not yet converted.
+    ready: VecDeque<String>,
+    preserve_structs: bool,
+    preserve_class_names: bool,
+    seq_type: SeqType,
+    intern_strings: bool,
+    numpy_arrays: bool,
+    progress: Option<Progress>,
+}
+
+#[pymethods]
+impl SeqIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python) -> PyResult<Option<PyObject>> {
+        loop {
+            if let Some(source) = self.ready.pop_front() {
+                let element = crate::parse_str(py, &source, &source, true)?;
+                let mut converter = Converter::new(py);
+                converter.preserve_structs = self.preserve_structs;
+                converter.preserve_class_names = self.preserve_class_names;
+                converter.seq_type = self.seq_type;
+                converter.intern_strings = self.intern_strings;
+                converter.numpy_arrays = self.numpy_arrays;
+                converter.includes.enter_file(&self.path)?;
+                return converter.convert_document(&element).map(Some);
+            }
+            if self.splitter.finished {
+                if let Some(progress) = &mut self.progress {
+                    progress.finish(py)?;
+                }
+                return Ok(None);
+            }
+            let mut line = String::new();
+            let bytes = self.reader.read_line(&mut line)?;
+            if let Some(progress) = &mut self.progress {
+                progress.advance(py, bytes)?;
+            }
+            if bytes == 0 {
+                return Err(exceptions::PyValueError::new_err(format!(
+                    "{}: unexpected end of file, expected `]`",
+                    self.path.display()
+                )));
+            }
+            self.splitter
+                .feed(&line, &mut self.ready)
+                .map_err(|message| {
+                    exceptions::PyValueError::new_err(format!(
+                        "{}: {}",
+                        self.path.display(),
+                        message
+                    ))
+                })?;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+enum State {
+    #[default]
+    Normal,
+    String {
+        escaped: bool,
+    },
+    /// Raw string with the given number of `#`.
+    RawString(usize),
+    Char {
+        escaped: bool,
+    },
+    /// Nesting depth of block comments.
+    BlockComment(usize),
+}
+
+/// Splits the text of a top-level sequence into the source text of its elements, keeping track of
+/// brackets, strings and comments across lines.
+#[derive(Default)]
+struct Splitter {
+    state: State,
+    /// Bracket depth, where 1 is directly inside the top-level sequence.
+    depth: usize,
+    /// Whether the splitter is inside a `#![...]` attribute before the sequence.
+    attribute: bool,
+    attribute_depth: usize,
+    /// The attributes before the sequence, which apply to every element.
+    attributes: String,
+    finished: bool,
+    current: String,
+}
+
+impl Splitter {
+    fn feed(&mut self, line: &str, ready: &mut VecDeque<String>) -> Result<(), String> {
+        let mut chars = line.char_indices();
+        while let Some((i, c)) = chars.next() {
+            let rest = &line[i..];
+            match self.state {
+                State::String { escaped } => {
+                    self.current.push(c);
+                    self.state = match c {
+                        '"' if !escaped => State::Normal,
+                        '\\' if !escaped => State::String { escaped: true },
+                        _ => State::String { escaped: false },
+                    };
+                }
+                State::Char { escaped } => {
+                    self.current.push(c);
+                    self.state = match c {
+                        '\'' if !escaped => State::Normal,
+                        '\\' if !escaped => State::Char { escaped: true },
+                        _ => State::Char { escaped: false },
+                    };
+                }
+                State::RawString(hashes) => {
+                    self.current.push(c);
+                    if c == '"' && rest[1..].starts_with(&"#".repeat(hashes)) {
+                        for _ in 0..hashes {
+                            chars.next();
+                            self.current.push('#');
+                        }
+                        self.state = State::Normal;
+                    }
+                }
+                State::BlockComment(depth) => {
+                    if rest.starts_with("/*") {
+                        chars.next();
+                        self.state = State::BlockComment(depth + 1);
+                    } else if rest.starts_with("*/") {
+                        chars.next();
+                        self.state = if depth == 1 {
+                            self.current.push(' ');
+                            State::Normal
+                        } else {
+                            State::BlockComment(depth - 1)
+                        };
+                    }
+                }
+                State::Normal => {
+                    if rest.starts_with("//") {
+                        self.current.push('\n');
+                        break;
+                    } else if rest.starts_with("/*") {
+                        chars.next();
+                        self.state = State::BlockComment(1);
+                        continue;
+                    }
+                    if self.depth == 0 {
+                        self.outside(c, rest)?;
+                        continue;
+                    }
+                    match c {
+                        '"' => self.state = State::String { escaped: false },
+                        '\'' => self.state = State::Char { escaped: false },
+                        'r' if is_raw_string_start(line, i) => {
+                            let hashes = rest[1..].chars().take_while(|&c| c == '#').count();
+                            for _ in 0..hashes + 1 {
+                                chars.next();
+                            }
+                            self.current.push_str(&rest[..hashes + 2]);
+                            self.state = State::RawString(hashes);
+                            continue;
+                        }
+                        '(' | '[' | '{' => self.depth += 1,
+                        ')' | ']' | '}' if self.depth > 1 => self.depth -= 1,
+                        ']' => {
+                            self.depth = 0;
+                            self.finished = true;
+                            self.emit(ready);
+                            continue;
+                        }
+                        ')' | '}' => return Err(format!("unexpected `{}`", c)),
+                        ',' if self.depth == 1 => {
+                            self.emit(ready);
+                            continue;
+                        }
+                        _ => {}
+                    }
+                    self.current.push(c);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles a character outside of the top-level sequence.
+    fn outside(&mut self, c: char, rest: &str) -> Result<(), String> {
+        if c.is_whitespace() {
+            return Ok(());
+        }
+        if self.finished {
+            return Err(format!("unexpected `{}` after the end of the sequence", c));
+        }
+        if rest.starts_with("#!") {
+            self.attribute = true;
+        }
+        if self.attribute {
+            self.attributes.push(c);
+            match c {
+                '[' => self.attribute_depth += 1,
+                ']' => {
+                    self.attribute_depth -= 1;
+                    self.attribute = self.attribute_depth > 0;
+                    if !self.attribute {
+                        self.attributes.push('\n');
+                    }
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+        if c == '[' {
+            self.depth = 1;
+            return Ok(());
+        }
+        Err(format!(
+            "iter_load requires a top-level sequence, found `{}`",
+            c
+        ))
+    }
+
+    fn emit(&mut self, ready: &mut VecDeque<String>) {
+        let element = self.current.trim();
+        if !element.is_empty() {
+            ready.push_back(format!("{}{}", self.attributes, element));
+        }
+        self.current.clear();
+    }
+}
+
+/// Whether the `r` at `i` starts a raw string like `r"..."` or `r#"..."#` rather than being part
+/// of an identifier.
+fn is_raw_string_start(line: &str, i: usize) -> bool {
+    let after = line[i + 1..].trim_start_matches('#');
+    let before = line[..i].chars().next_back();
+    after.starts_with('"') && !before.map_or(false, |c| c.is_alphanumeric() || c == '_')
+}
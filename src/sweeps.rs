@@ -0,0 +1,211 @@
+use std::path::PathBuf;
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::lexer::{self, Token, TokenKind};
+use crate::variables::{is_ident, is_punct, skip_trivia};
+use crate::{Converter, Source};
+
+/// Loads the RON file at `path`, given as a `str` or `os.PathLike` as for `load`, or the RON text
+/// `text`, containing hyperparameter sweeps, and returns one config for every combination of
+/// swept values. A sweep such as `lr: @sweep([1e-3, 3e-4, 1e-4])` takes each of the listed values
+/// in turn:
+///
+/// ```ron
+/// (lr: @sweep([1e-3, 3e-4]), layers: @sweep([2, 4]))
+/// ```
+///
+/// expands to 4 configs, in the order of `itertools.product` over the sweeps in the document.
+/// Each config is converted as by `load` with the given options.
+#[pyfunction]
+#[pyo3(signature = (
+    path = None,
+    preserve_structs = false,
+    preserve_class_names = false,
+    *,
+    text = None,
+))]
+pub fn expand_sweeps(
+    py: Python,
+    path: Option<PathBuf>,
+    preserve_structs: bool,
+    preserve_class_names: bool,
+    text: Option<String>,
+) -> PyResult<PyObject> {
+    if preserve_structs && preserve_class_names {
+        return Err(exceptions::PyValueError::new_err(
+            "preserve_structs and preserve_class_names cannot be true at the same time",
+        ));
+    }
+    let (source, path) = match Source::new("expand_sweeps", path, text)? {
+        Source::Text(text) => (text, None),
+        Source::File(path) => (crate::compression::read_to_string(&path)?, Some(path)),
+    };
+    let mut configs = vec![];
+    for (i, source) in expand(&source)?.iter().enumerate() {
+        let name = match &path {
+            Some(path) => format!("{} (sweep {})", path.display(), i + 1),
+            None => format!("<sweep {}>", i + 1),
+        };
+        let value = crate::parse_str(py, source, &name, true)?;
+        let mut converter = Converter::new(py);
+        converter.preserve_structs = preserve_structs;
+        converter.preserve_class_names = preserve_class_names;
+        if let Some(path) = &path {
+            converter.includes.enter_file(path)?;
+        }
+        configs.push(converter.convert_document(&value)?);
+    }
+    Ok(PyList::new(py, configs).into())
+}
+
+/// Applies every combination of the values in `grid`, which maps dotted paths to lists of values,
+/// to `base` (or a file, if given as `os.PathLike`) as by `apply_overrides`. Returns a list of
+/// `(name, config)` pairs in the order of `itertools.product` over the paths of `grid`, where
+/// `name` is a run name like `lr=0.001,batch_size=32` listing the values of the combination.
+#[pyfunction]
+pub fn grid(py: Python, base: &PyAny, grid: &PyDict) -> PyResult<PyObject> {
+    let base = crate::load_if_path(py, base)?;
+    let mut axes = vec![];
+    for (path, values) in grid {
+        let values = values.iter()?.collect::<PyResult<Vec<_>>>()?;
+        axes.push((path.extract::<&str>()?, values));
+    }
+    let lens = axes
+        .iter()
+        .map(|(_, values)| values.len())
+        .collect::<Vec<_>>();
+    let mut runs = vec![];
+    for choices in product(&lens) {
+        let overrides = PyDict::new(py);
+        let mut name = vec![];
+        for ((path, values), choice) in axes.iter().zip(choices) {
+            let value = values[choice];
+            overrides.set_item(path, value)?;
+            let text = match value.extract::<&str>() {
+                Ok(text) => text.to_string(),
+                Err(_) => crate::format::inline(py, value)?,
+            };
+            name.push(format!("{}={}", path, text));
+        }
+        let config = crate::path::apply_overrides(py, base.as_ref(py), overrides, None)?;
+        runs.push((name.join(","), config));
+    }
+    Ok(PyList::new(py, runs).into())
+}
+
+enum Part {
+    Text(String),
+    /// The values of a sweep, as source text.
+    Sweep(Vec<String>),
+}
+
+/// Returns the source of every combination of swept values, with each `@sweep([...])` replaced by
+/// one of its values. A source without sweeps is returned as is.
+fn expand(source: &str) -> PyResult<Vec<String>> {
+    let tokens = lexer::tokenize(source);
+    let mut parts = vec![];
+    let mut text = String::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if is_punct(&tokens, i, '@') && is_ident(source, &tokens, i + 1, "sweep") {
+            let (values, end) = sweep(source, &tokens, i)?;
+            parts.push(Part::Text(std::mem::take(&mut text)));
+            parts.push(Part::Sweep(values));
+            i = end;
+        } else {
+            text.push_str(tokens[i].text(source));
+            i += 1;
+        }
+    }
+    parts.push(Part::Text(text));
+
+    let sweeps = parts
+        .iter()
+        .filter_map(|part| match part {
+            Part::Sweep(values) => Some(values.len()),
+            Part::Text(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let mut sources = vec![];
+    for choices in product(&sweeps) {
+        let mut choices = choices.into_iter();
+        let mut source = String::new();
+        for part in &parts {
+            match part {
+                Part::Text(text) => source.push_str(text),
+                Part::Sweep(values) => source.push_str(&values[choices.next().unwrap()]),
+            }
+        }
+        sources.push(source);
+    }
+    Ok(sources)
+}
+
+/// Returns every combination of indices into lists of the given lengths, in the order of
+/// `itertools.product`.
+fn product(lens: &[usize]) -> Vec<Vec<usize>> {
+    let count = lens.iter().product::<usize>();
+    let mut combinations = vec![];
+    for n in 0..count {
+        // Decode `n` in the mixed radix of the lengths, with the last list varying fastest.
+        let mut choices = vec![0; lens.len()];
+        let mut rest = n;
+        for (choice, len) in choices.iter_mut().zip(lens).rev() {
+            *choice = rest % len;
+            rest /= len;
+        }
+        combinations.push(choices);
+    }
+    combinations
+}
+
+/// Parses the sweep starting at `tokens[start]`, returning the source text of its values and the
+/// index of the first token after it.
+fn sweep(source: &str, tokens: &[Token], start: usize) -> PyResult<(Vec<String>, usize)> {
+    let error = |message: &str| {
+        let line = source[..tokens[start].start].matches('\n').count() + 1;
+        exceptions::PyValueError::new_err(format!("Invalid @sweep on line {}: {}", line, message))
+    };
+    let open = skip_trivia(tokens, start + 2);
+    if !is_punct(tokens, open, '(') {
+        return Err(error("expected `(`"));
+    }
+    let list = skip_trivia(tokens, open + 1);
+    if !is_punct(tokens, list, '[') {
+        return Err(error("expected a list of values"));
+    }
+    let mut values = vec![];
+    let mut value = String::new();
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(list + 1) {
+        match token.kind {
+            TokenKind::Punct('(') | TokenKind::Punct('[') | TokenKind::Punct('{') => depth += 1,
+            TokenKind::Punct(',') if depth == 0 => {
+                values.push(std::mem::take(&mut value).trim().to_string());
+                continue;
+            }
+            TokenKind::Punct(']') if depth == 0 => {
+                if !value.trim().is_empty() {
+                    values.push(value.trim().to_string());
+                }
+                if values.is_empty() || values.iter().any(|value| value.is_empty()) {
+                    return Err(error("expected a non-empty list of values"));
+                }
+                let close = skip_trivia(tokens, i + 1);
+                if !is_punct(tokens, close, ')') {
+                    return Err(error("expected `)` after the list of values"));
+                }
+                return Ok((values, close + 1));
+            }
+            TokenKind::Punct(')') | TokenKind::Punct(']') | TokenKind::Punct('}') => depth -= 1,
+            // A line comment would comment out the rest of the line the value is substituted into.
+            TokenKind::LineComment | TokenKind::BlockComment => continue,
+            _ => {}
+        }
+        value.push_str(token.text(source));
+    }
+    Err(error("expected `]` after the values"))
+}
@@ -0,0 +1,95 @@
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+/// Serializes a value, or a RON file given as `os.PathLike`, to TOML. Struct names are kept in a
+/// `"!__name__"` key. TOML has no null and its documents are tables, so `None` values and
+/// top-level values other than dicts and structs are errors.
+#[pyfunction]
+pub fn to_toml(py: Python, value_or_path: &PyAny) -> PyResult<String> {
+    let value = crate::json::load_named_if_path(py, value_or_path)?;
+    let value = from_json(crate::json::to_value(py, value.as_ref(py))?, "")?;
+    if !value.is_table() {
+        return Err(exceptions::PyValueError::new_err(format!(
+            "TOML documents must be tables, not {}",
+            value.type_str()
+        )));
+    }
+    toml::to_string(&value).map_err(|e| exceptions::PyValueError::new_err(e.to_string()))
+}
+
+/// Parses TOML text into Python objects. Dates and times are returned as strings.
+#[pyfunction]
+pub fn from_toml(py: Python, text: &str) -> PyResult<PyObject> {
+    let value: toml::Value = toml::from_str(text)
+        .map_err(|e| exceptions::PyValueError::new_err(format!("Invalid TOML: {}", e)))?;
+    to_py(py, &value)
+}
+
+fn from_json(value: serde_json::Value, path: &str) -> PyResult<toml::Value> {
+    Ok(match value {
+        serde_json::Value::Null => {
+            return Err(exceptions::PyValueError::new_err(format!(
+                "TOML can't represent None (at {})",
+                if path.is_empty() { "<root>" } else { path }
+            )))
+        }
+        serde_json::Value::Bool(b) => toml::Value::Boolean(b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => toml::Value::Integer(i),
+            None if n.is_u64() => {
+                return Err(exceptions::PyValueError::new_err(format!(
+                    "TOML integers are 64-bit signed, {} is too large (at {})",
+                    n, path
+                )))
+            }
+            None => toml::Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_json::Value::String(s) => toml::Value::String(s),
+        serde_json::Value::Array(elements) => toml::Value::Array(
+            elements
+                .into_iter()
+                .enumerate()
+                .map(|(i, element)| from_json(element, &format!("{}[{}]", path, i)))
+                .collect::<PyResult<_>>()?,
+        ),
+        serde_json::Value::Object(object) => {
+            let mut entries = vec![];
+            for (key, value) in object {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                entries.push((key, from_json(value, &child)?));
+            }
+            // TOML requires the plain values of a table to come before its subtables.
+            entries.sort_by_key(|(_, value)| value.is_table());
+            toml::Value::Table(entries.into_iter().collect())
+        }
+    })
+}
+
+fn to_py(py: Python, value: &toml::Value) -> PyResult<PyObject> {
+    Ok(match value {
+        toml::Value::String(s) => s.into_py(py),
+        toml::Value::Integer(i) => i.into_py(py),
+        toml::Value::Float(f) => f.into_py(py),
+        toml::Value::Boolean(b) => b.into_py(py),
+        toml::Value::Datetime(datetime) => datetime.to_string().into_py(py),
+        toml::Value::Array(elements) => {
+            let elements = elements
+                .iter()
+                .map(|element| to_py(py, element))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, elements).into()
+        }
+        toml::Value::Table(table) => {
+            let dict = PyDict::new(py);
+            for (key, value) in table {
+                dict.set_item(key, to_py(py, value)?)?;
+            }
+            dict.into()
+        }
+    })
+}
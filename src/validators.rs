@@ -0,0 +1,69 @@
+use pyo3::create_exception;
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+create_exception!(
+    pyron,
+    ValidationError,
+    exceptions::PyValueError,
+    "Raised by `load` when values fail their validators. `errors` is a list of `(path, message)`."
+);
+
+/// Runs the validators of a loaded value and raises a `ValidationError` listing every failure.
+/// `validators` maps dotted paths, which can contain `fnmatch` wildcards, to callables that are
+/// called with each value at a matching path. A validator fails by returning a false value or by
+/// raising an exception, whose message is reported. Paths that aren't present aren't validated.
+/// The struct names stored under `tag_key` by `preserve_class_names` aren't validated either.
+pub fn check(py: Python, value: &PyAny, validators: &PyDict, tag_key: &str) -> PyResult<()> {
+    let fnmatch = PyModule::import(py, "fnmatch")?.getattr("fnmatchcase")?;
+    let mut errors = vec![];
+    walk(py, "", value, tag_key, &mut |path, value| {
+        for (pattern, validator) in validators {
+            if !fnmatch.call1((path, pattern))?.is_true()? {
+                continue;
+            }
+            match validator.call1((value,)) {
+                Ok(valid) if valid.is_true()? => {}
+                Ok(_) => errors.push((
+                    path.to_string(),
+                    format!("{} failed validation", value.repr()?),
+                )),
+                Err(e) => errors.push((path.to_string(), e.value(py).to_string())),
+            }
+        }
+        Ok(())
+    })?;
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let message = errors
+        .iter()
+        .map(|(path, message)| format!("\n  {}: {}", path, message))
+        .collect::<String>();
+    let error = ValidationError::new_err(format!(
+        "{} validation error{}:{}",
+        errors.len(),
+        if errors.len() == 1 { "" } else { "s" },
+        message
+    ));
+    error.value(py).setattr("errors", errors)?;
+    Err(error)
+}
+
+/// Calls `f(path, value)` on every node below the root of a loaded value, parents first.
+fn walk(
+    py: Python,
+    path: &str,
+    value: &PyAny,
+    tag_key: &str,
+    f: &mut dyn FnMut(&str, &PyAny) -> PyResult<()>,
+) -> PyResult<()> {
+    if !path.is_empty() {
+        f(path, value)?;
+    }
+    for child in crate::visit::children(py, path, value, tag_key)? {
+        walk(py, &child.path, child.value, tag_key, f)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,161 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use pyo3::{exceptions, PyResult};
+
+use crate::lexer::{self, Token, TokenKind};
+
+/// Expands `#let` variable definitions. A document may start with any number of definitions of
+/// the form `#let name = value;`, which can then be referenced as `$name` anywhere in later
+/// definitions and in the document itself:
+///
+/// ```ron
+/// #let lr = 3e-4;
+/// #let encoder = (layers: 12, dim: 768);
+/// (optimizer: (lr: $lr), encoder: $encoder, decoder: $encoder)
+/// ```
+///
+/// Definitions are replaced by blank lines so that line numbers in diagnostics stay accurate.
+pub fn expand(source: &str) -> PyResult<Cow<str>> {
+    if !source.contains("#let") && !source.contains('$') {
+        return Ok(Cow::Borrowed(source));
+    }
+    let tokens = lexer::tokenize(source);
+    let mut variables = HashMap::new();
+    let mut output = String::with_capacity(source.len());
+    let mut i = 0;
+    loop {
+        while i < tokens.len() && tokens[i].is_trivia() {
+            output.push_str(tokens[i].text(source));
+            i += 1;
+        }
+        if let Some(end) = attribute_end(&tokens, i) {
+            for token in &tokens[i..end] {
+                output.push_str(token.text(source));
+            }
+            i = end;
+        } else if is_punct(&tokens, i, '#') && is_ident(source, &tokens, i + 1, "let") {
+            let (name, value, end) = definition(source, &tokens, i)?;
+            let value = substitute(source, value, &variables)?;
+            variables.insert(name, value.trim().to_string());
+            let newlines = source[tokens[i].start..tokens[end - 1].end]
+                .matches('\n')
+                .count();
+            output.push_str(&"\n".repeat(newlines));
+            i = end;
+        } else {
+            break;
+        }
+    }
+    output.push_str(&substitute(source, &tokens[i..], &variables)?);
+    Ok(Cow::Owned(output))
+}
+
+/// Parses the definition starting at `tokens[start]`, returning the variable name, the tokens of
+/// its value, and the index of the first token after the terminating `;`.
+fn definition<'a>(
+    source: &str,
+    tokens: &'a [Token],
+    start: usize,
+) -> PyResult<(String, &'a [Token], usize)> {
+    let error = |message: &str| {
+        let line = source[..tokens[start].start].matches('\n').count() + 1;
+        exceptions::PyValueError::new_err(format!("Invalid #let on line {}: {}", line, message))
+    };
+    let mut i = skip_trivia(tokens, start + 2);
+    let name = match tokens.get(i) {
+        Some(token) if token.kind == TokenKind::Ident => token.text(source).to_string(),
+        _ => return Err(error("expected a variable name")),
+    };
+    i = skip_trivia(tokens, i + 1);
+    if !is_punct(tokens, i, '=') {
+        return Err(error("expected `=`"));
+    }
+    let value_start = i + 1;
+    let mut depth = 0;
+    for (j, token) in tokens.iter().enumerate().skip(value_start) {
+        match token.kind {
+            TokenKind::Punct('(') | TokenKind::Punct('[') | TokenKind::Punct('{') => depth += 1,
+            TokenKind::Punct(')') | TokenKind::Punct(']') | TokenKind::Punct('}') => depth -= 1,
+            TokenKind::Punct(';') if depth == 0 => {
+                return Ok((name, &tokens[value_start..j], j + 1));
+            }
+            _ => {}
+        }
+    }
+    Err(error("expected `;` after the value"))
+}
+
+/// Concatenates the source text of `tokens`, replacing each `$name` with the text of the variable.
+fn substitute(
+    source: &str,
+    tokens: &[Token],
+    variables: &HashMap<String, String>,
+) -> PyResult<String> {
+    let mut output = String::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if tokens[i].kind == TokenKind::Punct('$')
+            && i + 1 < tokens.len()
+            && tokens[i + 1].kind == TokenKind::Ident
+        {
+            let name = tokens[i + 1].text(source);
+            match variables.get(name) {
+                Some(value) => output.push_str(value),
+                None => {
+                    let line = source[..tokens[i].start].matches('\n').count() + 1;
+                    return Err(exceptions::PyValueError::new_err(format!(
+                        "Undefined variable ${} on line {}",
+                        name, line
+                    )));
+                }
+            }
+            i += 2;
+        } else {
+            output.push_str(tokens[i].text(source));
+            i += 1;
+        }
+    }
+    Ok(output)
+}
+
+/// If `tokens[start..]` begins with an inner attribute like `#![enable(...)]`, returns the index
+/// of the first token after it.
+pub fn attribute_end(tokens: &[Token], start: usize) -> Option<usize> {
+    if !is_punct(tokens, start, '#') || !is_punct(tokens, start + 1, '!') {
+        return None;
+    }
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(start + 2) {
+        match token.kind {
+            TokenKind::Punct('[') => depth += 1,
+            TokenKind::Punct(']') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ if depth == 0 && !token.is_trivia() => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+pub fn skip_trivia(tokens: &[Token], mut i: usize) -> usize {
+    while i < tokens.len() && tokens[i].is_trivia() {
+        i += 1;
+    }
+    i
+}
+
+pub fn is_punct(tokens: &[Token], i: usize, c: char) -> bool {
+    matches!(tokens.get(i), Some(token) if token.kind == TokenKind::Punct(c))
+}
+
+pub fn is_ident(source: &str, tokens: &[Token], i: usize, ident: &str) -> bool {
+    matches!(
+        tokens.get(i),
+        Some(token) if token.kind == TokenKind::Ident && token.text(source) == ident
+    )
+}
@@ -0,0 +1,268 @@
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyList, PyLong, PyTuple};
+
+/// Rewrites a value (or file, if given as `os.PathLike`) by calling `callback(path, value, name)`
+/// on every node and using its return value in place of the node. `path` is the dotted path of the
+/// node, and `name` is its struct name for namedtuples and `!__name__`-tagged dicts, or `None`.
+/// Nodes are visited bottom-up, so `value` already has its children replaced. The callback should
+/// return `value` unchanged to keep a node.
+#[pyfunction]
+pub fn transform(py: Python, value: &PyAny, callback: &PyAny) -> PyResult<PyObject> {
+    let value = crate::load_if_path(py, value)?;
+    visit(py, "", value.as_ref(py), callback)
+}
+
+fn visit(py: Python, path: &str, value: &PyAny, callback: &PyAny) -> PyResult<PyObject> {
+    let mut replaced = vec![];
+    for child in children(py, path, value, "!__name__")? {
+        replaced.push((child.key, visit(py, &child.path, child.value, callback)?));
+    }
+    let rebuilt = rebuild(py, value, replaced)?;
+    let name = match value.downcast::<PyDict>() {
+        Ok(dict) => dict
+            .get_item("!__name__")
+            .map(|name| name.extract())
+            .transpose()?,
+        Err(_) => match value.downcast::<PyTuple>() {
+            Ok(tuple) if crate::is_namedtuple(tuple) => Some(tuple.get_type().name()?.to_string()),
+            _ => None,
+        },
+    };
+    Ok(callback.call1((path, rebuilt, name))?.into())
+}
+
+/// A child of a node in a loaded or serialized value.
+pub struct Child<'py> {
+    /// The dict key or field name, or the index in a list or tuple.
+    pub key: &'py PyAny,
+    /// The dotted path of the child.
+    pub path: String,
+    pub value: &'py PyAny,
+}
+
+/// Returns the children of the node at `path`: the values of dicts, except for the struct name
+/// stored under `tag_key`, the items of lists and tuples, and the fields of namedtuples and
+/// dataclasses.
+pub fn children<'py>(
+    py: Python<'py>,
+    path: &str,
+    value: &'py PyAny,
+    tag_key: &str,
+) -> PyResult<Vec<Child<'py>>> {
+    let mut children = vec![];
+    let mut push = |key: &'py PyAny, path: String, value: &'py PyAny| {
+        children.push(Child { key, path, value });
+    };
+    if let Ok(dict) = value.downcast::<PyDict>() {
+        for (key, child) in dict {
+            if key.extract::<&str>().map_or(true, |key| key != tag_key) {
+                push(key, crate::diff::join(path, key)?, child);
+            }
+        }
+    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+        if crate::is_namedtuple(tuple) {
+            let fields = tuple.getattr("_fields")?.downcast::<PyTuple>()?;
+            for (field, child) in fields.iter().zip(tuple.iter()) {
+                push(field, crate::diff::join(path, field)?, child);
+            }
+        } else {
+            for (i, child) in tuple.iter().enumerate() {
+                push(
+                    i.into_py(py).into_ref(py),
+                    format!("{}[{}]", path, i),
+                    child,
+                );
+            }
+        }
+    } else if let Ok(list) = value.downcast::<PyList>() {
+        for (i, child) in list.iter().enumerate() {
+            push(
+                i.into_py(py).into_ref(py),
+                format!("{}[{}]", path, i),
+                child,
+            );
+        }
+    } else if is_dataclass(py, value)? {
+        for field in value
+            .getattr("__dataclass_fields__")?
+            .downcast::<PyDict>()?
+            .keys()
+        {
+            let child = value.getattr(field.extract::<&str>()?)?;
+            push(field, crate::diff::join(path, field)?, child);
+        }
+    }
+    Ok(children)
+}
+
+/// Copies a node, replacing its children with the `(key, value)` pairs of `children`, given in the
+/// order returned by `children`.
+pub fn rebuild(py: Python, value: &PyAny, children: Vec<(&PyAny, PyObject)>) -> PyResult<PyObject> {
+    let values = || children.iter().map(|(_, child)| child.clone_ref(py));
+    Ok(if let Ok(dict) = value.downcast::<PyDict>() {
+        let result = dict.copy()?;
+        for (key, child) in &children {
+            result.set_item(key, child)?;
+        }
+        result.into()
+    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+        if crate::is_namedtuple(tuple) {
+            tuple
+                .call_method1("_make", (values().collect::<Vec<_>>(),))?
+                .into()
+        } else {
+            PyTuple::new(py, values()).into()
+        }
+    } else if value.downcast::<PyList>().is_ok() {
+        PyList::new(py, values()).into()
+    } else if is_dataclass(py, value)? {
+        // Copy the instance and set the fields directly, which also works for frozen dataclasses
+        // and fields that aren't arguments of `__init__`.
+        let copy = PyModule::import(py, "copy")?.call_method1("copy", (value,))?;
+        let set_attribute = PyModule::import(py, "builtins")?
+            .getattr("object")?
+            .getattr("__setattr__")?;
+        for (field, child) in &children {
+            set_attribute.call1((copy, *field, child))?;
+        }
+        copy.into()
+    } else {
+        value.into()
+    })
+}
+
+fn is_dataclass(py: Python, value: &PyAny) -> PyResult<bool> {
+    PyModule::import(py, "dataclasses")?
+        .call_method1("is_dataclass", (value,))?
+        .is_true()
+}
+
+/// Replaces the nodes of a value that are selected by `redact` with `"***"`. `redact` is either a
+/// list of dotted paths, which can contain `fnmatch` wildcards (`*.token` matches `token` fields at
+/// any depth below the root) and list indices (`[*]` matches any index, as in
+/// `devices[*].token`), or a callable `redact(path, value)` returning whether to replace the node.
+/// Nodes are visited top-down, and the children of replaced nodes aren't visited.
+pub fn redact(py: Python, value: &PyAny, redact: &PyAny, tag_key: &str) -> PyResult<PyObject> {
+    let fnmatch = PyModule::import(py, "fnmatch")?.getattr("fnmatchcase")?;
+    let patterns = if redact.is_callable() {
+        vec![]
+    } else {
+        let mut patterns = vec![];
+        for pattern in redact.iter()? {
+            patterns.push(fnmatch_pattern(pattern?.extract::<&str>()?));
+        }
+        patterns
+    };
+    let selects = |path: &str, value: &PyAny| -> PyResult<bool> {
+        if redact.is_callable() {
+            return redact.call1((path, value))?.is_true();
+        }
+        for pattern in &patterns {
+            if fnmatch.call1((path, pattern.as_str()))?.is_true()? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    };
+    replace_nodes(py, "", value, tag_key, &|path, value| {
+        Ok(if selects(path, value)? {
+            Some("***".into_py(py))
+        } else {
+            None
+        })
+    })
+}
+
+/// Replaces the integers selected by `formats`, a dict mapping dotted paths (which can contain
+/// `fnmatch` wildcards and list indices, as for `redact`) to `"hex"`, `"binary"` or `"octal"`,
+/// with `LiteralInt`s whose source is the integer in that base, such as `0xFF`.
+pub fn int_formats(
+    py: Python,
+    value: &PyAny,
+    formats: &PyDict,
+    tag_key: &str,
+) -> PyResult<PyObject> {
+    let fnmatch = PyModule::import(py, "fnmatch")?.getattr("fnmatchcase")?;
+    let mut patterns = vec![];
+    for (pattern, format) in formats {
+        let (prefix, spec) = match format.extract::<&str>()? {
+            "hex" => ("0x", "X"),
+            "binary" => ("0b", "b"),
+            "octal" => ("0o", "o"),
+            format => {
+                return Err(exceptions::PyValueError::new_err(format!(
+                    "int_formats values must be \"hex\", \"binary\" or \"octal\", not \"{}\"",
+                    format
+                )))
+            }
+        };
+        patterns.push((fnmatch_pattern(pattern.extract::<&str>()?), prefix, spec));
+    }
+    let format = PyModule::import(py, "builtins")?.getattr("format")?;
+    replace_nodes(py, "", value, tag_key, &|path, value| {
+        if value.downcast::<PyLong>().is_err() || value.downcast::<PyBool>().is_ok() {
+            return Ok(None);
+        }
+        for &(ref pattern, prefix, spec) in &patterns {
+            if fnmatch.call1((path, pattern.as_str()))?.is_true()? {
+                let digits = format.call1((value, spec))?.extract::<&str>()?;
+                let source = match digits.strip_prefix('-') {
+                    Some(digits) => format!("-{}{}", prefix, digits),
+                    None => format!("{}{}", prefix, digits),
+                };
+                return Ok(Some(crate::literals::literal(py, value.into(), &source)?));
+            }
+        }
+        Ok(None)
+    })
+}
+
+/// Translates the list indices of a path pattern, `[*]` for any index and `[n]` for index `n`, to
+/// `fnmatch`, where brackets enclose a set of characters.
+fn fnmatch_pattern(pattern: &str) -> String {
+    let mut result = String::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('[') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let index = after.find(']').map(|end| &after[..end]);
+        match index {
+            Some(index)
+                if index == "*"
+                    || (!index.is_empty() && index.chars().all(|c| c.is_ascii_digit())) =>
+            {
+                result.push_str(&format!("[[]{}[]]", index));
+                rest = &after[index.len() + 1..];
+            }
+            _ => {
+                result.push('[');
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Copies a value, replacing the nodes below the root for which `replace(path, value)` returns a
+/// replacement. The children of replaced nodes aren't visited.
+fn replace_nodes(
+    py: Python,
+    path: &str,
+    value: &PyAny,
+    tag_key: &str,
+    replace: &dyn Fn(&str, &PyAny) -> PyResult<Option<PyObject>>,
+) -> PyResult<PyObject> {
+    if !path.is_empty() {
+        if let Some(replacement) = replace(path, value)? {
+            return Ok(replacement);
+        }
+    }
+    let mut replaced = vec![];
+    for child in children(py, path, value, tag_key)? {
+        let replacement = replace_nodes(py, &child.path, child.value, tag_key, replace)?;
+        replaced.push((child.key, replacement));
+    }
+    rebuild(py, value, replaced)
+}
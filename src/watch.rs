@@ -0,0 +1,179 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use pyo3::exceptions;
+use pyo3::prelude::*;
+
+use crate::cache::modified;
+use crate::Converter;
+
+/// Watches a RON file and the files it includes, and calls `callback` with the newly loaded value
+/// whenever any of them changes. Files are polled for changes every `interval` seconds on a
+/// background thread. Errors while reloading (for example, while a file is only partially
+/// written) are passed to `on_error`, or printed if it is `None`. Values are converted as by
+/// `load` with the given options. The returned `Watcher` stops watching when its `stop` method is
+/// called or at the end of a `with` block.
+#[pyfunction]
+#[pyo3(signature = (
+    path,
+    callback,
+    interval = 1.0,
+    on_error = None,
+    preserve_structs = false,
+    preserve_class_names = false,
+))]
+pub fn watch(
+    py: Python,
+    path: PathBuf,
+    callback: PyObject,
+    interval: f64,
+    on_error: Option<PyObject>,
+    preserve_structs: bool,
+    preserve_class_names: bool,
+) -> PyResult<Watcher> {
+    if preserve_structs && preserve_class_names {
+        return Err(exceptions::PyValueError::new_err(
+            "preserve_structs and preserve_class_names cannot be true at the same time",
+        ));
+    }
+    // A zero interval would poll the files in a busy loop.
+    let interval = Duration::try_from_secs_f64(interval)
+        .ok()
+        .filter(|interval| !interval.is_zero())
+        .ok_or_else(|| exceptions::PyValueError::new_err("interval must be a positive number"))?;
+    let mut state = State {
+        path,
+        callback,
+        on_error,
+        preserve_structs,
+        preserve_class_names,
+        files: vec![],
+    };
+    // Load once to find the included files, and to fail early if the file can't be loaded.
+    state.load(py)?;
+    let stopped = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let stopped = stopped.clone();
+        std::thread::spawn(move || state.run(interval, &stopped))
+    };
+    Ok(Watcher {
+        stopped,
+        thread: Some(thread),
+    })
+}
+
+/// Handle returned by `watch`.
+#[pyclass(module = "pyron")]
+pub struct Watcher {
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[pymethods]
+impl Watcher {
+    /// Stops watching, waiting for a callback that is currently running to return.
+    fn stop(&mut self, py: Python) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            // Joining a thread that is waiting for the GIL requires releasing it.
+            let _ = py.allow_threads(|| thread.join());
+        }
+    }
+
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        py: Python,
+        _exc_type: &PyAny,
+        _exc_value: &PyAny,
+        _traceback: &PyAny,
+    ) -> bool {
+        self.stop(py);
+        false
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+struct State {
+    path: PathBuf,
+    callback: PyObject,
+    on_error: Option<PyObject>,
+    preserve_structs: bool,
+    preserve_class_names: bool,
+    /// The watched files with their modification times when they were last loaded.
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl State {
+    /// Loads the file and records the files it was loaded from.
+    fn load(&mut self, py: Python) -> PyResult<PyObject> {
+        let document = crate::parse_file(py, &self.path, true)?;
+        let mut converter = Converter::new(py);
+        converter.preserve_structs = self.preserve_structs;
+        converter.preserve_class_names = self.preserve_class_names;
+        converter.includes.enter_file(&self.path)?;
+        let value = converter.convert_document(&document)?;
+        // Sources other than files can't be watched, but the file itself still can.
+        let files = match converter.includes.files() {
+            Some(files) => files.to_vec(),
+            None => vec![self.path.clone()],
+        };
+        self.files = files
+            .into_iter()
+            .map(|file| {
+                let mtime = modified(&file);
+                (file, mtime)
+            })
+            .collect();
+        Ok(value)
+    }
+
+    fn run(mut self, interval: Duration, stopped: &AtomicBool) {
+        let step = interval.min(Duration::from_millis(50));
+        let mut waited = Duration::ZERO;
+        while !stopped.load(Ordering::Relaxed) {
+            std::thread::sleep(step);
+            waited += step;
+            if waited < interval {
+                continue;
+            }
+            waited = Duration::ZERO;
+            let changed = self
+                .files
+                .iter()
+                .any(|(file, mtime)| modified(file) != *mtime);
+            if !changed || stopped.load(Ordering::Relaxed) {
+                continue;
+            }
+            Python::with_gil(|py| {
+                let result = self
+                    .load(py)
+                    .and_then(|value| self.callback.call1(py, (value,)));
+                if let Err(e) = result {
+                    // Reload again on the next change rather than on every poll.
+                    for (file, mtime) in &mut self.files {
+                        *mtime = modified(file);
+                    }
+                    let reported = match &self.on_error {
+                        Some(on_error) => on_error.call1(py, (e.value(py),)).map(|_| ()),
+                        None => Err(e),
+                    };
+                    if let Err(e) = reported {
+                        e.print(py);
+                    }
+                }
+            });
+        }
+    }
+}
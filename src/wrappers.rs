@@ -0,0 +1,204 @@
+use pyo3::basic::CompareOp;
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyString, PyTuple};
+
+/// A RON char such as `'c'`, returned for chars by `load` with `preserve_chars` and written back
+/// as a char by `to_string`, whereas plain strings are always written as strings. The character
+/// is `value`, and `str(char)` returns it as well.
+#[pyclass(module = "pyron")]
+pub struct Char {
+    value: char,
+}
+
+impl Char {
+    pub fn new(value: char) -> Char {
+        Char { value }
+    }
+
+    pub fn value(&self) -> char {
+        self.value
+    }
+}
+
+#[pymethods]
+impl Char {
+    #[new]
+    fn py_new(value: &str) -> PyResult<Char> {
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            (Some(value), None) => Ok(Char::new(value)),
+            _ => Err(exceptions::PyValueError::new_err(format!(
+                "Char requires a string of one character, not {:?}",
+                value
+            ))),
+        }
+    }
+
+    #[getter(value)]
+    fn py_value(&self) -> String {
+        self.value.to_string()
+    }
+
+    fn __str__(&self) -> String {
+        self.value.to_string()
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        let value = PyString::new(py, self.value.encode_utf8(&mut [0; 4]));
+        Ok(format!("Char({})", value.repr()?))
+    }
+
+    fn __hash__(&self) -> u64 {
+        self.value as u64
+    }
+
+    fn __richcmp__(&self, py: Python, other: &PyAny, op: CompareOp) -> PyObject {
+        let other = match other.extract::<PyRef<Char>>() {
+            Ok(other) => other,
+            Err(_) => return py.NotImplemented(),
+        };
+        match op {
+            CompareOp::Eq => (self.value == other.value).into_py(py),
+            CompareOp::Ne => (self.value != other.value).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+
+    fn __reduce__<'py>(&self, py: Python<'py>) -> (PyObject, &'py PyTuple) {
+        (
+            py.get_type::<Char>().into(),
+            PyTuple::new(py, [self.value.to_string()]),
+        )
+    }
+}
+
+/// An `Option` that is present, as in `Some(value)`, returned for `Some(...)` by `load` with
+/// `preserve_options` and written back as `Some(...)` by `to_string`, so that values of Rust
+/// `Option<T>` fields keep their wrapper. The wrapped value is `value`.
+#[pyclass(name = "Some", module = "pyron")]
+pub struct SomeValue {
+    value: PyObject,
+}
+
+impl SomeValue {
+    pub fn new(value: PyObject) -> SomeValue {
+        SomeValue { value }
+    }
+
+    pub fn value<'py>(&self, py: Python<'py>) -> &'py PyAny {
+        self.value.as_ref(py)
+    }
+}
+
+#[pymethods]
+impl SomeValue {
+    #[new]
+    fn py_new(value: PyObject) -> SomeValue {
+        SomeValue::new(value)
+    }
+
+    #[getter(value)]
+    fn py_value(&self, py: Python) -> PyObject {
+        self.value.clone_ref(py)
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        Ok(format!("Some({})", self.value(py).repr()?))
+    }
+
+    fn __hash__(&self, py: Python) -> PyResult<isize> {
+        self.value(py).hash()
+    }
+
+    fn __richcmp__(&self, py: Python, other: &PyAny, op: CompareOp) -> PyResult<PyObject> {
+        let other = match other.extract::<PyRef<SomeValue>>() {
+            Ok(other) => other,
+            Err(_) => return Ok(py.NotImplemented()),
+        };
+        match op {
+            CompareOp::Eq | CompareOp::Ne => {
+                Ok(self.value(py).rich_compare(other.value(py), op)?.into())
+            }
+            _ => Ok(py.NotImplemented()),
+        }
+    }
+
+    fn __reduce__<'py>(&self, py: Python<'py>) -> (PyObject, &'py PyTuple) {
+        (
+            py.get_type::<SomeValue>().into(),
+            PyTuple::new(py, [&self.value]),
+        )
+    }
+}
+
+/// A unit struct or an enum variant without fields, such as `Paused`, returned by `load` with
+/// `preserve_units` and written back as the bare name by `to_string`, so that variants can be
+/// told apart instead of all loading as `None`. The name is `name`.
+#[pyclass(module = "pyron")]
+pub struct Unit {
+    name: String,
+}
+
+impl Unit {
+    pub fn new(name: String) -> Unit {
+        Unit { name }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Whether a name is a RON identifier, as the names of structs, fields and units must be.
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[pymethods]
+impl Unit {
+    #[new]
+    fn py_new(name: String) -> PyResult<Unit> {
+        if !is_identifier(&name) {
+            return Err(exceptions::PyValueError::new_err(format!(
+                "Unit requires an identifier, not {:?}",
+                name
+            )));
+        }
+        Ok(Unit::new(name))
+    }
+
+    #[getter(name)]
+    fn py_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn __repr__(&self, py: Python) -> PyResult<String> {
+        Ok(format!("Unit({})", PyString::new(py, &self.name).repr()?))
+    }
+
+    fn __hash__(&self, py: Python) -> PyResult<isize> {
+        PyString::new(py, &self.name).hash()
+    }
+
+    fn __richcmp__(&self, py: Python, other: &PyAny, op: CompareOp) -> PyObject {
+        let other = match other.extract::<PyRef<Unit>>() {
+            Ok(other) => other,
+            Err(_) => return py.NotImplemented(),
+        };
+        match op {
+            CompareOp::Eq => (self.name == other.name).into_py(py),
+            CompareOp::Ne => (self.name != other.name).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+
+    fn __reduce__<'py>(&self, py: Python<'py>) -> (PyObject, &'py PyTuple) {
+        (
+            py.get_type::<Unit>().into(),
+            PyTuple::new(py, [self.name.as_str()]),
+        )
+    }
+}
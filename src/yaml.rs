@@ -0,0 +1,99 @@
+use pyo3::exceptions;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::Value;
+
+use crate::json::NAME_KEY;
+
+/// Serializes a value, or a RON file given as `os.PathLike`, to YAML. Named structs become
+/// mappings tagged with their name, e.g. `!Adam {lr: 0.1}`.
+#[pyfunction]
+pub fn to_yaml(py: Python, value_or_path: &PyAny) -> PyResult<String> {
+    let value = crate::json::load_named_if_path(py, value_or_path)?;
+    let value = from_json(crate::json::to_value(py, value.as_ref(py))?);
+    serde_yaml::to_string(&value).map_err(|e| exceptions::PyValueError::new_err(e.to_string()))
+}
+
+/// Parses YAML text into Python objects. Tagged mappings become dicts with the tag stored under
+/// `"!__name__"`, as with `preserve_class_names`.
+#[pyfunction]
+pub fn from_yaml(py: Python, text: &str) -> PyResult<PyObject> {
+    let value: Value = serde_yaml::from_str(text)
+        .map_err(|e| exceptions::PyValueError::new_err(format!("Invalid YAML: {}", e)))?;
+    to_py(py, &value)
+}
+
+/// Converts the JSON representation of a value into YAML, turning `"!__name__"` keys into tags.
+fn from_json(value: serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Bool(b),
+        serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => Value::from(i),
+            (None, Some(u)) => Value::from(u),
+            _ => Value::from(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_json::Value::String(s) => Value::String(s),
+        serde_json::Value::Array(elements) => {
+            Value::Sequence(elements.into_iter().map(from_json).collect())
+        }
+        serde_json::Value::Object(mut object) => {
+            let name = match object.remove(NAME_KEY) {
+                Some(serde_json::Value::String(name)) => Some(name),
+                _ => None,
+            };
+            let mapping = Value::Mapping(
+                object
+                    .into_iter()
+                    .map(|(key, value)| (Value::String(key), from_json(value)))
+                    .collect(),
+            );
+            match name {
+                Some(name) => Value::Tagged(Box::new(TaggedValue {
+                    tag: Tag::new(name),
+                    value: mapping,
+                })),
+                None => mapping,
+            }
+        }
+    }
+}
+
+fn to_py(py: Python, value: &Value) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.into_py(py),
+        Value::Number(n) => match (n.as_i64(), n.as_u64(), n.as_f64()) {
+            (Some(i), _, _) => i.into_py(py),
+            (None, Some(u), _) => u.into_py(py),
+            (_, _, f) => f.into_py(py),
+        },
+        Value::String(s) => s.into_py(py),
+        Value::Sequence(elements) => {
+            let elements = elements
+                .iter()
+                .map(|element| to_py(py, element))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, elements).into()
+        }
+        Value::Mapping(mapping) => {
+            let dict = PyDict::new(py);
+            for (key, value) in mapping {
+                dict.set_item(to_py(py, key)?, to_py(py, value)?)?;
+            }
+            dict.into()
+        }
+        Value::Tagged(tagged) => {
+            let value = to_py(py, &tagged.value)?;
+            match value.as_ref(py).downcast::<PyDict>() {
+                Ok(dict) => {
+                    let name = tagged.tag.to_string();
+                    dict.set_item(NAME_KEY, name.trim_start_matches('!'))?;
+                    value
+                }
+                Err(_) => value,
+            }
+        }
+    })
+}
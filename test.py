@@ -90,3 +90,1916 @@ assert result == {
 
 result = pyron.loads("Foo()", preserve_class_names=True)
 assert result == {"!__name__": "Foo"}, result
+
+
+import os
+import tempfile
+
+with tempfile.TemporaryDirectory() as tmp:
+    os.makedirs(os.path.join(tmp, "common"))
+    with open(os.path.join(tmp, "common", "optimizer.ron"), "w") as f:
+        f.write("Adam(lr: 0.001)")
+    result = pyron.loads('(optimizer: #include("common/optimizer.ron"))', include_paths=[tmp])
+    assert result == {"optimizer": {"lr": 0.001}}, result
+
+with tempfile.TemporaryDirectory() as tmp:
+    with open(os.path.join(tmp, "a.ron"), "w") as f:
+        f.write('(b: #include("b.ron"))')
+    with open(os.path.join(tmp, "b.ron"), "w") as f:
+        f.write('(a: #include("a.ron"))')
+    try:
+        pyron.load(os.path.join(tmp, "a.ron"))
+        assert False, "expected include cycle error"
+    except ValueError as e:
+        assert "a.ron -> " in str(e) and "b.ron -> " in str(e), e
+
+fixtures = {"base.ron": '(lr: 0.1, schedule: #include("schedule.ron"))', "schedule.ron": '"cosine"'}
+result = pyron.loads('#include("base.ron")', include_resolver=fixtures.__getitem__)
+assert result == {"lr": 0.1, "schedule": "cosine"}, result
+
+with tempfile.TemporaryDirectory() as tmp:
+    os.makedirs(os.path.join(tmp, "experiments"))
+    with open(os.path.join(tmp, "experiments", "a.ron"), "w") as f:
+        f.write("(model: (layers: 2, dim: 64))")
+    with open(os.path.join(tmp, "experiments", "b.ron"), "w") as f:
+        f.write("(model: (layers: 4))")
+    with open(os.path.join(tmp, "main.ron"), "w") as f:
+        f.write('#include("experiments/*.ron")')
+    result = pyron.load(os.path.join(tmp, "main.ron"))
+    assert result == {"model": {"layers": 4, "dim": 64}}, result
+
+try:
+    pyron.loads('(a: #include("/etc/passwd"))', allow_includes=False)
+    assert False, "expected includes to be rejected"
+except ValueError as e:
+    assert "allow_includes=False" in str(e), e
+
+remote = {
+    "https://configs.internal/base.ron": '(optimizer: #include("optimizer.ron"))',
+    "https://configs.internal/optimizer.ron": "(lr: 0.01)",
+}
+result = pyron.loads(
+    '#include("https://configs.internal/base.ron")',
+    allowed_urls=["https://configs.internal/"],
+    url_fetcher=remote.__getitem__,
+)
+assert result == {"optimizer": {"lr": 0.01}}, result
+try:
+    pyron.loads('#include("https://evil.example/x.ron")', allowed_urls=["https://configs.internal/"])
+    assert False, "expected URL to be rejected"
+except ValueError as e:
+    assert "allowed URL prefixes" in str(e), e
+remote = {
+    "https://configs.internal/team/absolute.ron": '(secret: #include("/etc/passwd"))',
+    "https://configs.internal/team/glob.ron": '(parts: #include("*.ron"))',
+    "https://configs.internal/team/escape.ron": '(other: #include("../../evil.example/x.ron"))',
+    "https://configs.internal/team/sibling.ron": '(base: #include("../shared/./base.ron"))',
+    "https://configs.internal/shared/base.ron": "(lr: 0.1)",
+}
+for name, message in [("absolute", "relative path"), ("glob", "glob pattern"), ("escape", "allowed URL prefixes")]:
+    try:
+        pyron.loads(
+            f'#include("https://configs.internal/team/{name}.ron")',
+            allowed_urls=["https://configs.internal/team/"],
+            url_fetcher=remote.__getitem__,
+        )
+        assert False, f"expected {name} include to be rejected"
+    except ValueError as e:
+        assert message in str(e), e
+result = pyron.loads(
+    '#include("https://configs.internal/team/sibling.ron")',
+    allowed_urls=["https://configs.internal/"],
+    url_fetcher=remote.__getitem__,
+)
+assert result == {"base": {"lr": 0.1}}, result
+lookalikes = [
+    "https://configs.internal.evil.example/team/x.ron",
+    "https://configs.internal@evil.example/team/x.ron",
+    "https://configs.internal:8443/team/x.ron",
+    "http://configs.internal/team/x.ron",
+    "https://configs.internal/teammate/x.ron",
+]
+for url in lookalikes:
+    try:
+        pyron.loads(f'#include("{url}")', allowed_urls=["https://configs.internal/team"], url_fetcher=lambda url: "1")
+        assert False, url
+    except ValueError as e:
+        assert "allowed URL prefixes" in str(e), e
+result = pyron.loads(
+    '#include("https://CONFIGS.internal:443/team/shared.ron")',
+    allowed_urls=["https://configs.internal/team"],
+    url_fetcher=lambda url: "(lr: 0.1)",
+)
+assert result == {"lr": 0.1}, result
+
+os.environ["PYRON_TEST_DATA"] = "/data"
+result = pyron.loads('(path: "${PYRON_TEST_DATA}/train", cache: "${PYRON_TEST_MISSING:-/tmp}")', interpolate_env=True)
+assert result == {"path": "/data/train", "cache": "/tmp"}, result
+
+result = pyron.loads(
+    '(model: (hidden_size: 256, name: "base"), ffn_size: "${model.hidden_size} * 4", run: "${model.name}-v2", size: "${model.hidden_size}")',
+    interpolate_refs=True,
+)
+assert result == {"model": {"hidden_size": 256, "name": "base"}, "ffn_size": 1024, "run": "base-v2", "size": 256}, result
+result = pyron.loads(
+    '(name: "base", template: "$${name}", mixed: "${name}: $${name} costs $$5", only: "$${")',
+    interpolate_refs=True,
+)
+assert result == {"name": "base", "template": "${name}", "mixed": "base: ${name} costs $$5", "only": "${"}, result
+
+result = pyron.loads(
+    """
+#let lr = 3e-4;
+#let encoder = (layers: 12, dim: 768);
+(optimizer: (lr: $lr), encoder: $encoder, decoder: $encoder, note: "costs $5")
+"""
+)
+assert result == {
+    "optimizer": {"lr": 3e-4},
+    "encoder": {"layers": 12, "dim": 768},
+    "decoder": {"layers": 12, "dim": 768},
+    "note": "costs $5",
+}, result
+
+base = pyron.loads('(model: Transformer(layers: 12, dim: 768), tags: ["base"], lr: 0.1)', preserve_class_names=True)
+override = pyron.loads('(model: Transformer(layers: 24), tags: ["large"])', preserve_class_names=True)
+assert pyron.merge(base, override) == {
+    "model": {"layers": 24, "dim": 768, "!__name__": "Transformer"},
+    "tags": ["large"],
+    "lr": 0.1,
+}
+assert pyron.merge(base, override, lists="concat")["tags"] == ["base", "large"]
+override = pyron.loads('(model: Mlp(layers: 2))', preserve_class_names=True)
+assert pyron.merge(base, override)["model"] == {"layers": 2, "!__name__": "Mlp"}
+
+config = pyron.loads("(optimizer: (lr: 0.1), model: (layers: [(dim: 64)], arch: \"mlp\"))")
+result = pyron.apply_overrides(config, {"optimizer.lr": "3e-4", "model.layers[0].dim": "128", "model.arch": "resnet", "seed": 5})
+assert result == {"optimizer": {"lr": 3e-4}, "model": {"layers": [{"dim": 128}], "arch": "resnet"}, "seed": 5}, result
+assert config["optimizer"]["lr"] == 0.1
+
+a = pyron.loads("(optimizer: (lr: 0.1, momentum: 0.9), layers: [64, 64], seed: 1)")
+b = pyron.loads("(optimizer: (lr: 0.01), layers: [64, 64, 32], seed: 1, name: \"run\")")
+assert pyron.diff(a, b) == {
+    "added": {"layers[2]": 32, "name": "run"},
+    "removed": {"optimizer.momentum": 0.9},
+    "changed": {"optimizer.lr": (0.1, 0.01)},
+}, pyron.diff(a, b)
+assert pyron.patch(a, pyron.diff(a, b), strict=True) == b
+
+assert pyron.canonicalize("(b: 1, a: Adam(lr: 1e-3, beta: 0.9))") == pyron.canonicalize(
+    """(
+    a: Adam(beta: 0.9, lr: 0.001),
+    b: 1,
+)"""
+)
+assert pyron.canonicalize({"b": 1, "a": 2}) == pyron.canonicalize({"a": 2, "b": 1})
+assert pyron.fingerprint({"b": 1, "a": 2}) == pyron.fingerprint({"a": 2, "b": 1})
+assert len(pyron.fingerprint({"a": 1}, length=12)) == 12
+
+config = {"model": {"layers": [{"dim": 64}, {"dim": 32}], "betas": (0.9, 0.99)}, "optimizer": {"lr": 3e-4}}
+flat = pyron.flatten(config)
+assert flat == {"model.layers[0].dim": 64, "model.layers[1].dim": 32, "model.betas": (0.9, 0.99), "optimizer.lr": 3e-4}, flat
+assert pyron.unflatten(flat) == config
+
+text = "(training: (optimizer: Adam(lr: 3e-4)), layers: [(dim: 64), (dim: 32)])"
+assert pyron.get(text=text, path="training.optimizer.lr") == 3e-4
+assert pyron.get(text=text, path="layers[*].dim") == [64, 32]
+assert pyron.get(text=text, path="training.missing", default=1) == 1
+from pathlib import Path
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "query.ron")
+    with open(path, "w") as f:
+        f.write(text)
+    assert pyron.get(Path(path), "layers[1].dim") == 32
+    # A `str` is a path, as for `load`.
+    assert pyron.get(path, "layers[0].dim") == 64
+    try:
+        pyron.get(os.path.join(tmp, "qurey.ron"), "layers")
+        assert False
+    except FileNotFoundError:
+        pass
+    for args, kwargs in [((path, "layers"), {"text": text}), ((), {"path": "layers"})]:
+        try:
+            pyron.get(*args, **kwargs)
+            assert False, kwargs
+        except TypeError as e:
+            assert "text=" in str(e), e
+
+config = pyron.loads("(encoder: Adam(lr: 0.1), decoder: Sgd(lr: 0.2), steps: 10)", preserve_class_names=True)
+scaled = pyron.transform(config, lambda path, value, name: value * 0.5 if path.endswith("lr") else value)
+assert scaled["encoder"]["lr"] == 0.05 and scaled["decoder"]["lr"] == 0.1 and scaled["steps"] == 10, scaled
+names = []
+pyron.transform(config, lambda path, value, name: names.append((path, name)) or value)
+assert ("encoder", "Adam") in names and ("steps", None) in names, names
+
+doc = pyron.parse_document(text="""(
+    // Learning rate schedule
+    optimizer: Adam(
+        lr: 0.1, // tuned
+        betas: (0.9, 0.99),
+    ),
+    layers: [64, 32],
+)""")
+doc["optimizer"]["lr"] = 0.5
+doc["optimizer"]["eps"] = 1
+doc["layers"].append(16)
+del doc["optimizer"]["betas"]
+assert doc["optimizer"]["lr"] == 0.5 and "eps" in doc["optimizer"]
+assert doc.to_string() == """(
+    // Learning rate schedule
+    optimizer: Adam(
+        lr: 0.5, // tuned
+        eps: 1,
+    ),
+    layers: [64, 32, 16],
+)""", doc.to_string()
+assert doc.value == {"optimizer": {"lr": 0.5, "eps": 1}, "layers": [64, 32, 16]}, doc.value
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "document.ron")
+    with open(path, "w") as f:
+        f.write("(lr: 0.1)")
+    assert pyron.parse_document(Path(path)).value == {"lr": 0.1}
+    assert pyron.parse_document(path).value == {"lr": 0.1}
+    try:
+        pyron.parse_document(path, text="(lr: 0.2)")
+        assert False
+    except TypeError as e:
+        assert "text=" in str(e), e
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "config.ron")
+    with open(path, "w") as f:
+        f.write("""(
+    // Optimizer settings
+    optimizer: (
+        lr: 0.1, // peak LR
+    ),
+    seed: 1,
+)""")
+    comments = {}
+    config = pyron.load(path, comments=comments)
+    assert comments == {"optimizer": "Optimizer settings", "optimizer.lr": "peak LR"}, comments
+    config["seed"] = 2
+    pyron.dump(config, path, comments=comments)
+    reloaded = {}
+    assert pyron.load(path, comments=reloaded, cache=True) == {"optimizer": {"lr": 0.1}, "seed": 2}
+    assert reloaded == comments, reloaded
+    reloaded = {}
+    assert pyron.load(path, comments=reloaded, cache=True) == {"optimizer": {"lr": 0.1}, "seed": 2}
+    assert reloaded == comments, reloaded
+    with open(path) as f:
+        reloaded = {}
+        assert pyron.load(f, comments=reloaded) == {"optimizer": {"lr": 0.1}, "seed": 2}
+        assert reloaded == comments, reloaded
+
+Optimizer = namedtuple("Optimizer", ["lr", "warmup"])
+text = pyron.to_string({"optimizer": Optimizer(lr=3e-4, warmup=1000)}, comments={"optimizer.lr": "peak LR after warmup", "optimizer": "Adam\nwith defaults"})
+assert "    // Adam\n    // with defaults\n    \"optimizer\": Optimizer(" in text, text
+assert "        // peak LR after warmup\n        lr: " in text, text
+assert pyron.loads(text) == {"optimizer": {"lr": 3e-4, "warmup": 1000}}
+
+from dataclasses import field
+from typing import Annotated
+
+
+@dataclass
+class Schedule:
+    warmup: Annotated[int, "number of warmup steps"]
+    lr: float = field(default=1e-3, metadata={"doc": "peak learning rate"})
+
+
+text = pyron.to_string({"schedule": Schedule(warmup=100)}, field_docs=True)
+assert "        // number of warmup steps\n        warmup: 100," in text, text
+assert "        // peak learning rate\n        lr: " in text, text
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "config.ron")
+    with open(path, "w") as f:
+        f.write("""(
+    training: (
+        steps: 1000, // short run
+        layers: [64, 32],
+    ),
+)
+""")
+    pyron.edit(path, {"training.steps": 100000, "training.layers[1]": 16})
+    with open(path) as f:
+        assert f.read() == """(
+    training: (
+        steps: 100000, // short run
+        layers: [64, 16],
+    ),
+)
+"""
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "config.ron")
+    with open(path, "w") as f:
+        f.write("(\n    // batch size per device\n    batch_size: 32,\n)\n")
+    with pyron.open(path) as doc:
+        doc["batch_size"] = 64
+        doc["seed"] = 1
+    with open(path) as f:
+        assert f.read() == "(\n    // batch size per device\n    batch_size: 64,\n    seed: 1,\n)\n"
+    assert os.listdir(tmp) == ["config.ron"]
+
+import contextlib
+import json
+import pathlib
+import io
+
+
+@dataclass
+class Training:
+    steps: int
+    lr: float = 1e-3
+
+
+with tempfile.TemporaryDirectory() as tmp:
+    good = os.path.join(tmp, "good.ron")
+    bad = os.path.join(tmp, "bad.ron")
+    with open(good, "w") as f:
+        f.write("(steps: 10, lr: 1)")
+    with open(bad, "w") as f:
+        f.write("(lr: \"fast\", epochs: 2)")
+    assert pyron.main(["check", good]) == 0
+    stderr = io.StringIO()
+    with contextlib.redirect_stderr(stderr):
+        assert pyron.main(["check", good, bad, "--schema", "__main__:Training"]) == 1
+    assert stderr.getvalue() == f"{bad}: steps: missing field\n{bad}: lr: expected float, found str\n{bad}: epochs: unknown field\n", stderr.getvalue()
+
+with tempfile.TemporaryDirectory() as tmp:
+    ron_path = os.path.join(tmp, "config.ron")
+    json_path = os.path.join(tmp, "config.json")
+    with open(ron_path, "w") as f:
+        f.write("(optimizer: Adam(lr: 0.1), layers: [64, 32])")
+    assert pyron.main(["convert", ron_path, "--to", "json", "--output", json_path]) == 0
+    with open(json_path) as f:
+        assert json.load(f) == {"optimizer": {"lr": 0.1}, "layers": [64, 32]}
+    stdout = io.StringIO()
+    with contextlib.redirect_stdout(stdout):
+        assert pyron.main(["convert", json_path, "--to", "ron"]) == 0
+    assert pyron.loads(stdout.getvalue()) == {"optimizer": {"lr": 0.1}, "layers": [64, 32]}
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "config.ron")
+    with open(path, "w") as f:
+        f.write("(training: (optimizer: Adam(lr: 0.1, name: \"adam\")))")
+    for args, expected in [
+        (["training.optimizer.lr"], "0.1\n"),
+        (["training.optimizer.name"], "\"adam\"\n"),
+        (["training.optimizer.name", "--raw"], "adam\n"),
+    ]:
+        stdout = io.StringIO()
+        with contextlib.redirect_stdout(stdout):
+            assert pyron.main(["get", path] + args) == 0
+        assert stdout.getvalue() == expected, stdout.getvalue()
+    with contextlib.redirect_stderr(io.StringIO()):
+        assert pyron.main(["get", path, "training.missing"]) == 1
+
+with tempfile.TemporaryDirectory() as tmp:
+    a = os.path.join(tmp, "a.ron")
+    b = os.path.join(tmp, "b.ron")
+    with open(a, "w") as f:
+        f.write("(optimizer: (lr: 0.1, momentum: 0.9), layers: [64])")
+    with open(b, "w") as f:
+        f.write("(\n    optimizer: (lr: 0.01),\n    layers: [64],\n    name: \"run\",\n)")
+    stdout = io.StringIO()
+    with contextlib.redirect_stdout(stdout):
+        assert pyron.main(["diff", a, b]) == 1
+        assert pyron.main(["diff", a, a]) == 0
+    assert stdout.getvalue() == "+ name: \"run\"\n~ optimizer.lr: 0.1 -> 0.01\n- optimizer.momentum: 0.9\n", stdout.getvalue()
+    with open(a, "w") as f:
+        f.write("(optimizer: Adam(lr: 0.1))")
+    with open(b, "w") as f:
+        f.write("(optimizer: Sgd(lr: 0.1))")
+    stdout = io.StringIO()
+    with contextlib.redirect_stdout(stdout):
+        assert pyron.main(["diff", a, b]) == 1
+    assert stdout.getvalue() == "~ optimizer: Adam(lr: 0.1) -> Sgd(lr: 0.1)\n", stdout.getvalue()
+
+with tempfile.TemporaryDirectory() as tmp:
+    paths = [os.path.join(tmp, name) for name in ["base.ron", "machine.ron", "experiment.ron", "out.ron"]]
+    for path, text in zip(paths, ["(gpus: 1, optimizer: (lr: 0.1, momentum: 0.9))", "(gpus: 8)", "(optimizer: (lr: 0.01))"]):
+        with open(path, "w") as f:
+            f.write(text)
+    assert pyron.main(["merge", *paths[:3], "--output", paths[3]]) == 0
+    assert pyron.load(paths[3]) == {"gpus": 8, "optimizer": {"lr": 0.01, "momentum": 0.9}}
+    for path, text in zip(paths, ["(optimizer: Adam(lr: 0.1, betas: (0.9, 0.99)))", "(gpus: 8)", "(optimizer: Adam(lr: 0.01))"]):
+        with open(path, "w") as f:
+            f.write(text)
+    assert pyron.main(["merge", *paths[:3], "--output", paths[3]]) == 0
+    assert pyron.load(paths[3], preserve_class_names=True) == {
+        "optimizer": {"!__name__": "Adam", "lr": 0.01, "betas": (0.9, 0.99)},
+        "gpus": 8,
+    }
+
+Point = namedtuple("Point", ["x", "y"])
+text = pyron.to_json({"origin": Point(0, 1.5), "tags": ("a", "b"), "n": None})
+assert json.loads(text) == {"origin": {"!__name__": "Point", "x": 0, "y": 1.5}, "tags": ["a", "b"], "n": None}, text
+assert pyron.from_json(text) == json.loads(text)
+assert json.loads(pyron.to_json({2**70: 1, 1.5: 2})) == {str(2**70): 1, "1.5": 2}
+assert json.loads(pyron.to_json([2**64 - 1, -(2**63)])) == [2**64 - 1, -(2**63)]
+try:
+    pyron.to_json({"seed": 2**70 + 1})
+    assert False
+except ValueError as e:
+    assert str(2**70 + 1) in str(e), e
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "config.ron")
+    with open(path, "w") as f:
+        f.write("(optimizer: Adam(lr: 0.1))")
+    text = pyron.to_json(pathlib.Path(path), indent=2)
+    assert text == '{\n  "optimizer": {\n    "!__name__": "Adam",\n    "lr": 0.1\n  }\n}', text
+
+if hasattr(pyron, "to_yaml"):
+    text = pyron.to_yaml({"optimizer": Point(0.1, 2)})
+    assert "!Point" in text, text
+    assert pyron.from_yaml(text) == {"optimizer": {"x": 0.1, "y": 2, "!__name__": "Point"}}
+
+text = pyron.to_toml({"model": {"layers": [64, 32]}, "name": "run", "optimizer": Point(0.1, 2)})
+assert pyron.from_toml(text) == {"name": "run", "model": {"layers": [64, 32]}, "optimizer": {"!__name__": "Point", "x": 0.1, "y": 2}}, text
+try:
+    pyron.to_toml({"seed": None})
+    assert False
+except ValueError as e:
+    assert "None" in str(e) and "seed" in str(e)
+
+value = {"origin": Point(0, 1.5), "shape": (3, 4), "layers": [64, None], 1: b"raw", "big": 2**63, "bigger": 2**70 + 1, 2**70: -(2**70)}
+decoded = pyron.from_msgpack(pyron.to_msgpack(value))
+assert decoded == value, decoded
+assert type(decoded["origin"]).__name__ == "Point" and isinstance(decoded["shape"], tuple)
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "config.ron")
+    with open(path, "w") as f:
+        f.write("(optimizer: Adam(lr: 0.1), shape: (3, 4))")
+    compiled = pyron.compile(path, preserve_structs=True)
+    assert pyron.load_compiled(compiled) == pyron.load(path, preserve_structs=True)
+    with open(path, "w") as f:
+        f.write(f"(big: {2**70}, negative: {-(2**70)}, huge: {10**400})")
+    compiled = pyron.load_compiled(pyron.compile(path))
+    assert compiled == {"big": 2**70, "negative": -(2**70), "huge": 10**400}, compiled
+    assert all(isinstance(value, int) for value in compiled.values())
+    try:
+        pyron.load_compiled(pyron.to_msgpack({}))
+        assert False
+    except ValueError:
+        pass
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "config.ron")
+    with open(os.path.join(tmp, "model.ron"), "w") as f:
+        f.write("(dim: 64)")
+    with open(path, "w") as f:
+        f.write("(model: #include(\"model.ron\"), steps: 10)")
+    first = pyron.load(path, cache=True)
+    first["steps"] = 0
+    assert pyron.load(path, cache=True) == {"model": {"dim": 64}, "steps": 10}
+    with open(os.path.join(tmp, "model.ron"), "w") as f:
+        f.write("(dim: 128)")
+    os.utime(os.path.join(tmp, "model.ron"), (0, 0))
+    assert pyron.load(path, cache=True) == {"model": {"dim": 128}, "steps": 10}
+    with open(path, "w") as f:
+        f.write(f"(seed: {2**70 + 1})")
+    os.utime(path, (0, 0))
+    for _ in range(2):
+        seed = pyron.load(path, cache=True)["seed"]
+        assert seed == 2**70 + 1 and isinstance(seed, int), seed
+    with open(path, "w") as f:
+        f.write('(run_name: "interned run name", tags: ["interned run name"])')
+    os.utime(path, (1, 1))
+    first, second = (pyron.load(path, cache=True, intern_strings=True) for _ in range(2))
+    assert first["run_name"] is second["run_name"] is second["tags"][0]
+    assert next(iter(first)) is next(iter(second))
+    pyron.clear_cache()
+
+with tempfile.TemporaryDirectory() as tmp:
+    paths = []
+    for i in range(20):
+        paths.append(os.path.join(tmp, f"run{i}.ron"))
+        with open(paths[-1], "w") as f:
+            f.write(f"(run: {i}, optimizer: Adam(lr: 0.1))")
+    assert pyron.load_many(paths, threads=3) == [{"run": i, "optimizer": {"lr": 0.1}} for i in range(20)]
+    assert [config["optimizer"].lr for config in pyron.load_many(paths[:2], preserve_structs=True)] == [0.1, 0.1]
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "records.ron")
+    with open(path, "w") as f:
+        f.write("""#![enable(implicit_some)]
+// records
+[
+    (step: 0, note: "a, b]", seed: 5), /* skipped ] */
+    (step: 1, tags: [1, 2], note: r#"quote " ["#),
+    Sample(step: 2, note: 'x'),
+]
+""")
+    records = list(pyron.iter_load(path, preserve_class_names=True))
+    assert records == [
+        {"step": 0, "note": "a, b]", "seed": 5},
+        {"step": 1, "tags": [1, 2], "note": "quote \" ["},
+        {"step": 2, "note": "x", "!__name__": "Sample"},
+    ], records
+
+assert list(pyron.events(text='#![enable(implicit_some)]\nConfig(lr: 0.1, layers: [64, Some(-1)], act: ("relu", ()), extra: {"a": None})')) == [
+    ("start_struct", "Config"),
+    ("key", "lr"),
+    ("scalar", 0.1),
+    ("key", "layers"),
+    ("start_seq", None),
+    ("scalar", 64),
+    ("scalar", -1),
+    ("end_seq", None),
+    ("key", "act"),
+    ("start_tuple", None),
+    ("scalar", "relu"),
+    ("scalar", ()),
+    ("end_tuple", None),
+    ("key", "extra"),
+    ("start_map", None),
+    ("scalar", "a"),
+    ("scalar", None),
+    ("end_map", None),
+    ("end_struct", None),
+]
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "big.ron")
+    with open(path, "w") as f:
+        f.write("[\n" + "".join(f'    (id: {i}, name: "item {i}\\n"), // {i}\n' for i in range(1000)) + "]\n")
+    ids = [value for kind, value in pyron.events(pathlib.Path(path)) if kind == "scalar" and isinstance(value, int)]
+    assert ids == list(range(1000))
+    with open(path) as f:
+        assert list(pyron.events(path)) == list(pyron.events(text=f.read()))
+try:
+    list(pyron.events(text="(a: 1,\n b 2)"))
+    assert False
+except ValueError as e:
+    assert "line 2" in str(e), e
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "big.ron")
+    with open(path, "w") as f:
+        f.write('Config(name: "big", layers: [Layer(dim: 64), Layer(dim: 32)], table: {"a": (1, Some(2))}, seed: Some(5))')
+    config = pyron.load(path, lazy=True)
+    assert config.name == "Config"
+    assert config["name"] == "big" and config.seed == 5
+    assert len(config) == 4 and "layers" in config and list(config) == ["name", "layers", "table", "seed"]
+    assert len(config.layers) == 2 and config.layers[-1].dim == 32 and config.layers[0].name == "Layer"
+    assert config["table"]["a"][1] == 2 and list(config.table["a"]) == [1, 2]
+    assert config.layers.materialize() == [{"dim": 64}, {"dim": 32}]
+    assert config.materialize() == pyron.load(path)
+    assert pyron.load(path, lazy=True, preserve_structs=True).layers[0].materialize().dim == 64
+    try:
+        config["missing"]
+        assert False
+    except KeyError:
+        pass
+    try:
+        pyron.load(path, lazy=True, cache=True)
+        assert False
+    except ValueError:
+        pass
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "mapped.ron")
+    with open(path, "w") as f:
+        f.write("(values: [" + ", ".join(str(i) for i in range(10000)) + '], name: "ü")')
+    assert pyron.load(path, mmap=True) == pyron.load(path)
+    assert pyron.load(path, mmap=True, lazy=True).values[9999] == 9999
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "progress.ron")
+    with open(path, "w") as f:
+        f.write("[\n" + "".join(f'    (id: {i}, padding: "{"x" * 100}"),\n' for i in range(30000)) + "]\n")
+    size = os.path.getsize(path)
+    reported = []
+    assert len(pyron.load(path, progress=reported.append)) == 30000
+    assert len(reported) > 2 and reported == sorted(reported) and reported[-1] == size, reported
+    try:
+        pyron.load(path, mmap=True, progress=reported.append)
+        assert False
+    except ValueError as e:
+        assert "mmap" in str(e), e
+    reported = []
+    assert sum(1 for _ in pyron.iter_load(path, progress=reported.append)) == 30000
+    assert len(reported) > 2 and reported == sorted(reported) and reported[-1] >= size - 1, reported
+
+import asyncio
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "served.ron")
+    with open(path, "w") as f:
+        f.write("Server(port: 8080, hosts: [\"a\", \"b\"])")
+
+    async def load_both():
+        return await asyncio.gather(
+            pyron.load_async(path),
+            pyron.load_async(path, preserve_class_names=True),
+        )
+
+    plain, named = asyncio.run(load_both())
+    assert plain == {"port": 8080, "hosts": ["a", "b"]}
+    assert named["!__name__"] == "Server"
+
+    async def load_missing():
+        try:
+            await pyron.load_async(os.path.join(tmp, "missing.ron"))
+            assert False
+        except OSError:
+            pass
+
+    asyncio.run(load_missing())
+
+assert pyron.load(io.StringIO("Config(lr: 0.5, layers: [1, 2])"), preserve_class_names=True) == {
+    "lr": 0.5,
+    "layers": [1, 2],
+    "!__name__": "Config",
+}
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "opened.ron")
+    with open(path, "w") as f:
+        f.write("(a: 1) // comment")
+    with open(path) as f:
+        assert pyron.load(f) == {"a": 1}
+    with open(path) as f:
+        try:
+            pyron.load(f, cache=True)
+            assert False
+        except ValueError:
+            pass
+
+import sys
+
+buffer = io.StringIO()
+pyron.dump({"a": 1, "b": [1, 2]}, buffer)
+assert pyron.loads(buffer.getvalue()) == {"a": 1, "b": [1, 2]}
+with contextlib.redirect_stdout(io.StringIO()) as stdout:
+    pyron.dump((1, "x"), sys.stdout)
+assert pyron.loads(stdout.getvalue()) == (1, "x")
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = pathlib.Path(tmp) / "pathlike.ron"
+    pyron.dump({"name": "x", "sizes": (1, 2)}, path)
+    assert pyron.load(path) == {"name": "x", "sizes": (1, 2)}
+    assert pyron.load(path, lazy=True).sizes[1] == 2
+
+assert pyron.loads(b'(name: "\xc3\xbc")') == {"name": "ü"}
+assert pyron.loads(b"\xef\xbb\xbf[1, 2]") == [1, 2]
+assert pyron.loads(bytearray(b"(1, 2)")) == (1, 2)
+try:
+    pyron.loads(b"[1, \xff]")
+    assert False
+except ValueError as e:
+    assert "UTF-8" in str(e)
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "binary.ron")
+    with open(path, "wb") as f:
+        f.write(b"\xef\xbb\xbf(a: 1)")
+    with open(path, "rb") as f:
+        assert pyron.load(f) == {"a": 1}
+
+import subprocess
+
+output = subprocess.run(
+    [sys.executable, "-c", "import pyron; print(pyron.load('-'))"],
+    input="Config(lr: 0.1, steps: 10)",
+    capture_output=True,
+    text=True,
+    check=True,
+).stdout
+assert output.strip() == "{'lr': 0.1, 'steps': 10}", output
+
+import gzip
+
+with tempfile.TemporaryDirectory() as tmp:
+    for name in ["config.ron.gz", "config.ron.zst"]:
+        path = os.path.join(tmp, name)
+        pyron.dump({"records": list(range(100))}, path)
+        with open(path, "rb") as f:
+            assert not f.read().startswith(b"(")
+        assert pyron.load(path) == {"records": list(range(100))}
+        assert pyron.load(path, mmap=True) == {"records": list(range(100))}
+        assert pyron.load(path, progress=lambda n: None) == {"records": list(range(100))}
+    path = os.path.join(tmp, "records.ron.gz")
+    with gzip.open(path, "wt") as f:
+        f.write("[(step: 0), (step: 1)]")
+    assert list(pyron.iter_load(path)) == [{"step": 0}, {"step": 1}]
+    assert [value for kind, value in pyron.events(pathlib.Path(path)) if kind == "scalar"] == [0, 1]
+
+opened = []
+
+
+def open_memory(url):
+    opened.append(url)
+    return io.StringIO({"memory://base.ron": "(lr: 0.1)"}[url])
+
+
+pyron.register_opener("memory", open_memory)
+pyron.register_opener("bytes", lambda url: b"[1, 2]")
+assert pyron.load("memory://base.ron", preserve_class_names=True) == {"lr": 0.1}
+assert pyron.load("bytes://anything") == [1, 2]
+assert opened == ["memory://base.ron"]
+pyron.register_opener("memory", None)
+try:
+    pyron.load("memory://base.ron")
+    assert False
+except OSError:
+    pass
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "atomic.ron")
+    pyron.dump({"version": 1}, path, atomic=True)
+    pyron.dump({"version": 2}, path, atomic=True)
+    assert pyron.load(path) == {"version": 2}
+    assert os.listdir(tmp) == ["atomic.ron"]
+    try:
+        pyron.dump(object(), path, atomic=True)
+        assert False
+    except ValueError:
+        pass
+    assert pyron.load(path) == {"version": 2}
+    pyron.dump([1], os.path.join(tmp, "atomic.ron.gz"), atomic=True)
+    assert pyron.load(os.path.join(tmp, "atomic.ron.gz")) == [1]
+    if os.name == "posix":
+        os.chmod(path, 0o600)
+        pyron.dump({"version": 3}, path, atomic=True)
+        assert os.stat(path).st_mode & 0o777 == 0o600
+        pyron.edit(path, {"version": 4})
+        assert os.stat(path).st_mode & 0o777 == 0o600
+
+from concurrent.futures import ThreadPoolExecutor
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "shared.ron")
+    with ThreadPoolExecutor(8) as executor:
+        list(executor.map(lambda version: pyron.dump({"version": version}, path, atomic=True), range(64)))
+    assert pyron.load(path)["version"] in range(64)
+    assert os.listdir(tmp) == ["shared.ron"]
+
+with tempfile.TemporaryDirectory() as tmp:
+    for name in ["metrics.ron", "metrics.ron.gz"]:
+        path = os.path.join(tmp, name)
+        pyron.append_record(path, {"step": 0, "loss": 1.5, "note": "a\nb"})
+        pyron.append_record(path, Optimizer(lr=0.1, warmup=[1.0, 2.0]))
+        if name == "metrics.ron":
+            with open(path) as f:
+                lines = f.read().splitlines()
+            assert len(lines) == 2, lines
+            assert pyron.loads(lines[0]) == {"step": 0, "loss": 1.5, "note": "a\nb"}
+            assert pyron.loads(lines[1], preserve_class_names=True) == {"lr": 0.1, "warmup": [1.0, 2.0], "!__name__": "Optimizer"}
+
+with tempfile.TemporaryDirectory() as tmp:
+    for name in ["events.ron", "events.ron.zst"]:
+        path = os.path.join(tmp, name)
+        for step in range(3):
+            pyron.append_record(path, Optimizer(lr=step / 10, warmup=None))
+        records = pyron.iter_records(path, preserve_structs=True)
+        assert [record.lr for record in records] == [0.0, 0.1, 0.2]
+    path = os.path.join(tmp, "log.ron")
+    with open(path, "w") as f:
+        f.write("// header\n(a: 1)\n\n(a: 2\n")
+    records = pyron.iter_records(path)
+    assert next(records) == {"a": 1}
+    try:
+        next(records)
+        assert False
+    except ValueError as e:
+        assert "log.ron:4" in str(e), e
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "bundle.ron")
+    bundle = [{"config": {"lr": 0.1}}, [0.5, 0.25], {"note": "a\n---\nb"}]
+    pyron.dump_all(bundle, path)
+    assert pyron.load_all(path) == bundle
+    with open(path, "w") as f:
+        f.write('// config\nConfig(lr: 0.1)\n---\n// no value\n---  \n[1, 2] // results\n---\nr#"\n---\n"#\n')
+    assert pyron.load_all(path, preserve_class_names=True) == [{"lr": 0.1, "!__name__": "Config"}, [1, 2], "\n---\n"]
+
+with tempfile.TemporaryDirectory() as tmp:
+    assert pyron.load_dir(tmp) == {}
+    for name, text in [
+        ("10-base.ron", "(model: (dim: 64, layers: 2), tags: [\"base\"])"),
+        ("20-machine.ron", "(model: (dim: 128), tags: [\"gpu\"])"),
+        ("30-env.ron.gz", None),
+        ("notes.txt", "not ron"),
+    ]:
+        if text is not None:
+            with open(os.path.join(tmp, name), "w") as f:
+                f.write(text)
+    pyron.dump({"env": "prod"}, os.path.join(tmp, "30-env.ron.gz"))
+    os.mkdir(os.path.join(tmp, "sub.ron"))
+    assert pyron.load_dir(tmp) == {"model": {"dim": 128, "layers": 2}, "tags": ["gpu"], "env": "prod"}
+    assert pyron.load_dir(pathlib.Path(tmp), lists="concat")["tags"] == ["base", "gpu"]
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "service.ron")
+    with open(path, "w") as f:
+        f.write('(db: (host: "localhost", port: 5432), workers: 2, url: "${db.host}:${db.port}")')
+    with open(os.path.join(tmp, "service.prod.ron"), "w") as f:
+        f.write('(db: (host: "db.internal"), workers: 16)')
+    assert pyron.load(path, profile="prod") == {
+        "db": {"host": "db.internal", "port": 5432},
+        "workers": 16,
+        "url": "${db.host}:${db.port}",
+    }
+    assert pyron.load(path, profile="prod", interpolate_refs=True)["url"] == "db.internal:5432"
+    assert pyron.load(path, profile="prod", cache=True)["workers"] == 16
+    assert pyron.load(path, cache=True)["workers"] == 2
+    pyron.clear_cache()
+    try:
+        pyron.load(path, profile="staging")
+        assert False
+    except OSError:
+        pass
+
+import time
+
+
+def wait_for(condition, timeout=5.0):
+    deadline = time.monotonic() + timeout
+    while not condition() and time.monotonic() < deadline:
+        time.sleep(0.01)
+    return condition()
+
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "live.ron")
+    included = os.path.join(tmp, "limits.ron")
+    with open(included, "w") as f:
+        f.write("(rps: 10)")
+    with open(path, "w") as f:
+        f.write('(name: "live", limits: #include("limits.ron"))')
+    values, errors = [], []
+    with pyron.watch(path, values.append, interval=0.02, on_error=errors.append):
+        with open(included, "w") as f:
+            f.write("(rps: 20)")
+        os.utime(included, (time.time() + 10, time.time() + 10))
+        assert wait_for(lambda: values), "no reload after changing an include"
+        assert values[-1] == {"name": "live", "limits": {"rps": 20}}
+        with open(path, "w") as f:
+            f.write("(name: ")
+        os.utime(path, (time.time() + 20, time.time() + 20))
+        assert wait_for(lambda: errors), "no error for an invalid file"
+        assert isinstance(errors[0], ValueError)
+    count = len(values)
+    os.utime(path, (time.time() + 30, time.time() + 30))
+    time.sleep(0.1)
+    assert len(values) == count
+    for interval in [0, -1, float("nan")]:
+        try:
+            pyron.watch(path, values.append, interval=interval)
+            assert False, interval
+        except ValueError as e:
+            assert "interval" in str(e), e
+
+
+@dataclass(frozen=True)
+class Credentials:
+    user: str
+    api_key: str
+
+
+secrets = {
+    "service": {"url": "https://example.com", "token": "abc123"},
+    "credentials": Credentials(user="admin", api_key="hunter2"),
+    "backends": [{"token": "t1"}, {"token": "t2"}],
+    "optimizer": Optimizer(lr="0.1", warmup="x"),
+}
+redacted = pyron.loads(pyron.to_string(secrets, redact=["*.token", "credentials.api_key", "optimizer.lr"]))
+assert redacted["service"] == {"url": "https://example.com", "token": "***"}
+assert redacted["credentials"] == {"user": "admin", "api_key": "***"}
+assert redacted["backends"] == [{"token": "***"}, {"token": "***"}]
+assert redacted["optimizer"] == {"lr": "***", "warmup": "x"}
+assert pyron.loads(pyron.to_string(secrets, redact=["backends[1].token"]))["backends"] == [{"token": "t1"}, {"token": "***"}]
+assert pyron.loads(pyron.to_string(secrets, redact=["backends[*].token"]))["backends"] == [{"token": "***"}, {"token": "***"}]
+assert pyron.loads(pyron.to_string(secrets, redact=["backends[*]"]))["backends"] == ["***", "***"]
+assert pyron.loads(pyron.to_string(secrets, redact=["[bs]ervice.token"]))["service"]["token"] == "***"
+assert secrets["service"]["token"] == "abc123"
+assert secrets["credentials"].api_key == "hunter2"
+redacted = pyron.loads(
+    pyron.to_string(secrets, redact=lambda path, value: isinstance(value, str) and value.startswith("t"))
+)
+assert redacted["backends"] == [{"token": "***"}, {"token": "***"}]
+assert redacted["service"]["token"] == "abc123"
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "secrets.ron")
+    pyron.dump(secrets, path, redact=["service"])
+    assert pyron.load(path)["service"] == "***"
+text = pyron.to_string({"db": {"kind": "Postgres", "password": "hunter2"}}, tag_key="kind", redact=["db.*"])
+assert pyron.loads(text, preserve_class_names=True) == {"db": {"!__name__": "Postgres", "password": "***"}}, text
+
+
+def check_positive(value):
+    if value <= 0:
+        raise ValueError("must be positive")
+    return True
+
+
+validators = {"training.lr": lambda v: 0 < v < 1, "*.batch_size": check_positive}
+config = "(training: (lr: 0.1, batch_size: 32), eval: (batch_size: 64))"
+assert pyron.loads(config, validators=validators)["training"]["lr"] == 0.1
+try:
+    pyron.loads("(training: (lr: 3.0, batch_size: 0), eval: (batch_size: -1))", validators=validators)
+    assert False
+except pyron.ValidationError as e:
+    assert sorted(e.errors) == [
+        ("eval.batch_size", "must be positive"),
+        ("training.batch_size", "must be positive"),
+        ("training.lr", "3.0 failed validation"),
+    ], e.errors
+    assert "3 validation errors" in str(e)
+assert issubclass(pyron.ValidationError, ValueError)
+tagged = pyron.loads(
+    "(optimizer: Adam(lr: 0.1, eps: 1e-8))",
+    preserve_class_names=True,
+    tag_key="kind",
+    validators={"optimizer.*": lambda v: isinstance(v, float)},
+)
+assert tagged["optimizer"]["kind"] == "Adam"
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "train.ron")
+    with open(path, "w") as f:
+        f.write(config)
+    assert pyron.load(path, validators={"eval.batch_size": lambda v: v == 64})["eval"]["batch_size"] == 64
+    try:
+        pyron.load(path, validators={"eval.batch_size": lambda v: v == 32})
+        assert False
+    except pyron.ValidationError as e:
+        assert e.errors == [("eval.batch_size", "64 failed validation")]
+
+import warnings
+
+
+def rename_lr(config):
+    config["learning_rate"] = config.pop("lr")
+    return config
+
+
+def add_warmup(config):
+    config["warmup"] = 0
+    return config
+
+
+pyron.register_migration(1, rename_lr)
+pyron.register_migration(2, add_warmup)
+migrated, applied = pyron.migrate({"version": 1, "lr": 0.1})
+assert migrated == {"version": 3, "learning_rate": 0.1, "warmup": 0}
+assert applied == [1, 2]
+assert pyron.migrate({"version": 3, "warmup": 0}) == ({"version": 3, "warmup": 0}, [])
+assert pyron.migrate({"lr": 0.1}) == ({"lr": 0.1}, [])
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "old.ron")
+    with open(path, "w") as f:
+        f.write("(version: 2, learning_rate: 0.3)")
+    assert pyron.load(path) == {"version": 2, "learning_rate": 0.3}
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter("always")
+        assert pyron.load(path, migrate=True) == {"version": 3, "learning_rate": 0.3, "warmup": 0}
+    assert len(caught) == 1
+    assert "migrated from version 2 to 3 (2 -> 3)" in str(caught[0].message)
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter("always")
+        pyron.loads("(version: 3)", migrate=True)
+    assert not caught
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter("always")
+        with open(path, "w") as f:
+            f.write("Config(version: 2, learning_rate: 0.3)")
+        pyron.register_migration(2, lambda config: setattr(config, "warmup", 0) or config)
+        config = pyron.load(path, preserve_structs=True, mutable_structs=True, migrate=True)
+        assert (config.version, config.warmup) == (3, 0)
+        pyron.register_migration(2, lambda config: config._replace(learning_rate=0.1))
+        config = pyron.load(path, preserve_structs=True, migrate=True)
+        assert (config.__class__.__name__, config.version, config.learning_rate) == ("Config", 3, 0.1)
+        pyron.register_migration(2, add_warmup)
+pyron.register_migration(1, None)
+pyron.register_migration(2, None)
+assert pyron.migrate({"version": 1, "lr": 0.1}) == ({"version": 1, "lr": 0.1}, [])
+
+import itertools
+
+configs = pyron.expand_sweeps(
+    text="""
+    (
+        lr: @sweep([1e-3, 3e-4]), // learning rates
+        model: (layers: @sweep([2, 4, /* deep */ 8,]), dropout: 0.1),
+        schedule: @sweep([Cosine(warmup: 100), Linear(steps: 10)]),
+    )
+    """
+)
+assert len(configs) == 12
+expected = [
+    {"lr": lr, "model": {"layers": layers, "dropout": 0.1}, "schedule": schedule}
+    for lr, layers, schedule in itertools.product(
+        [1e-3, 3e-4], [2, 4, 8], [{"warmup": 100}, {"steps": 10}]
+    )
+]
+assert configs == expected, configs
+assert pyron.expand_sweeps(text="(lr: 0.1)") == [{"lr": 0.1}]
+for invalid in ["(lr: @sweep([]))", "(lr: @sweep(1))", "(lr: @sweep([1, 2))"]:
+    try:
+        pyron.expand_sweeps(text=invalid)
+        assert False, invalid
+    except ValueError as e:
+        assert "Invalid @sweep on line 1" in str(e)
+with tempfile.TemporaryDirectory() as tmp:
+    with open(os.path.join(tmp, "base.ron"), "w") as f:
+        f.write("(seed: 1)")
+    path = os.path.join(tmp, "sweep.ron")
+    with open(path, "w") as f:
+        f.write('(base: #include("base.ron"), lr: @sweep([0.1, 0.2]))')
+    configs = pyron.expand_sweeps(pathlib.Path(path))
+    assert configs == [{"base": {"seed": 1}, "lr": 0.1}, {"base": {"seed": 1}, "lr": 0.2}]
+    assert pyron.expand_sweeps(path) == configs
+    try:
+        pyron.expand_sweeps()
+        assert False
+    except TypeError as e:
+        assert "text=" in str(e), e
+
+base = {"optimizer": {"lr": 0.1, "name": "adam"}, "batch_size": 16}
+runs = pyron.grid(base, {"optimizer.lr": [1e-3, 3e-4], "batch_size": [32, 64], "optimizer.name": ["sgd"]})
+assert [name for name, _ in runs] == [
+    "optimizer.lr=0.001,batch_size=32,optimizer.name=sgd",
+    "optimizer.lr=0.001,batch_size=64,optimizer.name=sgd",
+    "optimizer.lr=0.0003,batch_size=32,optimizer.name=sgd",
+    "optimizer.lr=0.0003,batch_size=64,optimizer.name=sgd",
+]
+assert runs[3][1] == {"optimizer": {"lr": 3e-4, "name": "sgd"}, "batch_size": 64}
+assert base == {"optimizer": {"lr": 0.1, "name": "adam"}, "batch_size": 16}
+assert pyron.grid(base, {}) == [("", base)]
+assert pyron.grid(base, {"batch_size": []}) == []
+
+
+@dataclass
+class ArgsOptimizer:
+    lr: float = field(default=0.1, metadata={"doc": "Learning rate"})
+    name: str = "adam"
+
+
+@dataclass
+class TrainConfig:
+    optimizer: ArgsOptimizer
+    steps: int
+    layers: List[int]
+    compile: bool = False
+
+
+parser = pyron.argparser(TrainConfig, prog="train")
+help_text = parser.format_help()
+assert "--optimizer.lr float" in help_text and "Learning rate" in help_text
+assert "--layers List[int]" in help_text
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "train.ron")
+    with open(path, "w") as f:
+        f.write("(optimizer: (lr: 0.1, name: \"adam\"), steps: 100, layers: [64])")
+    args = parser.parse_args([path, "--optimizer.lr", "3e-4", "--layers", "[1, 2, 3]", "--compile", "true"])
+    assert pyron.from_args(args) == {
+        "optimizer": {"lr": 3e-4, "name": "adam"},
+        "steps": 100,
+        "layers": [1, 2, 3],
+        "compile": True,
+    }
+    args = parser.parse_args([path, "--optimizer.name", "123"])
+    assert pyron.from_args(args)["optimizer"]["name"] == "123"
+    with contextlib.redirect_stderr(io.StringIO()):
+        try:
+            parser.parse_args([path, "--steps", "many"])
+            assert False
+        except SystemExit:
+            pass
+
+from typing import Dict, Optional, Tuple
+
+
+@dataclass
+class CoercedConfig:
+    optimizer: ArgsOptimizer
+    layers: List[int]
+    compile: bool
+    tag: Optional[str]
+    shape: Tuple[int, str]
+    weights: Dict[str, float]
+
+
+base = {
+    "optimizer": {"lr": 0.1, "name": "adam"},
+    "layers": [1],
+    "compile": False,
+    "tag": None,
+    "shape": (1, "a"),
+}
+overrides = {
+    "optimizer.lr": "3e-4",
+    "optimizer.name": "123",
+    "layers": "[1,2,3]",
+    "compile": "true",
+    "tag": "None",
+    "shape[1]": "7",
+    "weights.a": "2",
+}
+result = pyron.apply_overrides(base, overrides, schema=CoercedConfig)
+assert result["optimizer"] == {"lr": 3e-4, "name": "123"}
+assert result["layers"] == [1, 2, 3]
+assert result["compile"] is True
+assert result["tag"] is None
+assert result["shape"] == (1, "7")
+assert result["weights"] == {"a": 2}
+assert pyron.apply_overrides(base, {"tag": "v1"}, schema=CoercedConfig)["tag"] == "v1"
+for path, text, message in [
+    ("optimizer.lr", "fast", "Invalid override of optimizer.lr: `fast` is not a valid float"),
+    ("layers", "[1, \"x\"]", "`[1, \"x\"]` is not a valid List[int] ([1]: expected int, found str)"),
+    ("compile", "1", "`1` is not a valid bool"),
+    ("optimizer.momentum", "0.9", "ArgsOptimizer has no field momentum"),
+]:
+    try:
+        pyron.apply_overrides(base, {path: text}, schema=CoercedConfig)
+        assert False, path
+    except ValueError as e:
+        assert message in str(e), str(e)
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "train.ron")
+    with open(path, "w") as f:
+        f.write("(optimizer: (lr: 0.1, name: \"adam\"), steps: 100, layers: [64])")
+    stderr = io.StringIO()
+    with contextlib.redirect_stderr(stderr):
+        try:
+            parser.parse_args([path, "--steps", "1.5"])
+            assert False
+        except SystemExit:
+            pass
+    assert "argument --steps: Invalid override of steps: `1.5` is not a valid int" in stderr.getvalue()
+
+import copy
+import pickle
+
+config = pyron.loads(
+    "Train(optimizer: Adam(lr: 0.1), shape: Shape(2, 3), steps: 10)",
+    preserve_structs=True,
+    mutable_structs=True,
+)
+assert isinstance(config, pyron.Record) and config.__name__ == "Train"
+assert config.optimizer.__name__ == "Adam" and config.optimizer.lr == 0.1
+assert config.shape._asdict() == {"_0": 2, "_1": 3}
+config.optimizer.lr *= 2
+config.steps = 20
+config.name = "run"
+del config.name
+assert config.optimizer.lr == 0.2 and config.steps == 20
+assert not hasattr(config, "name")
+assert repr(config.optimizer) == "Adam(lr=0.2)"
+assert config == pickle.loads(pickle.dumps(config))
+assert config == copy.deepcopy(config)
+assert config != pyron.Record("Train", **config._asdict(), extra=1)
+assert pyron.Record("Adam", lr=0.2) == config.optimizer
+assert pyron.loads(pyron.to_string(config), preserve_structs=True, mutable_structs=True) == config
+try:
+    pyron.loads("Adam(lr: 0.1)", mutable_structs=True)
+    assert False
+except ValueError:
+    pass
+
+
+@dataclass
+class OptimizerConfig:
+    lr: float
+    momentum: float = 0.9
+
+
+@dataclass
+class Point:
+    x: int
+    y: int
+
+
+pyron.register("OptimizerConfig", OptimizerConfig)
+pyron.register("Point", Point)
+config = pyron.loads("(optimizer: OptimizerConfig(lr: 0.1), origin: Point(1, 2))", preserve_structs=True)
+assert config == {"optimizer": OptimizerConfig(lr=0.1), "origin": Point(1, 2)}
+config = pyron.loads("Other(optimizer: OptimizerConfig(lr: 0.1))", preserve_structs=True, mutable_structs=True)
+assert config.optimizer == OptimizerConfig(lr=0.1)
+assert pyron.loads("OptimizerConfig(lr: 0.1)") == {"lr": 0.1}
+assert pyron.loads(pyron.to_string(OptimizerConfig(lr=0.3)), preserve_structs=True) == OptimizerConfig(lr=0.3)
+try:
+    pyron.loads("OptimizerConfig(rate: 0.1)", preserve_structs=True)
+    assert False
+except TypeError:
+    pass
+pyron.register("OptimizerConfig", None)
+pyron.register("Point", None)
+assert type(pyron.loads("OptimizerConfig(lr: 0.1)", preserve_structs=True)).__name__ == "OptimizerConfig"
+assert not isinstance(pyron.loads("OptimizerConfig(lr: 0.1)", preserve_structs=True), OptimizerConfig)
+
+points = pyron.loads("[" + ", ".join(f"P(x: {i}, y: {i})" for i in range(10000)) + "]", preserve_structs=True)
+assert len({type(point) for point in points}) == 1
+assert type(pyron.loads("P(x: 0, y: 0)", preserve_structs=True)) is type(points[0])
+assert type(pyron.loads("P(x: 0)", preserve_structs=True)) is not type(points[0])
+assert type(pyron.loads("P(0, 0)", preserve_structs=True)) is type(pyron.loads("P(1, 1)", preserve_structs=True))
+
+shape = pyron.loads("Shape(2, 3)", preserve_structs=True)
+assert shape._fields == ("_0", "_1") and tuple(shape) == (2, 3)
+assert pyron.loads("Inner(_0: 4, x: 5)", preserve_structs=True)._asdict() == {"_0": 4, "x": 5}
+for text in ["Foo(_hidden: 1)", "Foo(x: 1, _0: 2)", "Foo(class: 1)"]:
+    try:
+        pyron.loads(text, preserve_structs=True)
+        assert False, text
+    except ValueError as e:
+        assert "mutable_structs" in str(e), e
+assert pyron.loads("Foo(_hidden: 1)", preserve_structs=True, mutable_structs=True)._hidden == 1
+
+config = pyron.loads("Train(optimizer: Adam(lr: 0.1), shape: Shape(2, 3))", preserve_structs=True)
+assert pickle.loads(pickle.dumps(config)) == config
+assert type(pickle.loads(pickle.dumps(config)).optimizer) is type(config.optimizer)
+assert copy.deepcopy(config) == config
+output = subprocess.run(
+    [sys.executable, "-c", "import pickle, sys; print(repr(pickle.loads(sys.stdin.buffer.read())))"],
+    input=pickle.dumps(config),
+    capture_output=True,
+    check=True,
+).stdout.decode()
+assert output.strip() == "Train(optimizer=Adam(lr=0.1), shape=Shape(_0=2, _1=3))", output
+
+text = 'QueryResult(users: [User(name: "John", age: 30)], count: 1, origin: Point(x: 0, y: 1))'
+tagged = pyron.loads(text, preserve_class_names=True)
+assert pyron.loads(pyron.to_string(tagged), preserve_class_names=True) == tagged
+assert pyron.loads(pyron.to_string(tagged), preserve_structs=True) == pyron.loads(text, preserve_structs=True)
+assert pyron.to_string({"!__name__": "Foo"}).startswith("Foo(")
+assert pyron.loads(pyron.to_string({"!__name__": 1})) == {"!__name__": 1}
+try:
+    pyron.to_string({"!__name__": "Foo", 1: 2})
+    assert False
+except ValueError:
+    pass
+for invalid in [{"!__name__": "Foo", "my key": 1}, {"!__name__": "my struct", "x": 1}, {"!__name__": "Foo", "": 1}]:
+    try:
+        pyron.to_string(invalid)
+        assert False, invalid
+    except ValueError as e:
+        assert "not an identifier" in str(e), e
+
+tagged = pyron.loads('Run(model: Mlp(layers: 2), name: "a")', preserve_class_names=True, tag_key="__type__")
+assert tagged == {"model": {"layers": 2, "__type__": "Mlp"}, "name": "a", "__type__": "Run"}
+assert pyron.to_string(tagged, tag_key="__type__").startswith("Run(")
+assert pyron.loads(pyron.to_string(tagged, tag_key="__type__"), preserve_class_names=True, tag_key="__type__") == tagged
+assert pyron.loads(pyron.to_string(tagged)) == {"model": {"layers": 2, "__type__": "Mlp"}, "name": "a", "__type__": "Run"}
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "tagged.ron")
+    pyron.dump(tagged, path, tag_key="__type__")
+    assert pyron.load(path, preserve_class_names=True, tag_key="__type__") == tagged
+    assert pyron.load(path, preserve_class_names=True)["!__name__"] == "Run"
+
+text = 'Line(start: Point(1, 2), end: Point(3, Inner(_0: 4, x: 5)), tags: {"_0": 1})'
+assert (
+    pyron.to_string(pyron.loads(text, preserve_structs=True))
+    == """Line(
+    start: Point(
+        1,
+        2,
+    ),
+    end: Point(
+        3,
+        Inner(
+            _0: 4,
+            x: 5,
+        ),
+    ),
+    tags: {
+        "_0": 1,
+    },
+)"""
+), pyron.to_string(pyron.loads(text, preserve_structs=True))
+tagged = pyron.loads(text, preserve_class_names=True)
+assert pyron.loads(pyron.to_string(tagged), preserve_class_names=True) == tagged
+assert "Point(\n        1," in pyron.to_string(tagged)
+
+import types
+
+frozen = pyron.loads('(layers: [1, 2], optimizer: Adam(lr: 0.1), tags: {"a": [3]})', frozen=True)
+assert isinstance(frozen, types.MappingProxyType)
+assert frozen["layers"] == (1, 2) and frozen["tags"]["a"] == (3,)
+assert frozen["optimizer"] == {"lr": 0.1}
+try:
+    frozen["layers"] = []
+    assert False
+except TypeError:
+    pass
+try:
+    frozen["tags"]["b"] = 1
+    assert False
+except TypeError:
+    pass
+assert pyron.loads(pyron.to_string(frozen)) == {"layers": (1, 2), "optimizer": {"lr": 0.1}, "tags": {"a": (3,)}}
+structs = pyron.loads("Run(layers: [1, 2])", preserve_structs=True, frozen=True)
+assert type(structs).__name__ == "Run" and structs.layers == (1, 2)
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "frozen.ron")
+    with open(path, "w") as f:
+        f.write("(a: [1])")
+    assert pyron.load(path, frozen=True)["a"] == (1,)
+    assert pyron.load(path)["a"] == [1]
+try:
+    pyron.loads("A(x: 1)", preserve_structs=True, mutable_structs=True, frozen=True)
+    assert False
+except ValueError:
+    pass
+
+tuples = pyron.loads("(points: [[1, 2], [3, 4]], pair: (5, 6))", seq_type="tuple")
+assert tuples == {"points": ((1, 2), (3, 4)), "pair": (5, 6)}
+assert len({tuples["points"][0], (1, 2)}) == 1
+assert pyron.loads("[1]", seq_type="list") == [1]
+try:
+    pyron.loads("[1]", seq_type="set")
+    assert False
+except ValueError:
+    pass
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "seqs.ron")
+    with open(path, "w") as f:
+        f.write("[[1, 2], [3]]")
+    assert pyron.load(path, seq_type="tuple") == ((1, 2), (3,))
+    assert list(pyron.iter_load(path, seq_type="tuple")) == [(1, 2), (3,)]
+    path = os.path.join(tmp, "records.ron")
+    pyron.append_record(path, {"xs": [1, 2]})
+    assert list(pyron.iter_records(path, seq_type="tuple")) == [{"xs": (1, 2)}]
+
+first = pyron.loads('[(kind: "training"), (kind: "training")]', intern_strings=True)
+second = pyron.loads('(kind: "training")', intern_strings=True)
+assert first[0]["kind"] is first[1]["kind"] is second["kind"]
+assert [next(iter(record)) for record in first][0] is next(iter(second))
+assert pyron.loads('Point(x: "a")', preserve_class_names=True, intern_strings=True) == {"x": "a", "!__name__": "Point"}
+
+try:
+    import numpy
+except ImportError:
+    numpy = None
+if numpy is not None:
+    arrays = pyron.loads("(ids: [1, 2, 3], losses: [0.5, 0.25], mixed: [1, 2.0], names: [\"a\"], empty: [])", numpy_arrays=True)
+    assert arrays["ids"].dtype == numpy.int64 and arrays["ids"].tolist() == [1, 2, 3]
+    assert arrays["losses"].dtype == numpy.float64 and arrays["losses"].tolist() == [0.5, 0.25]
+    assert arrays["mixed"] == [1, 2.0] and arrays["names"] == ["a"] and arrays["empty"] == []
+    arrays["ids"][0] = 7
+    assert pyron.loads(pyron.to_string(arrays)) == {"ids": [7, 2, 3], "losses": [0.5, 0.25], "mixed": [1, 2.0], "names": ["a"], "empty": []}
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "rows.ron")
+    with open(path, "w") as f:
+        f.write('[Row(step: 0, loss: 1.5), Row(step: 1, loss: 0.5, note: "warmup done"), (step: 2, loss: 0.25)]')
+    columns = pyron.load_columns(path)
+    assert columns == {"step": [0, 1, 2], "loss": [1.5, 0.5, 0.25], "note": [None, "warmup done", None]}
+    assert list(columns) == ["step", "loss", "note"]
+    if numpy is not None:
+        columns = pyron.load_columns(path, numpy_arrays=True)
+        assert columns["step"].dtype == numpy.int64 and columns["loss"].tolist() == [1.5, 0.5, 0.25]
+        assert columns["note"] == [None, "warmup done", None]
+    with open(path, "w") as f:
+        f.write("[(step: 0), 1]")
+    try:
+        pyron.load_columns(path)
+        assert False
+    except ValueError as e:
+        assert "element 1 is not a struct" in str(e)
+
+try:
+    import pyarrow
+except ImportError:
+    pyarrow = None
+if pyarrow is not None:
+    table = pyron.to_arrow([{"step": 0, "loss": 1.5}, {"step": 1, "note": "done"}, {"step": 2, "loss": 0.5}])
+    assert table.column_names == ["step", "loss", "note"]
+    assert table.column("step").type == pyarrow.int64()
+    assert table.column("loss").to_pylist() == [1.5, None, 0.5]
+    assert table.column("note").to_pylist() == [None, "done", None]
+    with tempfile.TemporaryDirectory() as tmp:
+        path = os.path.join(tmp, "rows.ron")
+        with open(path, "w") as f:
+            f.write("[Row(step: 0), Row(step: 1)]")
+        assert pyron.to_arrow(pathlib.Path(path)).to_pydict() == {"step": [0, 1]}
+
+try:
+    pyron.to_string({"a": [1, object()]})
+    assert False
+except ValueError as e:
+    assert str(e) == "Unsupported type: object", str(e)
+
+
+@dataclass
+class Partial:
+    x: int
+
+
+partial = Partial(1)
+del partial.x
+try:
+    pyron.to_string([partial])
+    assert False
+except AttributeError:
+    pass
+assert pyron.loads(pyron.to_string((1, "a\n\"b\"", None, 2.0, True))) == (1, "a\n\"b\"", None, 2.0, True)
+assert pyron.canonicalize({"b": {2: 0, 1: 0}, "a": Partial(3)}) == """{
+    "a": Partial(
+        x: 3,
+    ),
+    "b": {
+        1: 0,
+        2: 0,
+    },
+}"""
+
+direct_source = """// comment
+Config(
+    name: "run \\"a\\"\\n", /* block /* nested */ */
+    lr: 3e-4, steps: -10, ratio: -0.5, unit: (),
+    layers: [Layer(dim: 64), Layer(dim: 32),],
+    shape: Shape(2, 3), pair: (1, "b"), extra: Some(None), flags: [true, false],
+    table: {"a": 1, 2: [], (1, 2): {}},
+)
+"""
+fallback_source = "#let unused = 0;\n" + direct_source
+for options in [{}, {"preserve_structs": True}, {"preserve_class_names": True}, {"seq_type": "tuple", "intern_strings": True}]:
+    direct = pyron.loads(direct_source, **options)
+    assert direct == pyron.loads(fallback_source, **options), options
+assert pyron.loads(direct_source)["name"] == 'run "a"\n'
+assert pyron.loads(direct_source)["table"] == {"a": 1, 2: [], (1, 2): {}}
+assert pyron.loads(direct_source, preserve_structs=True).shape == (2, 3)
+for source in ["0x1F", "-0b101", "1_000", "'c'", 'r"raw"', "(1)", "Unit", "{1: 2, 1: 3}", "[18446744073709551616, -9223372036854775809]"]:
+    assert pyron.loads(source) == pyron.loads("#let unused = 0;\n" + source), source
+assert pyron.loads("#let unused = 0;\n[0x1F, 1_000, 18446744073709551616]") == [31, 1000, 2**64]
+try:
+    pyron.loads("(a: 1", print_errors=False)
+    assert False
+except ValueError:
+    pass
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "direct.ron")
+    with open(path, "w") as f:
+        f.write(direct_source)
+    assert pyron.load(path) == pyron.loads(fallback_source)
+
+seed = 2**64 - 1
+assert pyron.to_string(seed) == "18446744073709551615"
+assert pyron.to_string({"seed": seed, "offset": -(2**100)}) == """{
+    "seed": 18446744073709551615,
+    "offset": -1267650600228229401496703205376,
+}"""
+assert pyron.loads(pyron.to_string({"seed": seed, "offset": -(2**100)})) == {"seed": seed, "offset": -(2**100)}
+lookalikes = {"seed": seed, "note": "\0num:5", "marker": "\0num:0"}
+assert pyron.loads(pyron.to_string(lookalikes)) == lookalikes
+assert pyron.loads("[9223372036854775807, 9223372036854775808, -9223372036854775809]") == [2**63 - 1, 2**63, -(2**63) - 1]
+assert pyron.to_string(["\0int:5"]) != "[\n    5,\n]"
+assert pyron.loads(pyron.to_string(["\0int:5"])) == ["\0int:5"]
+
+huge = 3**200
+assert pyron.to_string(huge) == str(huge)
+assert pyron.loads(pyron.to_string({"n": -huge, "m": [huge]})) == {"n": -huge, "m": [huge]}
+assert type(pyron.loads(str(10**40))) is int
+
+import math
+
+non_finite_source = "[NaN, inf, -inf, 1.5, 1e999]"
+loaded = pyron.loads(non_finite_source)
+assert math.isnan(loaded[0]) and loaded[1:] == [math.inf, -math.inf, 1.5, math.inf]
+assert pyron.loads(non_finite_source, non_finite="none") == [None, None, None, 1.5, None]
+assert pyron.loads("(lr: 1e999)", non_finite="none") == {"lr": None}
+for source in [non_finite_source, "(lr: 1e999)"]:
+    try:
+        pyron.loads(source, non_finite="error")
+        assert False
+    except ValueError as e:
+        assert "non_finite" in str(e)
+assert pyron.loads("[1.5, 2.0]", non_finite="error") == [1.5, 2.0]
+try:
+    pyron.loads("[1.0]", non_finite="nan")
+    assert False
+except ValueError as e:
+    assert "non_finite must be" in str(e)
+if numpy is not None:
+    assert pyron.loads("[1.0, NaN]", numpy_arrays=True, non_finite="none") == [1.0, None]
+
+assert pyron.to_string([math.inf]) == "[\n    inf,\n]"
+assert pyron.to_string({"lr": 0.5}, allow_nan=False) == '{\n    "lr": 0.5,\n}'
+for value in [math.nan, {"lr": [1.0, -math.inf]}]:
+    try:
+        pyron.to_string(value, allow_nan=False)
+        assert False
+    except ValueError as e:
+        assert "allow_nan" in str(e)
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "nan.ron")
+    try:
+        pyron.dump({"loss": math.nan}, path, allow_nan=False)
+        assert False
+    except ValueError:
+        pass
+    assert not os.path.exists(path)
+
+from decimal import Decimal
+
+prices = pyron.loads("(price: 0.1, fee: -1.10, total: 12345678901234567890.123456789, count: 3, tiny: 1e-30)", use_decimal=True)
+assert prices == {
+    "price": Decimal("0.1"),
+    "fee": Decimal("-1.10"),
+    "total": Decimal("12345678901234567890.123456789"),
+    "count": Decimal(3),
+    "tiny": Decimal("1e-30"),
+}
+assert str(prices["fee"]) == "-1.10"
+assert all(type(value) is Decimal for value in prices.values())
+assert pyron.loads("#let unused = 0;\n[0.1, 255, 0xFF]", use_decimal=True) == [Decimal("0.1"), Decimal(255), Decimal(255)]
+fallback_prices = pyron.loads("#let unused = 0;\n(fee: -1.10, total: 12345678901234567890.123456789)", use_decimal=True)
+assert fallback_prices == {"fee": Decimal("-1.10"), "total": Decimal("12345678901234567890.123456789")}
+assert str(fallback_prices["fee"]) == "-1.10"
+assert type(pyron.loads("0.1")) is float
+try:
+    pyron.loads("[1.0]", use_decimal=True, numpy_arrays=True)
+    assert False
+except ValueError:
+    pass
+
+floats = {"lr": 0.1 + 0.2, "decay": 1.0, "momentum": -0.9}
+assert pyron.to_string(floats) == '{\n    "lr": 0.30000000000000004,\n    "decay": 1.0,\n    "momentum": -0.9,\n}'
+assert pyron.to_string(floats, float_precision=3) == '{\n    "lr": 0.3,\n    "decay": 1.0,\n    "momentum": -0.9,\n}'
+assert pyron.to_string(floats, float_format="fixed", float_precision=2) == '{\n    "lr": 0.30,\n    "decay": 1.00,\n    "momentum": -0.90,\n}'
+assert pyron.to_string(floats, float_trailing_zero=False) == '{\n    "lr": 0.30000000000000004,\n    "decay": 1e0,\n    "momentum": -0.9,\n}'
+assert pyron.to_string([1e20, 2500.0, 1e300, 1.5e-300], float_trailing_zero=False) == "[\n    1e20,\n    2.5e3,\n    1e300,\n    1.5e-300,\n]"
+assert pyron.to_string([1e300, -1e20, 1e15, 1e-20]) == "[\n    1e300,\n    -1e20,\n    1000000000000000.0,\n    1e-20,\n]"
+assert pyron.to_string([1e300], float_precision=2) == "[\n    1e300,\n]"
+assert all(type(value) is float for value in pyron.loads(pyron.to_string([1e20, 2500.0, 1.0], float_trailing_zero=False)))
+assert pyron.to_string([2.4], float_format="fixed", float_precision=0) == "[\n    2.0,\n]"
+assert pyron.to_string([1.0, math.inf], float_precision=2) == "[\n    1.0,\n    inf,\n]"
+assert pyron.to_string({"n": 2**70, "x": 0.26}, float_precision=1) == '{\n    "n": 1180591620717411303424,\n    "x": 0.3,\n}'
+assert pyron.loads(pyron.to_string(floats, float_precision=3)) == {"lr": 0.3, "decay": 1.0, "momentum": -0.9}
+try:
+    pyron.to_string(1.0, float_format="fixed")
+    assert False
+except ValueError as e:
+    assert "float_precision" in str(e)
+
+hyperparameters = {"lr": 0.00001, "eps": 1e-8, "decay": 0.001, "steps": 250000.0, "zero": 0.0}
+assert pyron.to_string(hyperparameters) == '{\n    "lr": 0.00001,\n    "eps": 0.00000001,\n    "decay": 0.001,\n    "steps": 250000.0,\n    "zero": 0.0,\n}'
+assert pyron.to_string(hyperparameters, float_exponent_threshold=4) == '{\n    "lr": 1e-5,\n    "eps": 1e-8,\n    "decay": 0.001,\n    "steps": 2.5e5,\n    "zero": 0.0,\n}'
+assert pyron.to_string([1.2345e-7], float_exponent_threshold=4, float_precision=2) == "[\n    1.23e-7,\n]"
+assert pyron.to_string([-3e-6], float_exponent_threshold=4, float_format="fixed", float_precision=2) == "[\n    -3.00e-6,\n]"
+assert pyron.loads(pyron.to_string(hyperparameters, float_exponent_threshold=4)) == hyperparameters
+
+hardware = pyron.loads("(mask: 0xFF, flags: 0b1010, mode: 0o755, steps: 1_000_000, lr: 1e-3, scale: 2.50, offset: -0x10, count: 3)", preserve_numbers=True)
+assert hardware == {"mask": 255, "flags": 10, "mode": 493, "steps": 1000000, "lr": 0.001, "scale": 2.5, "offset": -16, "count": 3}
+assert isinstance(hardware["mask"], pyron.LiteralInt) and hardware["mask"].source == "0xFF"
+assert isinstance(hardware["lr"], pyron.LiteralFloat) and hardware["lr"].source == "1e-3"
+hardware["count"] += 1
+assert type(hardware["count"]) is int
+assert pyron.to_string(hardware) == """{
+    "mask": 0xFF,
+    "flags": 0b1010,
+    "mode": 0o755,
+    "steps": 1_000_000,
+    "lr": 1e-3,
+    "scale": 2.50,
+    "offset": -0x10,
+    "count": 4,
+}"""
+assert pyron.loads(pyron.to_string(hardware)) == {**hardware, "count": 4}
+assert pyron.loads("[0xFF, 1_000]") == [255, 1000]
+assert type(pyron.loads("0xFF")) is int
+assert pickle.loads(pickle.dumps(hardware["mask"])).source == "0xFF"
+fallback_hardware = pyron.loads("#let mask = 0xFF;\n(mask: $mask, lr: 1e-3, scale: 2.50, offset: -0x10, count: 3)", preserve_numbers=True)
+assert fallback_hardware == {"mask": 255, "lr": 0.001, "scale": 2.5, "offset": -16, "count": 3}
+assert [fallback_hardware[key].source for key in ["mask", "lr", "scale", "offset"]] == ["0xFF", "1e-3", "2.50", "-0x10"]
+included_hardware = pyron.loads('(device: #include("device.ron"))', include_resolver={"device.ron": "(mask: 0xFF, scale: 2.50)"}.__getitem__, preserve_numbers=True)
+assert included_hardware["device"]["mask"].source == "0xFF" and included_hardware["device"]["scale"].source == "2.50"
+
+registers = {"irq_mask": 0xFF00, "flags": 10, "mode": 493, "enabled": True, "devices": [{"mask": 15}, {"mask": -16}], "count": 3}
+assert pyron.to_string(registers, int_formats={"irq_mask": "hex", "flags": "binary", "mode": "octal", "enabled": "hex", "devices[*].mask": "hex"}) == """{
+    "irq_mask": 0xFF00,
+    "flags": 0b1010,
+    "mode": 0o755,
+    "enabled": true,
+    "devices": [
+        {
+            "mask": 0xF,
+        },
+        {
+            "mask": -0x10,
+        },
+    ],
+    "count": 3,
+}"""
+assert pyron.loads(pyron.to_string(registers, int_formats={"*": "hex"})) == registers
+assert pyron.to_string({"n": 2**70}, int_formats={"n": "hex"}) == '{\n    "n": 0x400000000000000000,\n}'
+assert registers["irq_mask"] == 0xFF00 and type(registers["irq_mask"]) is int
+try:
+    pyron.to_string(registers, int_formats={"flags": "decimal"})
+    assert False
+except ValueError as e:
+    assert "int_formats" in str(e)
+
+sizes = {"steps": 10000000, "dataset": 123456, "batch": 1024, "seed": -98765, "vocab": 10000, "big": 2**70, "lr": 1000000.0}
+assert pyron.to_string(sizes, int_separators=True) == """{
+    "steps": 10_000_000,
+    "dataset": 123_456,
+    "batch": 1024,
+    "seed": -98_765,
+    "vocab": 10_000,
+    "big": 1_180_591_620_717_411_303_424,
+    "lr": 1000000.0,
+}"""
+assert pyron.loads(pyron.to_string(sizes, int_separators=True)) == sizes
+assert pyron.to_string([-(2**63)], int_separators=True) == "[\n    -9_223_372_036_854_775_808,\n]"
+
+glyphs = pyron.loads("(separator: ',', comment: '#', name: \"x\")", preserve_chars=True)
+assert glyphs == {"separator": pyron.Char(","), "comment": pyron.Char("#"), "name": "x"}
+assert str(glyphs["separator"]) == "," and glyphs["comment"].value == "#"
+assert pyron.loads("'\\n'", preserve_chars=True) == pyron.Char("\n")
+assert repr(pyron.Char("c")) == "Char('c')"
+assert repr(pyron.Char("'")) == 'Char("\'")'
+assert repr(pyron.Char("\x00")) == "Char('\\x00')"
+assert repr(pyron.Char("é")) == "Char('é')"
+assert pyron.to_string(glyphs) == """{
+    "separator": ',',
+    "comment": '#',
+    "name": "x",
+}"""
+assert pyron.loads(pyron.to_string(glyphs), preserve_chars=True) == glyphs
+assert pyron.loads("'c'") == "c"
+assert pyron.to_string("c") == '"c"'
+assert len({pyron.Char("a"), pyron.Char("a")}) == 1
+assert pickle.loads(pickle.dumps(pyron.Char("a"))) == pyron.Char("a")
+try:
+    pyron.Char("ab")
+    assert False
+except ValueError:
+    pass
+
+optional = pyron.loads("(timeout: Some(30), retries: None, name: Some(\"a\"), nested: Some(Some([1])), plain: 5)", preserve_options=True)
+assert optional == {"timeout": pyron.Some(30), "retries": None, "name": pyron.Some("a"), "nested": pyron.Some(pyron.Some([1])), "plain": 5}
+assert optional["timeout"].value == 30 and repr(optional["name"]) == "Some('a')"
+assert pyron.to_string(optional) == """{
+    "timeout": Some(30),
+    "retries": None,
+    "name": Some("a"),
+    "nested": Some(Some([
+        1,
+    ])),
+    "plain": 5,
+}"""
+assert pyron.loads(pyron.to_string(optional), preserve_options=True) == optional
+assert pyron.loads("#let unused = 0;\nSome(1)", preserve_options=True) == pyron.Some(1)
+assert pyron.loads("Some(1)") == 1
+assert pyron.Some(1) != pyron.Some(2) and pyron.Some(1) != 1
+assert pickle.loads(pickle.dumps(pyron.Some((1, 2)))) == pyron.Some((1, 2))
+
+settings = {"timeout": pyron.Some(30), "retries": None, "name": pyron.Some("a")}
+assert pyron.to_string(settings, implicit_some=True) == """#![enable(implicit_some)]
+{
+    "timeout": 30,
+    "retries": None,
+    "name": "a",
+}"""
+assert pyron.to_string(settings).count("Some(") == 2
+
+extended_source = """#![enable(implicit_some)]
+#![enable(unwrap_newtypes, implicit_some)]
+// training
+(timeout: 30, name: Some("a"))
+"""
+assert pyron.extensions(text=extended_source) == ["implicit_some", "unwrap_newtypes"]
+assert pyron.extensions(text="(a: 1)") == []
+assert pyron.parse_document(text=extended_source).extensions == ["implicit_some", "unwrap_newtypes"]
+assert pyron.loads(extended_source) == {"timeout": 30, "name": "a"}
+assert pyron.loads(extended_source, preserve_options=True) == {"timeout": 30, "name": pyron.Some("a")}
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "extended.ron")
+    with open(path, "w") as f:
+        f.write(extended_source)
+    config = pyron.load(path, preserve_options=True)
+    config["timeout"] = 60
+    pyron.dump(config, path, extensions=pyron.extensions(pathlib.Path(path)))
+    with open(path) as f:
+        assert f.read() == """#![enable(implicit_some, unwrap_newtypes)]
+{
+    "timeout": 60,
+    "name": "a",
+}"""
+    assert pyron.extensions(pathlib.Path(path)) == ["implicit_some", "unwrap_newtypes"]
+    assert pyron.extensions(path) == ["implicit_some", "unwrap_newtypes"]
+    assert pyron.load(path) == {"timeout": 60, "name": "a"}
+assert pyron.to_string(1, extensions=["unwrap_newtypes"], implicit_some=True) == "#![enable(unwrap_newtypes, implicit_some)]\n1"
+try:
+    pyron.to_string(1, extensions=["implicit_everything"])
+    assert False
+except ValueError as e:
+    assert "implicit_everything" in str(e)
+
+newtype_source = "(timeout: Seconds(5.0), point: Point(1, 2), pair: (1, 2), single: Some(Meters(3)))"
+assert pyron.loads(newtype_source) == {"timeout": (5.0,), "point": (1, 2), "pair": (1, 2), "single": (3,)}
+assert pyron.loads(newtype_source, unwrap_newtypes=True) == {"timeout": 5.0, "point": (1, 2), "pair": (1, 2), "single": 3}
+assert pyron.loads("#let unused = 0;\n" + newtype_source, unwrap_newtypes=True) == pyron.loads(newtype_source, unwrap_newtypes=True)
+assert pyron.loads(newtype_source, preserve_structs=True, unwrap_newtypes=True)["point"].__class__.__name__ == "Point"
+newtypes = pyron.loads(newtype_source, preserve_structs=True)
+assert pyron.to_string(newtypes["timeout"]) == "Seconds(\n    5.0,\n)"
+assert pyron.to_string(newtypes, unwrap_newtypes=True) == """#![enable(unwrap_newtypes)]
+{
+    "timeout": 5.0,
+    "point": Point(
+        1,
+        2,
+    ),
+    "pair": (
+        1,
+        2,
+    ),
+    "single": 3,
+}"""
+
+unit_source = "(state: Paused, previous: Running, history: [Running, Paused], count: 2)"
+units = pyron.loads(unit_source, preserve_units=True)
+assert units == {
+    "state": pyron.Unit("Paused"),
+    "previous": pyron.Unit("Running"),
+    "history": [pyron.Unit("Running"), pyron.Unit("Paused")],
+    "count": 2,
+}
+assert units["state"] != units["previous"]
+assert units["state"].name == "Paused"
+assert repr(units["state"]) == "Unit('Paused')"
+assert {pyron.Unit("Paused"), pyron.Unit("Paused")} == {pyron.Unit("Paused")}
+assert pickle.loads(pickle.dumps(units["state"])) == units["state"]
+assert pyron.loads("#let unused = 0;\n" + unit_source, preserve_units=True) == units
+assert pyron.to_string(units) == """{
+    "state": Paused,
+    "previous": Running,
+    "history": [
+        Running,
+        Paused,
+    ],
+    "count": 2,
+}"""
+assert pyron.loads(pyron.to_string(units), preserve_units=True) == units
+assert pyron.to_string(pyron.Unit("Paused")) == "Paused"
+try:
+    pyron.Unit("not a name")
+    assert False
+except ValueError as e:
+    assert "identifier" in str(e)
+for source in ["(empty: Adam(), bare: Adam, keys: {Paused: 1})", "#let unused = 0;\n(empty: Adam(), bare: Adam, keys: {Paused: 1})"]:
+    mixed = pyron.loads(source, preserve_units=True)
+    assert not isinstance(mixed["empty"], pyron.Unit), source
+    assert mixed["bare"] == pyron.Unit("Adam") and mixed["keys"] == {pyron.Unit("Paused"): 1}, source
+for source in ["(lr: inf, loss: NaN, state: Paused)", "#let unused = 0;\n(lr: inf, loss: NaN, state: Paused)"]:
+    floats = pyron.loads(source, preserve_units=True)
+    assert floats["lr"] == math.inf and math.isnan(floats["loss"]), source
+    assert floats["state"] == pyron.Unit("Paused"), source
+assert pyron.from_msgpack(pyron.to_msgpack(units)) == units
+assert json.loads(pyron.to_json(units["state"])) == {"!__name__": "Paused"}
+if hasattr(pyron, "to_yaml"):
+    assert "!Paused" in pyron.to_yaml(units), pyron.to_yaml(units)
+
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "typed.ron")
+    with open(path, "w") as f:
+        f.write("(lr: 0.1, steps: 0x10, sep: ',', name: Some(\"run\"), state: Paused, ids: [1, 2])")
+    options = [
+        ({"use_decimal": True}, lambda config: isinstance(config["lr"], Decimal)),
+        ({"preserve_numbers": True}, lambda config: config["steps"].source == "0x10"),
+        ({"preserve_chars": True}, lambda config: config["sep"] == pyron.Char(",")),
+        ({"preserve_options": True}, lambda config: config["name"] == pyron.Some("run")),
+        ({"preserve_units": True}, lambda config: config["state"] == pyron.Unit("Paused")),
+    ]
+    if numpy is not None:
+        options.append(({"numpy_arrays": True}, lambda config: config["ids"].dtype == numpy.int64))
+    for kwargs, check in options:
+        for _ in range(2):
+            assert check(pyron.load(path, cache=True, **kwargs)), kwargs
+    pyron.clear_cache()
+
+variant_source = "(optimizer: Adam(lr: 0.1), fallback: Sgd(lr: 0.1), origin: Point(1, 2), plain: (lr: 0.1))"
+assert pyron.loads(variant_source) == {"optimizer": {"lr": 0.1}, "fallback": {"lr": 0.1}, "origin": (1, 2), "plain": {"lr": 0.1}}
+external = pyron.loads(variant_source, enum_repr="external")
+assert external == {
+    "optimizer": {"Adam": {"lr": 0.1}},
+    "fallback": {"Sgd": {"lr": 0.1}},
+    "origin": {"Point": (1, 2)},
+    "plain": {"lr": 0.1},
+}
+assert pyron.loads("#let unused = 0;\n" + variant_source, enum_repr="external") == external
+assert pyron.to_string(external["optimizer"], enum_repr="external") == "Adam(\n    lr: 0.1,\n)"
+assert pyron.loads(pyron.to_string(external, enum_repr="external"), enum_repr="external") == external
+adjacent = pyron.loads(variant_source, enum_repr="adjacent", tag_key="type")
+assert adjacent["optimizer"] == {"type": "Adam", "content": {"lr": 0.1}}
+assert adjacent["origin"] == {"type": "Point", "content": (1, 2)}
+assert pyron.to_string(adjacent, enum_repr="adjacent", tag_key="type") == pyron.to_string(external, enum_repr="external")
+assert pyron.loads(pyron.to_string(adjacent, enum_repr="adjacent", tag_key="type"), enum_repr="adjacent", tag_key="type") == adjacent
+# Only capitalized identifiers are variant names, so other single-entry dicts stay maps.
+assert pyron.to_string({"lr": {"x": 1}}, enum_repr="external") == pyron.to_string({"lr": {"x": 1}})
+# Maps with a single capitalized key can't be told apart from variants, and are written as structs.
+assert pyron.to_string({"Linux": {"cc": "gcc"}}, enum_repr="external") == 'Linux(\n    cc: "gcc",\n)'
+spaced = {"Linux": {"c compiler": "gcc"}}
+assert pyron.loads(pyron.to_string(spaced, enum_repr="external"), enum_repr="external") == spaced
+payload = pyron.loads(variant_source, enum_repr="adjacent", tag_key="type", content_key="value")
+assert payload["optimizer"] == {"type": "Adam", "value": {"lr": 0.1}}
+assert pyron.to_string(payload, enum_repr="adjacent", tag_key="type", content_key="value") == pyron.to_string(external, enum_repr="external")
+assert pyron.to_string(payload, enum_repr="adjacent", tag_key="type") != pyron.to_string(external, enum_repr="external")
+try:
+    pyron.loads(variant_source, enum_repr="internal")
+    assert False
+except ValueError as e:
+    assert "enum_repr" in str(e)
+try:
+    pyron.loads(variant_source, enum_repr="external", preserve_structs=True)
+    assert False
+except ValueError as e:
+    assert "enum_repr" in str(e)
+
+comma_config = {"layers": [64, 32], "optimizer": {"lr": 0.1}, "shape": (3, 4), "note": "a, b)"}
+assert pyron.to_string(comma_config, trailing_commas=False) == """{
+    "layers": [
+        64,
+        32
+    ],
+    "optimizer": {
+        "lr": 0.1
+    },
+    "shape": (3, 4),
+    "note": "a, b)"
+}"""
+assert pyron.loads(pyron.to_string(comma_config, trailing_commas=False)) == comma_config
+assert pyron.to_string(Point(1, 2), trailing_commas=False) == "Point(\n    x: 1,\n    y: 2\n)"
+
+inline_config = {
+    "layers": [64, 32],
+    "shape": (3, 4),
+    "optimizer": {"lr": 0.1, "betas": [0.9, 0.999]},
+    "stages": [{"name": "warmup", "steps": 1000}, {"name": "decay", "steps": 100000}],
+}
+assert pyron.to_string(inline_config, max_width=60) == """{
+    "layers": [64, 32],
+    "shape": (3, 4),
+    "optimizer": {"lr": 0.1, "betas": [0.9, 0.999]},
+    "stages": [
+        {"name": "warmup", "steps": 1000},
+        {"name": "decay", "steps": 100000},
+    ],
+}"""
+assert pyron.to_string(inline_config, max_width=60, trailing_commas=False).endswith("""        {"name": "decay", "steps": 100000}
+    ]
+}""")
+assert pyron.to_string({"a": [1, 2]}, max_width=13) == '{"a": [1, 2]}'
+assert pyron.to_string({"a": [1, 2]}, max_width=12) == pyron.to_string({"a": [1, 2]})
+assert pyron.to_string({"a": [1, 2]}, max_width=16) == '{"a": [1, 2]}'
+assert pyron.to_string({"ab": [1, 2], "c": 3}, max_width=18) == '{\n    "ab": [1, 2],\n    "c": 3,\n}'
+assert pyron.to_string(Point(1, 2), max_width=100) == "Point(x: 1, y: 2)"
+assert pyron.loads(pyron.to_string(inline_config, max_width=60)) == inline_config
+tabbed = {"a": [1, 2], "bc": [3, 4]}
+assert pyron.to_string(tabbed, max_width=16, indent="\t") == '{\n\t"a": [1, 2],\n\t"bc": [\n\t\t3,\n\t\t4,\n\t],\n}'
+assert pyron.to_string(tabbed, max_width=16, indent="\t") == pyron.to_string(tabbed, max_width=16).replace("    ", "\t")
+commented = pyron.to_string(inline_config, max_width=60, comments={"optimizer.lr": "learning rate"})
+assert commented.startswith("""{
+    "layers": [64, 32],
+    "shape": (3, 4),
+    "optimizer": {
+        // learning rate
+        "lr": 0.1,
+        "betas": [0.9, 0.999],
+    },
+"""), commented
+assert pyron.loads(commented) == inline_config
+text = pyron.to_string({"schedule": Schedule(warmup=100)}, field_docs=True, max_width=100)
+assert "        // number of warmup steps\n        warmup: 100,\n" in text, text
+
+aligned_config = {"lr": 0.1, "momentum": 0.9, "layers": [{"dim": 64, "dropout": 0.1}], "name": "run"}
+assert pyron.to_string(aligned_config, align_fields=True) == """{
+    "lr":       0.1,
+    "momentum": 0.9,
+    "layers":   [
+        {
+            "dim":     64,
+            "dropout": 0.1,
+        },
+    ],
+    "name":     "run",
+}"""
+assert pyron.to_string(aligned_config, align_fields=True, max_width=60) == """{
+    "lr":       0.1,
+    "momentum": 0.9,
+    "layers":   [{"dim": 64, "dropout": 0.1}],
+    "name":     "run",
+}"""
+assert pyron.loads(pyron.to_string(aligned_config, align_fields=True)) == aligned_config
+assert pyron.to_string(OptimizerConfig(lr=0.1), align_fields=True) == "OptimizerConfig(\n    lr:       0.1,\n    momentum: 0.9,\n)"
+assert pyron.to_string({"a": 1}, align_fields=True) == pyron.to_string({"a": 1})
+
+indent_config = {"model": {"layers": [64, 32]}, "name": "run"}
+assert pyron.to_string(indent_config, indent="\t") == '{\n\t"model": {\n\t\t"layers": [\n\t\t\t64,\n\t\t\t32,\n\t\t],\n\t},\n\t"name": "run",\n}'
+assert pyron.to_string(indent_config, indent="  ") == '{\n  "model": {\n    "layers": [\n      64,\n      32,\n    ],\n  },\n  "name": "run",\n}'
+assert pyron.loads(pyron.to_string(indent_config, indent="\t")) == indent_config
+assert pyron.to_string(indent_config, indent="  ", comments={"name": "Run name"}).endswith('  // Run name\n  "name": "run",\n}')
+try:
+    pyron.to_string(indent_config, indent="--")
+    assert False
+except ValueError as e:
+    assert "indent" in str(e)
+with tempfile.TemporaryDirectory() as tmp:
+    path = os.path.join(tmp, "indented.ron")
+    pyron.dump(indent_config, path, indent="\t")
+    with open(path) as f:
+        assert f.read() == pyron.to_string(indent_config, indent="\t")